@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env::var;
 use std::fs::{self, File};
 use std::io::{self, Read, Write};
@@ -10,6 +11,33 @@ use serde::Deserialize;
 pub struct Config {
     repo: Repo,
     shortcut: Shortcut,
+
+    #[serde(default)]
+    git: Git,
+
+    #[serde(default)]
+    journal: Journal,
+
+    #[serde(default)]
+    template: Templates,
+
+    #[serde(default)]
+    note: Note,
+
+    #[serde(default)]
+    attachment: Attachment,
+
+    #[serde(default)]
+    theme: Theme,
+
+    #[serde(default)]
+    autosave: Autosave,
+
+    #[serde(default)]
+    search: Search,
+
+    #[serde(default)]
+    semantic: Semantic,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -17,6 +45,24 @@ pub struct Shortcut {
     find: String,
     insert: String,
     quit: String,
+
+    #[serde(default = "Shortcut::default_journal")]
+    journal: String,
+
+    #[serde(default = "Shortcut::default_sequence")]
+    sequence: String,
+
+    #[serde(default = "Shortcut::default_next_tab")]
+    next_tab: String,
+
+    #[serde(default = "Shortcut::default_prev_tab")]
+    prev_tab: String,
+
+    #[serde(default = "Shortcut::default_close_tab")]
+    close_tab: String,
+
+    #[serde(default = "Shortcut::default_list")]
+    list: String,
 }
 
 impl Config {
@@ -27,6 +73,47 @@ impl Config {
     pub fn shortcut(&self) -> &Shortcut {
         &self.shortcut
     }
+
+    pub fn git(&self) -> &Git {
+        &self.git
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn template(&self) -> &Templates {
+        &self.template
+    }
+
+    pub fn note(&self) -> &Note {
+        &self.note
+    }
+
+    pub fn attachment(&self) -> &Attachment {
+        &self.attachment
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn autosave(&self) -> &Autosave {
+        &self.autosave
+    }
+
+    pub fn search(&self) -> &Search {
+        &self.search
+    }
+
+    pub fn semantic(&self) -> &Semantic {
+        &self.semantic
+    }
+
+    /// Replace the theme with the one of a reloaded config.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
 }
 
 impl std::str::FromStr for Config {
@@ -50,6 +137,495 @@ impl Shortcut {
     pub fn quit(&self) -> &str {
         &self.quit
     }
+
+    pub fn journal(&self) -> &str {
+        &self.journal
+    }
+
+    pub fn sequence(&self) -> &str {
+        &self.sequence
+    }
+
+    pub fn next_tab(&self) -> &str {
+        &self.next_tab
+    }
+
+    pub fn prev_tab(&self) -> &str {
+        &self.prev_tab
+    }
+
+    pub fn close_tab(&self) -> &str {
+        &self.close_tab
+    }
+
+    /// Shows or hides the list of notes.
+    pub fn list(&self) -> &str {
+        &self.list
+    }
+
+    fn default_journal() -> String {
+        "<Control>j".to_string()
+    }
+
+    fn default_sequence() -> String {
+        "<Control>e".to_string()
+    }
+
+    fn default_next_tab() -> String {
+        "<Control>Page_Down".to_string()
+    }
+
+    fn default_prev_tab() -> String {
+        "<Control>Page_Up".to_string()
+    }
+
+    fn default_close_tab() -> String {
+        "<Control>w".to_string()
+    }
+
+    fn default_list() -> String {
+        "<Control>l".to_string()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Git {
+    #[serde(default)]
+    enabled: bool,
+    remote: Option<String>,
+    #[serde(default = "Git::default_branch")]
+    branch: String,
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Git {
+            enabled: false,
+            remote: None,
+            branch: Self::default_branch(),
+        }
+    }
+}
+
+impl Git {
+    fn default_branch() -> String {
+        "main".to_string()
+    }
+
+    /// Commit notes into the repo when they are saved.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// URL or path of the remote to pull from and push to.
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Journal {
+    #[serde(default = "Journal::default_dir")]
+    dir: String,
+    template: Option<String>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Journal {
+            dir: Self::default_dir(),
+            template: None,
+        }
+    }
+}
+
+impl Journal {
+    fn default_dir() -> String {
+        "journal".to_string()
+    }
+
+    /// Directory under the repo that daily notes are stored in.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// Name of the template for daily notes.
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Attachment {
+    #[serde(default = "Attachment::default_dir")]
+    dir: String,
+    #[serde(default = "Attachment::default_max_width")]
+    max_width: i32,
+}
+
+impl Default for Attachment {
+    fn default() -> Self {
+        Attachment {
+            dir: Self::default_dir(),
+            max_width: Self::default_max_width(),
+        }
+    }
+}
+
+impl Attachment {
+    fn default_dir() -> String {
+        "assets".to_string()
+    }
+
+    fn default_max_width() -> i32 {
+        600
+    }
+
+    /// Directory under the repo that pasted and dropped files are copied to.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// Images wider than this are scaled down in the editor.
+    pub fn max_width(&self) -> i32 {
+        self.max_width
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Autosave {
+    #[serde(default = "Autosave::default_enabled")]
+    enabled: bool,
+    #[serde(default = "Autosave::default_delay")]
+    delay: u64,
+    #[serde(default = "Autosave::default_drafts")]
+    drafts: String,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        Autosave {
+            enabled: Self::default_enabled(),
+            delay: Self::default_delay(),
+            drafts: Self::default_drafts(),
+        }
+    }
+}
+
+impl Autosave {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_delay() -> u64 {
+        10
+    }
+
+    fn default_drafts() -> String {
+        "drafts".to_string()
+    }
+
+    /// Whether notes are saved once the editing pauses.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Seconds without an edit before the note is saved.
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+
+    /// Directory of the drafts of unsaved notes, relative to the directory
+    /// of the config.
+    pub fn drafts(&self) -> &str {
+        &self.drafts
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Search {
+    #[serde(default = "Search::default_limit")]
+    limit: usize,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search {
+            limit: Self::default_limit(),
+        }
+    }
+}
+
+impl Search {
+    fn default_limit() -> usize {
+        50
+    }
+
+    /// Notes shown at most in the results of a search.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Semantic {
+    #[serde(default = "Semantic::default_enabled")]
+    enabled: bool,
+    #[serde(default = "Semantic::default_model")]
+    model: String,
+}
+
+impl Default for Semantic {
+    fn default() -> Self {
+        Semantic {
+            enabled: Self::default_enabled(),
+            model: Self::default_model(),
+        }
+    }
+}
+
+impl Semantic {
+    fn default_enabled() -> bool {
+        false
+    }
+
+    fn default_model() -> String {
+        "models/all-MiniLM-L6-v2".to_string()
+    }
+
+    /// Whether notes are embedded for semantic search, it needs the app to
+    /// be built with the `semantic` feature.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Directory of the BERT model with `config.json`, `tokenizer.json` and
+    /// `model.safetensors`, relative to the directory of the config.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Theme {
+    #[serde(default = "Theme::default_font")]
+    font: String,
+    #[serde(default = "Theme::default_font_size")]
+    font_size: i32,
+    #[serde(default = "Theme::default_heading_sizes")]
+    heading_sizes: Vec<i32>,
+    #[serde(default)]
+    mode: ThemeMode,
+    #[serde(default = "Theme::default_css")]
+    css: String,
+    #[serde(default)]
+    colors: HashMap<String, String>,
+    #[serde(default)]
+    dark_colors: HashMap<String, String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            font: Self::default_font(),
+            font_size: Self::default_font_size(),
+            heading_sizes: Self::default_heading_sizes(),
+            mode: ThemeMode::default(),
+            css: Self::default_css(),
+            colors: HashMap::new(),
+            dark_colors: HashMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    fn default_font() -> String {
+        "Victor Mono".to_string()
+    }
+
+    fn default_font_size() -> i32 {
+        14
+    }
+
+    fn default_heading_sizes() -> Vec<i32> {
+        vec![30, 28, 24, 22, 20, 18, 16]
+    }
+
+    fn default_css() -> String {
+        "style.css".to_string()
+    }
+
+    /// Font family of the editor.
+    pub fn font(&self) -> &str {
+        &self.font
+    }
+
+    pub fn font_size(&self) -> i32 {
+        self.font_size
+    }
+
+    /// Font size of the headline of the level, from 1.
+    pub fn heading_size(&self, level: usize) -> i32 {
+        level
+            .checked_sub(1)
+            .and_then(|i| self.heading_sizes.get(i))
+            .copied()
+            .unwrap_or(self.font_size)
+    }
+
+    pub fn mode(&self) -> ThemeMode {
+        self.mode
+    }
+
+    /// CSS file that overrides the style of the app, relative to the
+    /// directory of the config.
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Color set for the name, dark colors fall back to the light ones.
+    pub fn color(&self, name: &str, dark: bool) -> Option<&str> {
+        let color = if dark {
+            self.dark_colors.get(name).or_else(|| self.colors.get(name))
+        } else {
+            self.colors.get(name)
+        };
+        color.map(|s| s.as_str())
+    }
+}
+
+/// Whether the editor is light or dark.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    /// Follow the preference of the desktop.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Templates {
+    #[serde(default = "Templates::default_dir")]
+    dir: String,
+    default: Option<String>,
+    #[serde(default)]
+    folders: HashMap<String, String>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            dir: Self::default_dir(),
+            default: None,
+            folders: HashMap::new(),
+        }
+    }
+}
+
+impl Templates {
+    fn default_dir() -> String {
+        "templates".to_string()
+    }
+
+    /// Directory under the repo that templates are stored in.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// Name of the template for new notes in the folder of the repo.
+    pub fn for_folder(&self, folder: &str) -> Option<&str> {
+        self.folders
+            .get(folder)
+            .or(self.default.as_ref())
+            .map(|s| s.as_str())
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Note {
+    #[serde(default = "Note::default_dir")]
+    dir: String,
+    #[serde(default)]
+    id: IdScheme,
+    #[serde(default)]
+    layout: Layout,
+    #[serde(default)]
+    collision: Collision,
+}
+
+/// How ids of new notes are generated.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IdScheme {
+    /// `20230101120000`
+    #[default]
+    Timestamp,
+    /// Luhmann-style `1`, `1a`, `1a1`.
+    Folgezettel,
+    /// `01GNNA1J2CF7YSFG1WHKRVE0ER`
+    Ulid,
+    /// Slugified title like `my-new-idea`.
+    Slug,
+}
+
+/// Sub-directories that new notes are put in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    #[default]
+    Flat,
+    Year,
+    YearMonth,
+}
+
+/// What to do when the id of a new note is already taken.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// Append `-1`, `-2`, ... to the id.
+    #[default]
+    Suffix,
+    Error,
+}
+
+impl Default for Note {
+    fn default() -> Self {
+        Note {
+            dir: Self::default_dir(),
+            id: IdScheme::default(),
+            layout: Layout::default(),
+            collision: Collision::default(),
+        }
+    }
+}
+
+impl Note {
+    fn default_dir() -> String {
+        "notes".to_string()
+    }
+
+    /// Directory under the repo that new notes are stored in.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    pub fn id(&self) -> IdScheme {
+        self.id
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn collision(&self) -> Collision {
+        self.collision
+    }
 }
 
 #[derive(Default)]
@@ -69,6 +645,16 @@ impl ConfigLoader {
         ConfigLoader { home, dir, path }
     }
 
+    /// Directory of the config, `~/.rustybrain`.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of the config file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn load(&self) -> Result<Config, anyhow::Error> {
         self.create_dir()?;
         self.attempt_set_default()?;
@@ -125,6 +711,74 @@ path = "RustyBrain"
 find = "<Control><Shift>f"
 insert = "<Control>i"
 quit = "<Meta>q"
+journal = "<Control>j"
+sequence = "<Control>e"
+next_tab = "<Control>Page_Down"
+prev_tab = "<Control>Page_Up"
+close_tab = "<Control>w"
+list = "<Control>l"
+
+[git]
+enabled = false
+# remote = "git@example.com:me/notes.git"
+branch = "main"
+
+[journal]
+dir = "journal"
+# template = "journal"
+
+[note]
+dir = "notes"
+# timestamp, folgezettel, ulid or slug
+id = "timestamp"
+# flat, year or year-month
+layout = "flat"
+# suffix or error
+collision = "suffix"
+
+[template]
+dir = "templates"
+# default = "note"
+
+[template.folders]
+# journal = "journal"
+
+[attachment]
+dir = "assets"
+max_width = 600
+
+[autosave]
+enabled = true
+# Seconds without an edit before the note is saved
+delay = 10
+# Unsaved edits to restore after a crash, relative to ~/.rustybrain
+drafts = "drafts"
+
+[search]
+# Notes shown at most in the results
+limit = 50
+
+[semantic]
+# Needs a build with the semantic feature
+enabled = false
+# Local BERT model, relative to ~/.rustybrain
+model = "models/all-MiniLM-L6-v2"
+
+[theme]
+font = "Victor Mono"
+font_size = 14
+heading_sizes = [30, 28, 24, 22, 20, 18, 16]
+# system, light or dark
+mode = "system"
+# Overrides the style of the app, relative to ~/.rustybrain
+css = "style.css"
+
+[theme.colors]
+# link = "blue"
+# code-block-background = "lightgray"
+
+[theme.dark_colors]
+# link = "skyblue"
 
 "#;
 
@@ -0,0 +1,128 @@
+//! Drafts of the notes that are edited but not saved, so the edits survive
+//! a crash of the app.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Unsaved title and content of a note.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Draft {
+    zid: String,
+    title: String,
+    content: String,
+}
+
+impl Draft {
+    pub fn new(zid: &str, title: &str, content: &str) -> Self {
+        Draft {
+            zid: zid.to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    pub fn zid(&self) -> &str {
+        &self.zid
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// Directory of the drafts, one file for each note.
+pub struct Drafts {
+    dir: PathBuf,
+}
+
+impl Drafts {
+    pub fn new(dir: &Path) -> Self {
+        Drafts {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Write the draft over the previous one of its note.
+    pub fn write(&self, draft: &Draft) -> Result<(), anyhow::Error> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(draft.zid());
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(draft)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Remove the draft of the note, it is fine if there is none.
+    pub fn remove(&self, zid: &str) -> Result<(), anyhow::Error> {
+        match fs::remove_file(self.path(zid)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drafts left in the directory, newest first.
+    pub fn list(&self) -> Result<Vec<Draft>, anyhow::Error> {
+        if !self.dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut drafts = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            let modified = fs::metadata(&path)?.modified()?;
+            let draft: Draft = serde_json::from_slice(&fs::read(&path)?)?;
+            drafts.push((modified, draft));
+        }
+        drafts.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(drafts.into_iter().map(|(_, d)| d).collect())
+    }
+
+    /// File of the draft, the zid is escaped to a single file name.
+    fn path(&self, zid: &str) -> PathBuf {
+        let mut name = String::new();
+        for b in zid.bytes() {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'.' {
+                name.push(b as char);
+            } else {
+                name.push_str(&format!("_{:02x}", b));
+            }
+        }
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Draft, Drafts};
+
+    #[test]
+    fn test_drafts() {
+        let dir = tempfile::tempdir().unwrap();
+        let drafts = Drafts::new(&dir.path().join("drafts"));
+        assert!(drafts.list().unwrap().is_empty());
+
+        let a = Draft::new("@/notes/a.md", "A", "# A\n");
+        let b = Draft::new("@/b_c.md", "B", "b");
+        drafts.write(&a).unwrap();
+        drafts.write(&b).unwrap();
+        drafts
+            .write(&Draft::new("@/notes/a.md", "A", "# A2\n"))
+            .unwrap();
+
+        let list = drafts.list().unwrap();
+        assert_eq!(list.len(), 2);
+        assert!(list.contains(&b));
+        assert!(list.iter().any(|d| d.content() == "# A2\n"));
+
+        drafts.remove(a.zid()).unwrap();
+        drafts.remove(a.zid()).unwrap();
+        assert_eq!(drafts.list().unwrap(), vec![b]);
+    }
+}
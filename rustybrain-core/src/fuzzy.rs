@@ -0,0 +1,156 @@
+//! Fuzzy matching of a pattern against titles and paths, the characters of
+//! the pattern are found in order and scored by where they are.
+
+/// Score of every matched character.
+const SCORE_MATCH: i64 = 16;
+/// Penalty of the first skipped character between two matched ones.
+const GAP_START: i64 = -3;
+/// Penalty of every other skipped character.
+const GAP_EXTENSION: i64 = -1;
+/// Bonus of a match at the start of a word.
+const BONUS_BOUNDARY: i64 = 8;
+/// Bonus of a match right after the previous one.
+const BONUS_CONSECUTIVE: i64 = 4;
+
+/// Score of a pattern in a text and where its characters are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    score: i64,
+    indices: Vec<usize>,
+}
+
+impl FuzzyMatch {
+    /// Higher is better.
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    /// Char indices of the matched characters in the text.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+/// Best match of the pattern in the text, `None` if the characters are not
+/// all in the text in order.
+///
+/// The match is case-insensitive unless the pattern has an uppercase letter.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let sensitive = pattern.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
+    let chars: Vec<char> = text.chars().collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            indices: vec![],
+        });
+    }
+    if pattern.len() > chars.len() {
+        return None;
+    }
+    let folded: Vec<char> = chars.iter().map(|&c| fold(c)).collect();
+    let bonus: Vec<i64> = (0..chars.len()).map(|j| bonus(&chars, j)).collect();
+
+    // `scores[i][j]` is the best score of the pattern up to `i` with `i`
+    // matched at `j`, `from[i][j]` is where `i - 1` is matched then.
+    let (m, n) = (pattern.len(), chars.len());
+    let mut scores = vec![vec![None; n]; m];
+    let mut from = vec![vec![0; n]; m];
+    for j in 0..n {
+        if folded[j] == pattern[0] {
+            scores[0][j] = Some(SCORE_MATCH + bonus[j] * 2);
+        }
+    }
+    for i in 1..m {
+        // Best score of `i - 1` matched before `j - 1`, with the gap.
+        let mut gap: Option<(i64, usize)> = None;
+        for j in 1..n {
+            if j >= 2 {
+                let extended = gap.map(|(s, k)| (s + GAP_EXTENSION, k));
+                let started =
+                    scores[i - 1][j - 2].map(|s| (s + GAP_START, j - 2));
+                gap = match (extended, started) {
+                    (Some(a), Some(b)) => Some(if b.0 >= a.0 { b } else { a }),
+                    (a, b) => a.or(b),
+                };
+            }
+            if folded[j] != pattern[i] {
+                continue;
+            }
+            let consecutive =
+                scores[i - 1][j - 1].map(|s| (s + BONUS_CONSECUTIVE, j - 1));
+            let best = match (consecutive, gap) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((s, k)) = best {
+                scores[i][j] = Some(s + SCORE_MATCH + bonus[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (end, score) = (0..n)
+        .filter_map(|j| scores[m - 1][j].map(|s| (j, s)))
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    let mut indices = vec![end];
+    let mut j = end;
+    for i in (1..m).rev() {
+        j = from[i][j];
+        indices.push(j);
+    }
+    indices.reverse();
+    Some(FuzzyMatch { score, indices })
+}
+
+/// Bonus of a match at `j`, at the start of a word or a camel case hump.
+fn bonus(chars: &[char], j: usize) -> i64 {
+    let prev = match j.checked_sub(1) {
+        Some(p) => chars[p],
+        None => return BONUS_BOUNDARY,
+    };
+    let c = chars[j];
+    if !prev.is_alphanumeric() && c.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && c.is_uppercase() {
+        BONUS_BOUNDARY - 1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_match;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("xyz", "Rust Book").is_none());
+        assert!(fuzzy_match("ab", "a").is_none());
+        assert_eq!(fuzzy_match("", "a").unwrap().indices(), &[] as &[usize]);
+
+        let m = fuzzy_match("rb", "Rust Book").unwrap();
+        assert_eq!(m.indices(), &[0, 5]);
+
+        // Word starts and runs are preferred to the first occurrence.
+        let m = fuzzy_match("book", "bo bo book").unwrap();
+        assert_eq!(m.indices(), &[6, 7, 8, 9]);
+        let m = fuzzy_match("zk", "notes/zettel-kasten.md").unwrap();
+        assert_eq!(m.indices(), &[6, 13]);
+
+        let a = fuzzy_match("rust", "Rust Book").unwrap();
+        let b = fuzzy_match("rust", "Trust Fund").unwrap();
+        assert!(a.score() > b.score());
+
+        // Smart case.
+        assert!(fuzzy_match("Rb", "rust book").is_none());
+        assert!(fuzzy_match("rb", "RUST BOOK").is_some());
+    }
+}
@@ -0,0 +1,429 @@
+//! Versioning and syncing the repo with git.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, TimeZone};
+use git2::build::CheckoutBuilder;
+use git2::{
+    Commit, ErrorCode, Oid, Patch, RepositoryInitOptions, Signature,
+    StatusOptions,
+};
+
+pub struct Repository {
+    repo: git2::Repository,
+    root: PathBuf,
+}
+
+/// A commit that changed a note.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    id: String,
+    time: DateTime<Local>,
+    message: String,
+}
+
+impl Revision {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn short_id(&self) -> &str {
+        &self.id[..7.min(self.id.len())]
+    }
+
+    pub fn time(&self) -> &DateTime<Local> {
+        &self.time
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Repository {
+    /// Open the git repository at `root`, initialize one on `branch` if not
+    /// exists.
+    pub fn open_or_init(
+        root: &Path,
+        branch: &str,
+    ) -> Result<Self, anyhow::Error> {
+        fs::create_dir_all(root)?;
+        let repo = match git2::Repository::open(root) {
+            Ok(repo) => repo,
+            Err(_) => {
+                let mut opts = RepositoryInitOptions::new();
+                opts.initial_head(branch);
+                git2::Repository::init_opts(root, &opts)?
+            }
+        };
+        Ok(Repository {
+            repo,
+            root: root.to_path_buf(),
+        })
+    }
+
+    /// Commit the file at `path`, nothing happens when it isn't changed.
+    pub fn commit_file(
+        &self,
+        path: &Path,
+        message: &str,
+    ) -> Result<Option<Oid>, anyhow::Error> {
+        let rel = self.relative(path)?;
+        let mut index = self.repo.index()?;
+        if path.exists() {
+            index.add_path(rel)?;
+        } else {
+            index.remove_path(rel)?;
+        }
+        index.write()?;
+        let tree_id = index.write_tree()?;
+        let parent = self.head_commit()?;
+        if let Some(p) = parent.as_ref() {
+            if p.tree_id() == tree_id {
+                return Ok(None);
+            }
+        }
+        let tree = self.repo.find_tree(tree_id)?;
+        let sig = self.signature()?;
+        let parents: Vec<&Commit> = parent.iter().collect();
+        let oid = self.repo.commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            message,
+            &tree,
+            &parents,
+        )?;
+        Ok(Some(oid))
+    }
+
+    /// Commits that changed the file at `path`, newest first.
+    pub fn history(&self, path: &Path) -> Result<Vec<Revision>, anyhow::Error> {
+        let rel = self.relative(path)?;
+        let mut result = vec![];
+        if self.head_commit()?.is_none() {
+            return Ok(result);
+        }
+        let mut walk = self.repo.revwalk()?;
+        walk.push_head()?;
+        walk.set_sorting(git2::Sort::TIME)?;
+        for oid in walk {
+            let commit = self.repo.find_commit(oid?)?;
+            let blob = Self::blob_id(&commit, rel);
+            let parent_blob = match commit.parent(0) {
+                Ok(p) => Self::blob_id(&p, rel),
+                Err(_) => None,
+            };
+            if blob.is_some() && blob != parent_blob {
+                let time = Local
+                    .timestamp_opt(commit.time().seconds(), 0)
+                    .single()
+                    .unwrap_or_else(Local::now);
+                result.push(Revision {
+                    id: commit.id().to_string(),
+                    time,
+                    message: commit.message().unwrap_or("").to_string(),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Content of the file at `path` in the revision.
+    pub fn show(
+        &self,
+        path: &Path,
+        rev: &str,
+    ) -> Result<String, anyhow::Error> {
+        let blob = self.blob(path, rev)?;
+        Ok(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    /// Unified diff from the revision to the file at `path` on disk.
+    pub fn diff(
+        &self,
+        path: &Path,
+        rev: &str,
+    ) -> Result<String, anyhow::Error> {
+        let rel = self.relative(path)?;
+        let old = self.blob(path, rev)?;
+        let new = fs::read(path)?;
+        let mut patch = Patch::from_blob_and_buffer(
+            &old,
+            Some(rel),
+            &new,
+            Some(rel),
+            None,
+        )?;
+        let buf = patch.to_buf()?;
+        Ok(buf.as_str().unwrap_or("").to_string())
+    }
+
+    /// Overwrite the file at `path` with its content in the revision.
+    pub fn restore(&self, path: &Path, rev: &str) -> Result<(), anyhow::Error> {
+        let blob = self.blob(path, rev)?;
+        fs::write(path, blob.content())?;
+        Ok(())
+    }
+
+    /// Fetch `branch` from `remote` and merge it into the local branch.
+    ///
+    /// HEAD has to be on `branch` and the tracked files have to be
+    /// committed, files that are not tracked are kept and the pull fails if
+    /// it would overwrite them.
+    pub fn pull(
+        &self,
+        remote: &str,
+        branch: &str,
+    ) -> Result<(), anyhow::Error> {
+        let refname = format!("refs/heads/{}", branch);
+        let head = self.repo.find_reference("HEAD")?;
+        if head.symbolic_target() != Some(refname.as_str()) {
+            return Err(anyhow::anyhow!("HEAD is not on {}", branch));
+        }
+        let changed = self.changed_files()?;
+        if changed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} files have uncommitted changes, commit them first",
+                changed
+            ));
+        }
+
+        let mut remote = self.repo.remote_anonymous(remote)?;
+        remote.fetch(&[branch], None, None)?;
+        let fetch_head = self.repo.find_reference("FETCH_HEAD")?;
+        let theirs = self.repo.reference_to_annotated_commit(&fetch_head)?;
+        let (analysis, _) = self.repo.merge_analysis(&[&theirs])?;
+        if analysis.is_up_to_date() {
+            return Ok(());
+        }
+
+        let theirs = self.repo.find_commit(theirs.id())?;
+        if analysis.is_unborn() || analysis.is_fast_forward() {
+            // The files are written before the branch moves, so a failed
+            // checkout leaves the repo as it was.
+            self.repo.checkout_tree(
+                theirs.as_object(),
+                Some(CheckoutBuilder::new().safe()),
+            )?;
+            self.repo.reference(
+                &refname,
+                theirs.id(),
+                true,
+                "pull: fast-forward",
+            )?;
+            return Ok(());
+        }
+
+        let ours = self
+            .head_commit()?
+            .ok_or_else(|| anyhow::anyhow!("no local commits"))?;
+        let mut index = self.repo.merge_commits(&ours, &theirs, None)?;
+        if index.has_conflicts() {
+            return Err(anyhow::anyhow!(
+                "Merge {} from remote has conflicts",
+                branch
+            ));
+        }
+        let tree = self.repo.find_tree(index.write_tree_to(&self.repo)?)?;
+        self.repo.checkout_tree(
+            tree.as_object(),
+            Some(CheckoutBuilder::new().safe()),
+        )?;
+        let sig = self.signature()?;
+        let message = format!("Merge {} from remote", branch);
+        self.repo.commit(
+            Some("HEAD"),
+            &sig,
+            &sig,
+            &message,
+            &tree,
+            &[&ours, &theirs],
+        )?;
+        Ok(())
+    }
+
+    /// Push the local `branch` to `remote`.
+    pub fn push(
+        &self,
+        remote: &str,
+        branch: &str,
+    ) -> Result<(), anyhow::Error> {
+        let mut remote = self.repo.remote_anonymous(remote)?;
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+        remote.push(&[refspec.as_str()], None)?;
+        Ok(())
+    }
+
+    /// Number of tracked files that differ from HEAD, in the index or on
+    /// disk.
+    fn changed_files(&self) -> Result<usize, anyhow::Error> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false);
+        Ok(self.repo.statuses(Some(&mut opts))?.len())
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> Result<&'a Path, anyhow::Error> {
+        Ok(path.strip_prefix(&self.root)?)
+    }
+
+    fn head_commit(&self) -> Result<Option<Commit<'_>>, anyhow::Error> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?)),
+            Err(e)
+                if e.code() == ErrorCode::UnbornBranch
+                    || e.code() == ErrorCode::NotFound =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn blob(
+        &self,
+        path: &Path,
+        rev: &str,
+    ) -> Result<git2::Blob<'_>, anyhow::Error> {
+        let rel = self.relative(path)?;
+        let commit = self.repo.revparse_single(rev)?.peel_to_commit()?;
+        let entry = commit.tree()?.get_path(rel)?;
+        Ok(entry.to_object(&self.repo)?.peel_to_blob()?)
+    }
+
+    fn blob_id(commit: &Commit, rel: &Path) -> Option<Oid> {
+        commit
+            .tree()
+            .ok()
+            .and_then(|t| t.get_path(rel).ok())
+            .map(|e| e.id())
+    }
+
+    fn signature(&self) -> Result<Signature<'static>, anyhow::Error> {
+        match self.repo.signature() {
+            Ok(sig) => Ok(sig),
+            Err(_) => Ok(Signature::now("RustyBrain", "rustybrain@localhost")?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Repository;
+
+    #[test]
+    fn test_commit_history_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::open_or_init(dir.path(), "main").unwrap();
+        let path = dir.path().join("note.md");
+
+        fs::write(&path, "first\n").unwrap();
+        assert!(repo.commit_file(&path, "Create note").unwrap().is_some());
+        fs::write(&path, "second\n").unwrap();
+        repo.commit_file(&path, "Update note").unwrap();
+        assert!(repo.commit_file(&path, "Nothing").unwrap().is_none());
+
+        let history = repo.history(&path).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].message(), "Update note");
+
+        let first = history[1].id().to_string();
+        let diff = repo.diff(&path, &first).unwrap();
+        assert!(diff.contains("-first"));
+        assert!(diff.contains("+second"));
+
+        repo.restore(&path, &first).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\n");
+    }
+
+    #[test]
+    fn test_push_and_pull_with_bare_remote() {
+        let remote = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(remote.path()).unwrap();
+        let url = remote.path().to_str().unwrap();
+
+        let a = tempfile::tempdir().unwrap();
+        let repo_a = Repository::open_or_init(a.path(), "main").unwrap();
+        fs::write(a.path().join("note.md"), "from a\n").unwrap();
+        repo_a.commit_file(&a.path().join("note.md"), "A").unwrap();
+        repo_a.push(url, "main").unwrap();
+
+        let b = tempfile::tempdir().unwrap();
+        let repo_b = Repository::open_or_init(b.path(), "main").unwrap();
+        repo_b.pull(url, "main").unwrap();
+        let content = fs::read_to_string(b.path().join("note.md")).unwrap();
+        assert_eq!(content, "from a\n");
+
+        fs::write(b.path().join("other.md"), "from b\n").unwrap();
+        repo_b.commit_file(&b.path().join("other.md"), "B").unwrap();
+        repo_b.push(url, "main").unwrap();
+
+        repo_a.pull(url, "main").unwrap();
+        assert!(a.path().join("other.md").exists());
+    }
+
+    #[test]
+    fn test_pull_keeps_uncommitted_changes() {
+        let remote = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(remote.path()).unwrap();
+        let url = remote.path().to_str().unwrap();
+
+        let a = tempfile::tempdir().unwrap();
+        let repo_a = Repository::open_or_init(a.path(), "main").unwrap();
+        let note_a = a.path().join("note.md");
+        fs::write(&note_a, "first\n").unwrap();
+        repo_a.commit_file(&note_a, "A").unwrap();
+        repo_a.push(url, "main").unwrap();
+
+        let b = tempfile::tempdir().unwrap();
+        let repo_b = Repository::open_or_init(b.path(), "main").unwrap();
+        repo_b.pull(url, "main").unwrap();
+
+        fs::write(&note_a, "second\n").unwrap();
+        repo_a.commit_file(&note_a, "A again").unwrap();
+        fs::write(a.path().join("new.md"), "from a\n").unwrap();
+        repo_a.commit_file(&a.path().join("new.md"), "New").unwrap();
+        repo_a.push(url, "main").unwrap();
+
+        // A tracked file edited outside the app.
+        let note_b = b.path().join("note.md");
+        fs::write(&note_b, "edited in b\n").unwrap();
+        assert!(repo_b.pull(url, "main").is_err());
+        assert_eq!(fs::read_to_string(&note_b).unwrap(), "edited in b\n");
+        // Committed and reverted, so the pull below is a merge.
+        repo_b.commit_file(&note_b, "B").unwrap();
+        fs::write(&note_b, "first\n").unwrap();
+        repo_b.commit_file(&note_b, "B again").unwrap();
+
+        // A file that is not tracked yet and would be overwritten.
+        let new_b = b.path().join("new.md");
+        fs::write(&new_b, "from b\n").unwrap();
+        assert!(repo_b.pull(url, "main").is_err());
+        assert_eq!(fs::read_to_string(&new_b).unwrap(), "from b\n");
+
+        fs::remove_file(&new_b).unwrap();
+        repo_b.pull(url, "main").unwrap();
+        assert_eq!(fs::read_to_string(&note_b).unwrap(), "second\n");
+        assert_eq!(fs::read_to_string(&new_b).unwrap(), "from a\n");
+    }
+
+    #[test]
+    fn test_pull_only_on_the_branch() {
+        let remote = tempfile::tempdir().unwrap();
+        git2::Repository::init_bare(remote.path()).unwrap();
+        let url = remote.path().to_str().unwrap();
+
+        let a = tempfile::tempdir().unwrap();
+        let repo_a = Repository::open_or_init(a.path(), "main").unwrap();
+        fs::write(a.path().join("note.md"), "from a\n").unwrap();
+        repo_a.commit_file(&a.path().join("note.md"), "A").unwrap();
+        repo_a.push(url, "main").unwrap();
+
+        let b = tempfile::tempdir().unwrap();
+        let repo_b = Repository::open_or_init(b.path(), "other").unwrap();
+        assert!(repo_b.pull(url, "main").is_err());
+        assert!(!b.path().join("note.md").exists());
+    }
+}
@@ -0,0 +1,139 @@
+//! Syntax highlighting of code in fenced code blocks by tree-sitter grammars.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use tree_sitter_highlight::{
+    HighlightConfiguration, HighlightEvent, Highlighter,
+};
+
+/// Names of the highlights, a token is highlighted by the longest name that
+/// its capture name starts with.
+pub const HIGHLIGHT_NAMES: &[&str] = &[
+    "attribute",
+    "comment",
+    "constant",
+    "constant.builtin",
+    "function",
+    "function.macro",
+    "keyword",
+    "number",
+    "operator",
+    "property",
+    "string",
+    "type",
+    "type.builtin",
+    "variable.builtin",
+];
+
+type Configs = HashMap<String, Option<Rc<HighlightConfiguration>>>;
+
+thread_local! {
+    static CONFIGS: RefCell<Configs> = RefCell::new(HashMap::new());
+}
+
+/// Byte ranges of the highlighted tokens in the code with their highlight
+/// names, the language is the info string of the fence such as `rust`.
+///
+/// Nothing is highlighted for an unknown language.
+pub fn highlight(
+    lang: &str,
+    code: &str,
+) -> Result<Vec<(Range<usize>, &'static str)>, anyhow::Error> {
+    let config = match config(lang) {
+        Some(config) => config,
+        None => return Ok(vec![]),
+    };
+    let mut highlighter = Highlighter::new();
+    let events = highlighter
+        .highlight(&config, code.as_bytes(), None, |_| None)
+        .map_err(to_error)?;
+    let mut stack = vec![];
+    let mut tokens = vec![];
+    for event in events {
+        match event.map_err(to_error)? {
+            HighlightEvent::HighlightStart(h) => stack.push(h.0),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                if let Some(h) = stack.last() {
+                    tokens.push((start..end, HIGHLIGHT_NAMES[*h]));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Cached configuration of the language, queries are compiled once.
+fn config(lang: &str) -> Option<Rc<HighlightConfiguration>> {
+    let lang = lang.trim().to_lowercase();
+    CONFIGS.with(|configs| {
+        configs
+            .borrow_mut()
+            .entry(lang.clone())
+            .or_insert_with(|| new_config(&lang).map(Rc::new))
+            .clone()
+    })
+}
+
+fn new_config(lang: &str) -> Option<HighlightConfiguration> {
+    let (language, highlights, injections, locals) = match lang {
+        "rust" | "rs" => (
+            tree_sitter_rust::language(),
+            tree_sitter_rust::HIGHLIGHT_QUERY,
+            "",
+            "",
+        ),
+        "python" | "py" => (
+            tree_sitter_python::language(),
+            tree_sitter_python::HIGHLIGHT_QUERY,
+            "",
+            "",
+        ),
+        "javascript" | "js" => (
+            tree_sitter_javascript::language(),
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+            tree_sitter_javascript::INJECTION_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ),
+        _ => return None,
+    };
+    let mut config =
+        HighlightConfiguration::new(language, highlights, injections, locals)
+            .ok()?;
+    let names: Vec<String> =
+        HIGHLIGHT_NAMES.iter().map(|n| n.to_string()).collect();
+    config.configure(&names);
+    Some(config)
+}
+
+/// Errors of the highlighter are not std errors.
+fn to_error(e: tree_sitter_highlight::Error) -> anyhow::Error {
+    anyhow::anyhow!("highlight failed: {:?}", e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::highlight;
+
+    #[test]
+    fn test_highlight() {
+        let code = "fn main() {\n    // hi\n    let s = \"中文\";\n}\n";
+        let tokens = highlight("rust", code).unwrap();
+        let name_of = |text: &str| {
+            let start = code.find(text).unwrap();
+            tokens
+                .iter()
+                .find(|(r, _)| r.start == start)
+                .map(|(_, name)| *name)
+        };
+        assert_eq!(name_of("fn"), Some("keyword"));
+        assert_eq!(name_of("// hi"), Some("comment"));
+        assert_eq!(name_of("\"中文\""), Some("string"));
+
+        assert!(highlight("unknown", code).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Generating ids of notes.
+use std::cmp::Ordering;
+
+use chrono::Local;
+
+pub fn timestamp() -> String {
+    Local::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+pub fn ulid() -> String {
+    ulid::Ulid::new().to_string()
+}
+
+/// Lowercase the title and join words with `-`, non-ASCII letters are kept.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        return "note".to_string();
+    }
+    slug.to_string()
+}
+
+/// Segment of a Luhmann-style folgezettel id, `1a2b` is `1`, `a`, `2`, `b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Number(u64),
+    Letters(String),
+}
+
+fn segments(id: &str) -> Option<Vec<Segment>> {
+    let mut result = vec![];
+    let mut chars = id.chars().peekable();
+    while let Some(c) = chars.peek().copied() {
+        if c.is_ascii_digit() {
+            let mut n = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                n.push(*d);
+                chars.next();
+            }
+            if n.len() > 6 {
+                return None;
+            }
+            result.push(Segment::Number(n.parse().ok()?));
+        } else if c.is_ascii_lowercase() {
+            let mut s = String::new();
+            while let Some(l) = chars.peek().filter(|l| l.is_ascii_lowercase())
+            {
+                s.push(*l);
+                chars.next();
+            }
+            result.push(Segment::Letters(s));
+        } else {
+            return None;
+        }
+    }
+    match result.first() {
+        Some(Segment::Number(_)) => Some(result),
+        _ => None,
+    }
+}
+
+fn join(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|s| match s {
+            Segment::Number(n) => n.to_string(),
+            Segment::Letters(l) => l.clone(),
+        })
+        .collect()
+}
+
+/// `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn next_letters(s: &str) -> String {
+    let mut chars: Vec<u8> = s.bytes().collect();
+    for c in chars.iter_mut().rev() {
+        if *c == b'z' {
+            *c = b'a';
+        } else {
+            *c += 1;
+            return String::from_utf8(chars).unwrap();
+        }
+    }
+    format!("a{}", String::from_utf8(chars).unwrap())
+}
+
+/// Numbers are limited to 6 digits to not confuse with timestamp ids.
+pub fn is_folgezettel(id: &str) -> bool {
+    segments(id).is_some()
+}
+
+/// Parent id of a folgezettel id, `1a2` is the child of `1a`.
+pub fn folgezettel_parent(id: &str) -> Option<String> {
+    let mut segs = segments(id)?;
+    segs.pop();
+    if segs.is_empty() {
+        return None;
+    }
+    Some(join(&segs))
+}
+
+/// First child of the folgezettel id, `1` to `1a` and `1a` to `1a1`.
+pub fn folgezettel_first_child(id: &str) -> Option<String> {
+    let mut segs = segments(id)?;
+    match segs.last()? {
+        Segment::Number(_) => segs.push(Segment::Letters("a".to_string())),
+        Segment::Letters(_) => segs.push(Segment::Number(1)),
+    }
+    Some(join(&segs))
+}
+
+/// Next sibling of the folgezettel id, `1a` to `1b` and `1a9` to `1a10`.
+pub fn folgezettel_next_sibling(id: &str) -> Option<String> {
+    let mut segs = segments(id)?;
+    let last = match segs.pop()? {
+        Segment::Number(n) => Segment::Number(n + 1),
+        Segment::Letters(l) => Segment::Letters(next_letters(&l)),
+    };
+    segs.push(last);
+    Some(join(&segs))
+}
+
+/// Next free top-level id or child id of `parent` among `existing` ids.
+pub fn folgezettel_next<'a, I>(parent: Option<&str>, existing: I) -> String
+where
+    I: Iterator<Item = &'a str>,
+{
+    let last = existing
+        .filter(|id| is_folgezettel(id))
+        .filter(|id| folgezettel_parent(id).as_deref() == parent)
+        .max_by(|a, b| folgezettel_cmp(a, b));
+    match (last, parent) {
+        (Some(last), _) => folgezettel_next_sibling(last).unwrap(),
+        (None, Some(parent)) => folgezettel_first_child(parent)
+            .unwrap_or_else(|| format!("{}a", parent)),
+        (None, None) => "1".to_string(),
+    }
+}
+
+/// Order folgezettel ids in the sequence, `1a9` comes before `1a10`.
+pub fn folgezettel_cmp(a: &str, b: &str) -> Ordering {
+    match (segments(a), segments(b)) {
+        (Some(a), Some(b)) => {
+            for (x, y) in a.iter().zip(b.iter()) {
+                let ord = match (x, y) {
+                    (Segment::Number(x), Segment::Number(y)) => x.cmp(y),
+                    (Segment::Letters(x), Segment::Letters(y)) => {
+                        x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                    }
+                    (Segment::Number(_), _) => Ordering::Less,
+                    (_, Segment::Number(_)) => Ordering::Greater,
+                };
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Rust 中文 notes "), "rust-中文-notes");
+        assert_eq!(slugify("!!!"), "note");
+    }
+
+    #[test]
+    fn test_folgezettel() {
+        assert_eq!(folgezettel_parent("1a2b").as_deref(), Some("1a2"));
+        assert_eq!(folgezettel_parent("1"), None);
+        assert_eq!(folgezettel_first_child("1").as_deref(), Some("1a"));
+        assert_eq!(folgezettel_first_child("1a").as_deref(), Some("1a1"));
+        assert_eq!(folgezettel_next_sibling("1z").as_deref(), Some("1aa"));
+        assert_eq!(folgezettel_next_sibling("1a9").as_deref(), Some("1a10"));
+        assert!(!is_folgezettel("a1"));
+        assert!(!is_folgezettel("20230101-note"));
+        assert!(!is_folgezettel("20230101120000"));
+
+        let ids = ["1", "2", "1a", "1b", "1a1", "10"];
+        assert_eq!(folgezettel_next(None, ids.iter().copied()), "11");
+        assert_eq!(folgezettel_next(Some("1"), ids.iter().copied()), "1c");
+        assert_eq!(folgezettel_next(Some("1b"), ids.iter().copied()), "1b1");
+        assert_eq!(folgezettel_cmp("1a9", "1a10"), Ordering::Less);
+        assert_eq!(folgezettel_cmp("1", "1a"), Ordering::Less);
+    }
+}
@@ -0,0 +1,234 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use super::{Attachment, Importer, Page, ASSETS_DIR};
+
+/// Importer for a Logseq graph.
+///
+/// Reads markdown pages from `pages/` and `journals/`, turns `key:: value`
+/// page properties into the header and keeps the outline blocks as nested
+/// markdown lists.
+pub struct Logseq {
+    graph: PathBuf,
+}
+
+impl Logseq {
+    pub fn new(graph: &Path) -> Self {
+        Logseq {
+            graph: graph.to_path_buf(),
+        }
+    }
+
+    fn markdowns(dir: &Path) -> Result<Vec<PathBuf>, anyhow::Error> {
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut result = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "md").unwrap_or(false) {
+                result.push(path);
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    fn stem(path: &Path) -> Result<String, anyhow::Error> {
+        Ok(path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid file name"))?
+            .to_string())
+    }
+
+    fn page(path: &Path) -> Result<Page, anyhow::Error> {
+        // Namespaced pages `a/b` are stored as `a___b.md` or `a%2Fb.md`.
+        let name = Self::stem(path)?.replace("___", "/").replace("%2F", "/");
+        let content = fs::read_to_string(path)?;
+        let mut page = Page {
+            title: name.clone(),
+            name,
+            ..Default::default()
+        };
+        Self::fill(&mut page, &content);
+        Ok(page)
+    }
+
+    fn journal(path: &Path) -> Result<Page, anyhow::Error> {
+        let stem = Self::stem(path)?;
+        let date = NaiveDate::parse_from_str(&stem, "%Y_%m_%d")?;
+        let content = fs::read_to_string(path)?;
+        let mut page = Page {
+            name: date.format("%Y-%m-%d").to_string(),
+            title: date.format("%Y-%m-%d").to_string(),
+            date: Some(date.format("%Y-%m-%d").to_string()),
+            aliases: vec![Self::journal_title(&date)],
+            ..Default::default()
+        };
+        Self::fill(&mut page, &content);
+        Ok(page)
+    }
+
+    /// Default journal title of Logseq, like `Jan 15th, 2023`.
+    fn journal_title(date: &NaiveDate) -> String {
+        let day = date.format("%-d").to_string();
+        let suffix = match day.as_str() {
+            "1" | "21" | "31" => "st",
+            "2" | "22" => "nd",
+            "3" | "23" => "rd",
+            _ => "th",
+        };
+        format!(
+            "{} {}{}, {}",
+            date.format("%b"),
+            day,
+            suffix,
+            date.format("%Y")
+        )
+    }
+
+    fn fill(page: &mut Page, content: &str) {
+        let mut body = String::new();
+        let mut in_properties = true;
+        for line in content.lines() {
+            if in_properties {
+                if let Some((key, value)) = Self::property(line) {
+                    match key {
+                        "title" => page.title = value.to_string(),
+                        "tags" => page.tags = Self::list(value),
+                        "alias" => page.aliases.extend(Self::list(value)),
+                        _ => {}
+                    }
+                    continue;
+                }
+                in_properties = false;
+            }
+
+            // Block properties like `id:: ...` and `collapsed:: true`.
+            if Self::property(line.trim_start_matches(['\t', ' ', '-']))
+                .is_some()
+            {
+                continue;
+            }
+
+            let indent = line.chars().take_while(|c| *c == '\t').count();
+            body.push_str(&"  ".repeat(indent));
+            body.push_str(
+                &line[indent..]
+                    .replace("../assets/", &format!("{}/", ASSETS_DIR)),
+            );
+            body.push('\n');
+        }
+        page.body = body;
+    }
+
+    fn property(line: &str) -> Option<(&str, &str)> {
+        let (key, value) = line.split_once(":: ")?;
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return None;
+        }
+        Some((key, value.trim()))
+    }
+
+    fn list(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(|s| {
+                s.trim()
+                    .trim_start_matches('#')
+                    .trim_start_matches("[[")
+                    .trim_end_matches("]]")
+                    .to_string()
+            })
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+}
+
+impl Importer for Logseq {
+    fn pages(&self) -> Result<Vec<Page>, anyhow::Error> {
+        let mut pages = vec![];
+        for path in Self::markdowns(&self.graph.join("pages"))? {
+            pages.push(Self::page(&path)?);
+        }
+        for path in Self::markdowns(&self.graph.join("journals"))? {
+            pages.push(Self::journal(&path)?);
+        }
+        Ok(pages)
+    }
+
+    fn attachments(&self) -> Result<Vec<Attachment>, anyhow::Error> {
+        let dir = self.graph.join(ASSETS_DIR);
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut result = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let (true, Some(name)) = (path.is_file(), path.file_name()) {
+                let name = PathBuf::from(name);
+                result.push(Attachment { path, name });
+            }
+        }
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{Importer, Logseq};
+
+    #[test]
+    fn test_logseq() {
+        let graph = tempfile::tempdir().unwrap();
+        let dir = graph.path();
+        for sub in ["pages", "journals", "assets"] {
+            fs::create_dir(dir.join(sub)).unwrap();
+        }
+        fs::write(
+            dir.join("pages/lang___Rust.md"),
+            "title:: The Rust Language\ntags:: [[lang]], #systems\n\
+             alias:: rustlang\n\n\
+             - Fast\n\tid:: 64a1\n\t- and safe\n\
+             - ![ferris](../assets/ferris.png)\n",
+        )
+        .unwrap();
+        fs::write(dir.join("journals/2023_01_02.md"), "- Read [[Rust]]\n")
+            .unwrap();
+        fs::write(dir.join("journals/notes.md"), "- Not a date\n").unwrap();
+        fs::write(dir.join("assets/ferris.png"), "png").unwrap();
+
+        let importer = Logseq::new(dir);
+        assert!(importer.pages().is_err());
+        fs::remove_file(dir.join("journals/notes.md")).unwrap();
+        let pages = importer.pages().unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let rust = &pages[0];
+        assert_eq!(rust.name, "lang/Rust");
+        assert_eq!(rust.title, "The Rust Language");
+        assert_eq!(rust.tags, ["lang", "systems"]);
+        assert_eq!(rust.aliases, ["rustlang"]);
+        assert_eq!(
+            rust.body,
+            "\n- Fast\n  - and safe\n- ![ferris](assets/ferris.png)\n"
+        );
+
+        let journal = &pages[1];
+        assert_eq!(journal.name, "2023-01-02");
+        assert_eq!(journal.date.as_deref(), Some("2023-01-02"));
+        assert_eq!(journal.aliases, ["Jan 2nd, 2023"]);
+        assert_eq!(journal.body, "- Read [[Rust]]\n");
+
+        let attachments = importer.attachments().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].name, PathBuf::from("ferris.png"));
+    }
+}
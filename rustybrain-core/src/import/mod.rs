@@ -0,0 +1,343 @@
+//! Import notes from other tools into the slip-box.
+mod logseq;
+mod obsidian;
+mod roam;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::value::Datetime;
+
+use crate::kasten::Kasten;
+
+pub use logseq::Logseq;
+pub use obsidian::Obsidian;
+pub use roam::Roam;
+
+/// Directory under the repo that attachments copied into.
+pub const ASSETS_DIR: &str = "assets";
+
+/// Extensions of the embeds that are shown as images, the other embeds are
+/// links.
+const IMAGE_EXTENSIONS: &[&str] =
+    &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+/// A page read from other tools before converting into zettel.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    /// Name of the page that used by links in the source tool.
+    pub name: String,
+    /// Other names that links may refer to this page.
+    pub aliases: Vec<String>,
+    pub title: String,
+    /// Date in `YYYY-MM-DD` format.
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+    pub body: String,
+}
+
+/// A file copied into the assets directory.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub path: PathBuf,
+    /// Path relative to the assets directory, the one in the source tool
+    /// so that files of the same name don't overwrite each other.
+    pub name: PathBuf,
+}
+
+pub trait Importer {
+    fn pages(&self) -> Result<Vec<Page>, anyhow::Error>;
+
+    fn attachments(&self) -> Result<Vec<Attachment>, anyhow::Error> {
+        Ok(vec![])
+    }
+}
+
+/// Mapping from names in the source tool to zids in the slip-box.
+#[derive(Debug, Default)]
+pub struct Report {
+    entries: Vec<ReportEntry>,
+    unresolved: Vec<(String, String)>,
+    attachments: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    source: String,
+    zid: String,
+    title: String,
+}
+
+impl ReportEntry {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn zid(&self) -> &str {
+        &self.zid
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+impl Report {
+    pub fn entries(&self) -> &[ReportEntry] {
+        &self.entries
+    }
+
+    /// Links that not point to any imported page, as `(zid, link)`.
+    pub fn unresolved(&self) -> &[(String, String)] {
+        &self.unresolved
+    }
+
+    pub fn attachments(&self) -> usize {
+        self.attachments
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in self.entries.iter() {
+            writeln!(f, "{}\t{}", entry.source, entry.zid)?;
+        }
+        for (zid, link) in self.unresolved.iter() {
+            writeln!(f, "{}: unresolved link [[{}]]", zid, link)?;
+        }
+        write!(
+            f,
+            "Imported {} notes and {} attachments",
+            self.entries.len(),
+            self.attachments
+        )
+    }
+}
+
+/// Import all pages and attachments from the importer into the kasten.
+pub fn import(
+    kasten: &mut Kasten,
+    importer: &dyn Importer,
+) -> Result<Report, anyhow::Error> {
+    let mut report = Report::default();
+    let assets = Path::new(&kasten.repo_path()).join(ASSETS_DIR);
+    // Embeds refer to attachments by the path or only by the file name.
+    let mut files = HashMap::new();
+    for attachment in importer.attachments()? {
+        let dest = assets.join(&attachment.name);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&attachment.path, &dest)?;
+        let name = attachment.name.to_string_lossy().replace('\\', "/");
+        if let Some(file_name) = attachment.name.file_name() {
+            files
+                .entry(file_name.to_string_lossy().to_lowercase())
+                .or_insert_with(|| name.clone());
+        }
+        files.insert(name.to_lowercase(), name);
+        report.attachments += 1;
+    }
+
+    let pages = importer.pages()?;
+    let mut names = HashMap::new();
+    let mut zettels = vec![];
+    for page in pages.iter() {
+        let z = kasten.create(&page.title)?;
+        let zid = z.borrow().zid().to_string();
+        names.insert(page.name.to_lowercase(), zid.clone());
+        for alias in page.aliases.iter() {
+            names.insert(alias.to_lowercase(), zid.clone());
+        }
+        report.entries.push(ReportEntry {
+            source: page.name.clone(),
+            zid,
+            title: page.title.clone(),
+        });
+        zettels.push(z);
+    }
+
+    for (page, z) in pages.iter().zip(zettels.iter()) {
+        let mut unresolved = vec![];
+        let body = convert_wikilinks(
+            &page.body,
+            &Resolver {
+                note: &|name| names.get(&name.to_lowercase()).cloned(),
+                file: &|name| files.get(&name.to_lowercase()).cloned(),
+            },
+            &mut unresolved,
+        );
+        {
+            let mut z = z.borrow_mut();
+            z.set_content(&body)?;
+            z.set_tags(page.tags.clone());
+            z.set_aliases(page.aliases.clone());
+            if let Some(date) = page.date.as_ref() {
+                if let Ok(date) = Datetime::from_str(date) {
+                    z.set_date(date);
+                }
+            }
+        }
+        kasten.save(&z.borrow())?;
+        let zid = z.borrow().zid().to_string();
+        for link in unresolved {
+            report.unresolved.push((zid.clone(), link));
+        }
+    }
+    Ok(report)
+}
+
+/// Zids of the pages and paths of the attachments by their names in the
+/// source tool.
+pub(crate) struct Resolver<'a> {
+    note: &'a dyn Fn(&str) -> Option<String>,
+    /// Path under the assets directory.
+    file: &'a dyn Fn(&str) -> Option<String>,
+}
+
+/// Convert `[[Name]]`, `[[Name|Label]]` and `![[file]]` into markdown links.
+///
+/// Embedded images become images, other embedded files and notes become
+/// links, since a note can't be transcluded. Links that can't be resolved
+/// are left untouched and collected into `unresolved`.
+pub(crate) fn convert_wikilinks(
+    body: &str,
+    resolver: &Resolver,
+    unresolved: &mut Vec<String>,
+) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let embed = rest[..start].ends_with('!');
+        let inner = &rest[start + 2..end];
+        let (target, label) = match inner.split_once('|') {
+            Some((target, label)) => (target.trim(), label.trim()),
+            None => (inner.trim(), inner.trim()),
+        };
+        let target = target.split('#').next().unwrap_or(target);
+
+        let file = (resolver.file)(target);
+        let image = Path::new(target)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+            .unwrap_or(false);
+        let before = if embed {
+            &rest[..start - 1]
+        } else {
+            &rest[..start]
+        };
+
+        if embed && image {
+            let path = file.unwrap_or_else(|| target.to_string());
+            out.push_str(before);
+            out.push_str(&format!("![{}]({}/{})", label, ASSETS_DIR, path));
+        } else if let Some(zid) = (resolver.note)(target) {
+            out.push_str(before);
+            out.push_str(&format!("[{}]({})", label, zid));
+        } else if let Some(path) = file {
+            out.push_str(before);
+            out.push_str(&format!("[{}]({}/{})", label, ASSETS_DIR, path));
+        } else {
+            unresolved.push(target.to_string());
+            out.push_str(&rest[..end + 2]);
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{convert_wikilinks, import, Obsidian, Resolver};
+    use crate::kasten::temp_kasten;
+
+    #[test]
+    fn test_convert_wikilinks() {
+        let note = |name: &str| match name {
+            "Rust" => Some("@/notes/1.md".to_string()),
+            _ => None,
+        };
+        let file = |name: &str| match name {
+            "logo.png" => Some("img/logo.png".to_string()),
+            "paper.pdf" => Some("docs/paper.pdf".to_string()),
+            _ => None,
+        };
+        let resolver = Resolver {
+            note: &note,
+            file: &file,
+        };
+        let mut unresolved = vec![];
+        let body = "See [[Rust|the language]], [[Rust#Traits]] and [[Go]].\n\
+                    ![[logo.png]] ![[icon.gif]] ![[paper.pdf]]\n\
+                    ![[Rust]] ![[Go]]";
+        let out = convert_wikilinks(body, &resolver, &mut unresolved);
+        assert_eq!(
+            out,
+            "See [the language](@/notes/1.md), [Rust#Traits](@/notes/1.md) \
+             and [[Go]].\n![logo.png](assets/img/logo.png) \
+             ![icon.gif](assets/icon.gif) [paper.pdf](assets/docs/paper.pdf)\n\
+             [Rust](@/notes/1.md) ![[Go]]"
+        );
+        assert_eq!(unresolved, vec!["Go".to_string(), "Go".to_string()]);
+    }
+
+    #[test]
+    fn test_import() {
+        let vault = tempfile::tempdir().unwrap();
+        for (dir, content) in [("a", "a"), ("b", "b")] {
+            fs::create_dir(vault.path().join(dir)).unwrap();
+            fs::write(vault.path().join(dir).join("logo.png"), content)
+                .unwrap();
+        }
+        fs::write(
+            vault.path().join("Rust.md"),
+            "![[b/logo.png]] ![[Go]] [[Missing]]\n",
+        )
+        .unwrap();
+        fs::write(vault.path().join("Go.md"), "[[Rust]]\n").unwrap();
+
+        let (dir, mut kasten) = temp_kasten("[git]\nenabled = true\n");
+        let report = import(&mut kasten, &Obsidian::new(vault.path())).unwrap();
+        assert_eq!(report.entries().len(), 2);
+        assert_eq!(report.attachments(), 2);
+        let assets = dir.path().join("assets");
+        for name in ["a", "b"] {
+            let path = assets.join(name).join("logo.png");
+            assert_eq!(fs::read_to_string(path).unwrap(), name);
+        }
+
+        let zid = |name: &str| {
+            let entry = report
+                .entries()
+                .iter()
+                .find(|e| e.source() == name)
+                .unwrap();
+            entry.zid().to_string()
+        };
+        let rust = kasten.get(&zid("Rust")).unwrap();
+        assert_eq!(
+            rust.borrow().content().trim(),
+            format!(
+                "![b/logo.png](assets/b/logo.png) [Go]({}) [[Missing]]",
+                zid("Go")
+            )
+        );
+        assert_eq!(report.unresolved(), [(zid("Rust"), "Missing".to_string())]);
+        // Saved by the kasten, with the backlinks and a commit.
+        let backlinks = kasten.iter_backlinks(&rust.borrow());
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].borrow().zid(), zid("Go"));
+        assert_eq!(kasten.history(&rust.borrow()).unwrap().len(), 2);
+    }
+}
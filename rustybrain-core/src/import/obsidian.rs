@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+
+use super::{Attachment, Importer, Page};
+use crate::md::split_front_matter;
+
+/// Importer for an Obsidian vault.
+///
+/// Markdown files become pages named by their file stem, YAML front-matter
+/// provides title, date, tags and aliases. Every other file is an attachment.
+pub struct Obsidian {
+    vault: PathBuf,
+}
+
+impl Obsidian {
+    pub fn new(vault: &Path) -> Self {
+        Obsidian {
+            vault: vault.to_path_buf(),
+        }
+    }
+
+    fn scan(&self) -> Result<Vec<PathBuf>, anyhow::Error> {
+        let mut dirs = vec![self.vault.clone()];
+        let mut result = vec![];
+        while let Some(cur) = dirs.pop() {
+            for entry in fs::read_dir(cur)? {
+                let path = entry?.path();
+                let hidden = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with('.'))
+                    .unwrap_or(false);
+                if hidden {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    result.push(path);
+                }
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    fn is_markdown(path: &Path) -> bool {
+        path.extension().map(|e| e == "md").unwrap_or(false)
+    }
+
+    fn page(path: &Path) -> Result<Page, anyhow::Error> {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid file name"))?
+            .to_string();
+        let content = fs::read_to_string(path)?;
+        let (front, body) = split_front_matter(&content, "---");
+
+        let mut page = Page {
+            title: name.clone(),
+            name,
+            body: body.to_string(),
+            ..Default::default()
+        };
+        if let Some(front) = front {
+            let value: Value = serde_yaml::from_str(front)?;
+            if let Some(title) = value.get("title").and_then(|v| v.as_str()) {
+                page.title = title.to_string();
+            }
+            page.date = value
+                .get("date")
+                .or_else(|| value.get("created"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.chars().take(10).collect());
+            page.tags = Self::strings(value.get("tags"));
+            page.aliases = Self::strings(value.get("aliases"));
+        }
+        Ok(page)
+    }
+
+    /// Front-matter lists may be a YAML sequence or a comma separated string.
+    fn strings(value: Option<&Value>) -> Vec<String> {
+        match value {
+            Some(Value::Sequence(seq)) => seq
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.trim_start_matches('#').to_string())
+                .collect(),
+            Some(Value::String(s)) => s
+                .split(',')
+                .map(|s| s.trim().trim_start_matches('#').to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Importer for Obsidian {
+    fn pages(&self) -> Result<Vec<Page>, anyhow::Error> {
+        self.scan()?
+            .iter()
+            .filter(|p| Self::is_markdown(p))
+            .map(|p| Self::page(p))
+            .collect()
+    }
+
+    fn attachments(&self) -> Result<Vec<Attachment>, anyhow::Error> {
+        let mut result = vec![];
+        for path in self.scan()? {
+            if Self::is_markdown(&path) {
+                continue;
+            }
+            let name = path.strip_prefix(&self.vault)?.to_path_buf();
+            result.push(Attachment { path, name });
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{Importer, Obsidian};
+
+    #[test]
+    fn test_obsidian() {
+        let vault = tempfile::tempdir().unwrap();
+        let dir = vault.path();
+        fs::create_dir_all(dir.join(".obsidian")).unwrap();
+        fs::write(dir.join(".obsidian/app.json"), "{}").unwrap();
+        fs::create_dir_all(dir.join("lang/img")).unwrap();
+        fs::write(dir.join("lang/img/ferris.png"), "png").unwrap();
+        fs::write(
+            dir.join("lang/Rust.md"),
+            "---\ntitle: The Rust Language\ndate: 2023-01-15T10:00\n\
+             tags: [\"#lang\", systems]\naliases: rustlang, Rust lang\n---\n\
+             Fast and safe.\n",
+        )
+        .unwrap();
+        fs::write(dir.join("Go.md"), "No front matter.\n").unwrap();
+
+        let importer = Obsidian::new(dir);
+        let pages = importer.pages().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].name, "Go");
+        assert_eq!(pages[0].title, "Go");
+        assert_eq!(pages[0].body, "No front matter.\n");
+        assert!(pages[0].date.is_none());
+
+        let rust = &pages[1];
+        assert_eq!(rust.name, "Rust");
+        assert_eq!(rust.title, "The Rust Language");
+        assert_eq!(rust.date.as_deref(), Some("2023-01-15"));
+        assert_eq!(rust.tags, ["lang", "systems"]);
+        assert_eq!(rust.aliases, ["rustlang", "Rust lang"]);
+        assert_eq!(rust.body.trim(), "Fast and safe.");
+
+        let attachments = importer.attachments().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].path, dir.join("lang/img/ferris.png"));
+        assert_eq!(attachments[0].name, PathBuf::from("lang/img/ferris.png"));
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+use serde::Deserialize;
+
+use super::{Importer, Page};
+
+/// Importer for a Roam Research JSON export.
+pub struct Roam {
+    export: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct RoamPage {
+    title: String,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+    #[serde(rename = "create-time")]
+    create_time: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct RoamBlock {
+    #[serde(default)]
+    string: String,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+}
+
+impl Roam {
+    pub fn new(export: &Path) -> Self {
+        Roam {
+            export: export.to_path_buf(),
+        }
+    }
+
+    fn write_blocks(body: &mut String, blocks: &[RoamBlock], depth: usize) {
+        for block in blocks {
+            let indent = "  ".repeat(depth);
+            let mut lines = block.string.lines();
+            if let Some(first) = lines.next() {
+                body.push_str(&format!("{}- {}\n", indent, first));
+            } else {
+                body.push_str(&format!("{}-\n", indent));
+            }
+            for line in lines {
+                body.push_str(&format!("{}  {}\n", indent, line));
+            }
+            Self::write_blocks(body, &block.children, depth + 1);
+        }
+    }
+}
+
+impl Importer for Roam {
+    fn pages(&self) -> Result<Vec<Page>, anyhow::Error> {
+        let raw = fs::read_to_string(&self.export)?;
+        let pages: Vec<RoamPage> = serde_json::from_str(&raw)?;
+        Ok(pages
+            .into_iter()
+            .map(|p| {
+                let mut body = String::new();
+                Self::write_blocks(&mut body, &p.children, 0);
+                let date = p
+                    .create_time
+                    .and_then(|ms| Local.timestamp_millis_opt(ms).single())
+                    .map(|d| d.format("%Y-%m-%d").to_string());
+                Page {
+                    name: p.title.clone(),
+                    title: p.title,
+                    date,
+                    body,
+                    ..Default::default()
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Importer, Roam};
+
+    #[test]
+    fn test_roam() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = dir.path().join("roam.json");
+        let json = r#"[
+            {
+                "title": "Rust",
+                "create-time": 1673776800000,
+                "children": [
+                    {"string": "Fast", "children": [{"string": "and safe"}]},
+                    {"string": "Two\nlines"},
+                    {}
+                ]
+            },
+            {"title": "Empty"}
+        ]"#;
+        fs::write(&export, json).unwrap();
+
+        let pages = Roam::new(&export).pages().unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].name, "Rust");
+        assert_eq!(pages[0].title, "Rust");
+        assert!(pages[0].date.as_deref().unwrap().starts_with("2023-01-1"));
+        assert_eq!(pages[0].body, "- Fast\n  - and safe\n- Two\n  lines\n-\n");
+        assert_eq!(pages[1].body, "");
+        assert!(pages[1].date.is_none());
+        assert!(Roam::new(&dir.path().join("none.json")).pages().is_err());
+    }
+}
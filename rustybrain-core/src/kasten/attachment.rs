@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::Kasten;
+use crate::zettel::Zettel;
+
+impl Kasten {
+    /// Directory of the repo that attachments are copied to.
+    pub fn attachments_dir(&self) -> PathBuf {
+        let c = self.config.borrow();
+        Path::new(c.repo_path()).join(c.attachment().dir())
+    }
+
+    /// Width that images are scaled down to in the editor.
+    pub fn image_max_width(&self) -> i32 {
+        self.config.borrow().attachment().max_width()
+    }
+
+    /// Path for a new attachment named `name`, and the link to it that is
+    /// relative to the repo. The name gets a suffix if it is already taken.
+    pub fn new_attachment(
+        &self,
+        name: &str,
+    ) -> Result<(String, PathBuf), anyhow::Error> {
+        let dir = self.attachments_dir();
+        fs::create_dir_all(&dir)?;
+        let name = Path::new(name)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("attachment");
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => {
+                (stem, format!(".{}", ext))
+            }
+            _ => (name, String::new()),
+        };
+        let mut candidate = name.to_string();
+        let mut n = 1;
+        while dir.join(&candidate).exists() {
+            candidate = format!("{}-{}{}", stem, n, ext);
+            n += 1;
+        }
+        let dir_name = self.config.borrow().attachment().dir().to_string();
+        Ok((format!("{}/{}", dir_name, candidate), dir.join(candidate)))
+    }
+
+    /// Copy the file into the attachments of the repo and commit it, the link
+    /// to the copy is returned.
+    pub fn attach_file(&self, src: &Path) -> Result<String, anyhow::Error> {
+        let name = src
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid file {:?}", src))?;
+        let (link, path) = self.new_attachment(name)?;
+        fs::copy(src, &path)?;
+        self.commit_attachment(&path)?;
+        Ok(link)
+    }
+
+    /// Commit a file that is written to the path of `new_attachment`.
+    pub fn commit_attachment(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(git) = self.git.as_ref() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            git.commit_file(path, &format!("Attach {}", name))?;
+        }
+        Ok(())
+    }
+
+    /// File that a link in the zettel points to, the link is relative to the
+    /// zettel, the repo or the attachments directory.
+    pub fn resolve_attachment(
+        &self,
+        z: &Zettel,
+        link: &str,
+    ) -> Option<PathBuf> {
+        let link = link.trim().trim_start_matches('<').trim_end_matches('>');
+        if link.contains("://") {
+            return None;
+        }
+        let path = Path::new(link);
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        let repo = PathBuf::from(self.repo_path());
+        [
+            z.path().parent().map(|d| d.join(path)),
+            Some(repo.join(path)),
+            Some(self.attachments_dir().join(path)),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|p| p.is_file())
+    }
+}
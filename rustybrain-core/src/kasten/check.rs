@@ -0,0 +1,113 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use super::Kasten;
+
+/// Problem found by [`Kasten::check`].
+#[derive(Debug, Clone)]
+pub enum Problem {
+    /// File in the repo that can't be loaded as a zettel.
+    Unreadable { path: PathBuf, reason: String },
+    /// Link points to a zettel that not exists in the slip-box.
+    BrokenLink { zid: String, link: String },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unreadable { path, reason } => {
+                write!(f, "{}: unreadable: {}", path.display(), reason)
+            }
+            Problem::BrokenLink { zid, link } => {
+                write!(f, "{}: broken link to {}", zid, link)
+            }
+        }
+    }
+}
+
+impl Kasten {
+    /// Scan the repo on disk and report notes that can't be loaded and
+    /// links to notes that don't exist.
+    pub fn check(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        let mut zettels = vec![];
+        let iter = self.iter_from_disk();
+        let entries = match iter.scan_markdowns() {
+            Ok(entries) => entries,
+            Err(e) => {
+                problems.push(Problem::Unreadable {
+                    path: PathBuf::from(self.repo_path()),
+                    reason: e.to_string(),
+                });
+                return problems;
+            }
+        };
+        for entry in entries {
+            let path = entry.path();
+            match iter.dir_entry_to_zettel(entry) {
+                Ok(z) => zettels.push(z),
+                Err(e) => problems.push(Problem::Unreadable {
+                    path,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+
+        for z in zettels.iter() {
+            for link in z.link_to_iter() {
+                if !link.starts_with("@/") {
+                    continue;
+                }
+                if !zettels.iter().any(|o| o.zid() == link) {
+                    problems.push(Problem::BrokenLink {
+                        zid: z.zid().to_string(),
+                        link: link.to_string(),
+                    });
+                }
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::super::{temp_config, Kasten};
+    use super::Problem;
+
+    #[test]
+    fn test_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = |title: &str, body: &str| {
+            format!("+++\ntitle = \"{}\"\n+++\n{}\n", title, body)
+        };
+        fs::write(dir.path().join("a.md"), note("a", "[B](@/b.md)")).unwrap();
+        fs::write(dir.path().join("b.md"), note("b", "[A](@/a.md)")).unwrap();
+        let kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+        assert!(kasten.check().is_empty());
+
+        fs::write(dir.path().join("c.md"), "+++\ntitle = \n+++\n").unwrap();
+        fs::remove_file(dir.path().join("b.md")).unwrap();
+        let problems = kasten.check();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            Problem::Unreadable { path, .. } if path.ends_with("c.md")
+        )));
+        assert!(problems.iter().any(|p| matches!(
+            p,
+            Problem::BrokenLink { zid, link }
+                if zid == "@/a.md" && link == "@/b.md"
+        )));
+        assert_eq!(
+            problems
+                .iter()
+                .find(|p| matches!(p, Problem::BrokenLink { .. }))
+                .unwrap()
+                .to_string(),
+            "@/a.md: broken link to @/b.md"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::rc::Rc;
+
+use super::{modified, Kasten};
+use crate::fuzzy::{fuzzy_match, FuzzyMatch};
+use crate::zettel::Zettel;
+
+/// Zettel found by [`Kasten::find`] and the characters that matched.
+#[derive(Debug, Clone)]
+pub struct Found {
+    zettel: Rc<RefCell<Zettel>>,
+    score: i64,
+    title: Vec<usize>,
+    path: Vec<usize>,
+    /// Alias that matched better than the title and the zid.
+    alias: Option<(String, Vec<usize>)>,
+}
+
+impl Found {
+    pub fn zettel(&self) -> &Rc<RefCell<Zettel>> {
+        &self.zettel
+    }
+
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    /// Char indices of the matched characters in the title.
+    pub fn title_indices(&self) -> &[usize] {
+        &self.title
+    }
+
+    /// Char indices of the matched characters in the zid.
+    pub fn path_indices(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// Alias that matched and the char indices of the matched characters
+    /// in it, if it is what matched.
+    pub fn alias(&self) -> Option<(&str, &[usize])> {
+        self.alias.as_ref().map(|(a, i)| (a.as_str(), i.as_slice()))
+    }
+}
+
+impl From<Rc<RefCell<Zettel>>> for Found {
+    /// Zettel found by other means than a match of its title or zid.
+    fn from(zettel: Rc<RefCell<Zettel>>) -> Self {
+        Found {
+            zettel,
+            score: 0,
+            title: vec![],
+            path: vec![],
+            alias: None,
+        }
+    }
+}
+
+impl Kasten {
+    /// Zettels whose title, aliases or zid fuzzy matches the pattern, best
+    /// first. An empty pattern gives the recently modified zettels first.
+    pub fn find(&self, pattern: &str) -> Vec<Found> {
+        let pattern = pattern.trim();
+        let mut found: Vec<Found> = self
+            .zettels
+            .iter()
+            .filter_map(|z| {
+                let mut found = Found::from(z.clone());
+                let z = z.borrow();
+                let title = fuzzy_match(pattern, z.title());
+                let path = fuzzy_match(pattern, z.zid());
+                let alias = z
+                    .aliases()
+                    .iter()
+                    .filter_map(|a| Some((a, fuzzy_match(pattern, a)?)))
+                    .max_by_key(|(_, m)| m.score());
+                let score = |m: Option<&FuzzyMatch>| m.map(|m| m.score());
+                let best = score(title.as_ref())
+                    .max(score(path.as_ref()))
+                    .max(alias.as_ref().map(|(_, m)| m.score()))?;
+                found.score = best;
+                if score(title.as_ref()) == Some(best) {
+                    found.title = title?.indices().to_vec();
+                } else if score(path.as_ref()) == Some(best) {
+                    found.path = path?.indices().to_vec();
+                } else if let Some((a, m)) = alias {
+                    found.alias = Some((a.to_string(), m.indices().to_vec()));
+                }
+                Some(found)
+            })
+            .collect();
+        // New zettels are appended to the kasten, so the recent ones are
+        // not always first there.
+        found.sort_by_cached_key(|f| {
+            let modified = modified(f.zettel.borrow().path());
+            (Reverse(f.score), Reverse(modified))
+        });
+        found.truncate(self.config.borrow().search().limit());
+        found
+    }
+}
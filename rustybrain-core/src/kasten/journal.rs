@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use toml::value::Datetime;
+
+use super::Kasten;
+use crate::zettel::Zettel;
+
+impl Kasten {
+    /// Open the daily note of the date, create it from the journal template
+    /// if not exists.
+    pub fn journal(
+        &mut self,
+        date: NaiveDate,
+    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+        let path = self.journal_path(&date);
+        if let Some(z) = self.zettels.iter().find(|z| z.borrow().path() == path)
+        {
+            return Ok(z.clone());
+        }
+
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let title = date.format("%Y-%m-%d").to_string();
+        let mut z = Zettel::create(&self.repo_path(), &path, None, &title)?;
+        z.set_date(Datetime::from_str(&title)?);
+        let template = self
+            .config
+            .borrow()
+            .journal()
+            .template()
+            .map(|s| s.to_string())
+            .or_else(|| self.folder_template(&path));
+        if let Some(name) = template {
+            self.apply_template(&mut z, &name, None)?;
+        }
+        z.save()?;
+        self.insert(z)
+    }
+
+    /// Days of the month that have a daily note.
+    pub fn journal_days(&self, year: i32, month: u32) -> Vec<u32> {
+        let dir = self.journal_dir();
+        let mut days: Vec<u32> = self
+            .zettels
+            .iter()
+            .filter_map(|z| {
+                let z = z.borrow();
+                if z.path().parent() != Some(dir.as_path()) {
+                    return None;
+                }
+                let stem = z.path().file_stem()?.to_str()?;
+                NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok()
+            })
+            .filter(|d| d.year() == year && d.month() == month)
+            .map(|d| d.day())
+            .collect();
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+
+    fn journal_dir(&self) -> PathBuf {
+        let c = self.config.borrow();
+        Path::new(c.repo_path()).join(c.journal().dir())
+    }
+
+    fn journal_path(&self, date: &NaiveDate) -> PathBuf {
+        self.journal_dir()
+            .join(format!("{}.md", date.format("%Y-%m-%d")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::rc::Rc;
+
+    use chrono::{Local, NaiveDate};
+
+    use super::super::{temp_config, temp_kasten, Kasten};
+
+    #[test]
+    fn test_journal_today() {
+        let (dir, mut kasten) = temp_kasten("");
+        let today = Local::now().date_naive();
+        let title = today.format("%Y-%m-%d").to_string();
+
+        let z = kasten.journal(today).unwrap();
+        let path = dir.path().join("journal").join(format!("{}.md", title));
+        assert!(path.is_file());
+        assert_eq!(z.borrow().path(), path);
+        assert_eq!(z.borrow().title(), title);
+        assert_eq!(z.borrow().date().unwrap().to_string(), title);
+        assert!(Rc::ptr_eq(&kasten.journal(today).unwrap(), &z));
+        assert_eq!(kasten.iter().count(), 1);
+    }
+
+    #[test]
+    fn test_journal_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("templates")).unwrap();
+        fs::write(
+            dir.path().join("templates/daily.md"),
+            "# {{title}}\n\n## Tasks\n",
+        )
+        .unwrap();
+        let config = "[journal]\ndir = \"daily\"\ntemplate = \"daily\"\n";
+        let mut kasten = Kasten::new(temp_config(dir.path(), config)).unwrap();
+        let date = NaiveDate::from_ymd_opt(2023, 1, 5).unwrap();
+        let z = kasten.journal(date).unwrap();
+        assert!(z.borrow().path().ends_with("daily/2023-01-05.md"));
+        assert_eq!(z.borrow().content(), "# 2023-01-05\n\n## Tasks\n");
+
+        let mut z = z.borrow().clone();
+        z.set_content("# 2023-01-05\n\nWrote tests.\n").unwrap();
+        kasten.save(&z).unwrap();
+        let date = NaiveDate::from_ymd_opt(2023, 1, 20).unwrap();
+        kasten.journal(date).unwrap();
+
+        // A new kasten reads the notes again from the disk.
+        let mut kasten = Kasten::new(temp_config(dir.path(), config)).unwrap();
+        let date = NaiveDate::from_ymd_opt(2023, 1, 5).unwrap();
+        let z = kasten.journal(date).unwrap();
+        assert_eq!(z.borrow().content(), "# 2023-01-05\n\nWrote tests.\n");
+        assert_eq!(kasten.iter().count(), 2);
+        assert_eq!(kasten.journal_days(2023, 1), [5, 20]);
+        assert!(kasten.journal_days(2023, 2).is_empty());
+    }
+}
@@ -1,7 +1,18 @@
+mod attachment;
+mod check;
+mod finder;
+mod journal;
+mod saved;
+mod search;
+mod semantic;
+mod sequence;
+mod suggest;
+mod template;
+
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
-    fs::{self, create_dir_all, DirEntry},
+    fs::{self, DirEntry},
     path::{Path, PathBuf},
     rc::Rc,
     slice::Iter,
@@ -9,15 +20,27 @@ use std::{
     usize,
 };
 
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 use tantivy::{
-    collector::TopDocs,
-    query::QueryParser,
-    schema::{Field, Schema, Value, STORED, TEXT},
-    Document, Index,
+    schema::{Field, Schema, INDEXED, STORED, STRING, TEXT},
+    DateTime, Document, Index, Term,
+};
+use toml::value::Datetime;
+
+use crate::{
+    config::{Collision, Config, IdScheme, Layout},
+    git::{Repository, Revision},
+    id, query,
+    zettel::Zettel,
 };
 
-use crate::{config::Config, zettel::Zettel};
+pub use check::Problem;
+pub use finder::Found;
+pub use saved::{SavedSearch, SortBy};
+pub use sequence::SequenceNode;
+
+/// Callbacks of the changes, see [`Kasten::connect_changed`].
+type Listeners = Rc<RefCell<Vec<Box<dyn Fn()>>>>;
 
 #[derive(Clone)]
 pub struct Kasten {
@@ -29,10 +52,25 @@ pub struct Kasten {
     title: Field,
     body: Field,
     path: Field,
+    /// Zid of the zettel, to replace its document.
+    zid: Field,
+    tag: Field,
+    link: Field,
+    created: Field,
+    /// Number of the zettels that link to the zettel.
+    linked: Field,
     index: Index,
 
     zettels: Vec<Rc<RefCell<Zettel>>>,
     backlinks: HashMap<String, Vec<usize>>,
+
+    git: Option<Rc<Repository>>,
+
+    /// Called after the zettels are changed, shared by the clones.
+    listeners: Listeners,
+
+    #[cfg(feature = "semantic")]
+    semantic: Rc<RefCell<Option<semantic::State>>>,
 }
 
 impl Kasten {
@@ -41,9 +79,25 @@ impl Kasten {
         let title = schema_builder.add_text_field("title", TEXT | STORED);
         let path = schema_builder.add_text_field("path", TEXT | STORED);
         let body = schema_builder.add_text_field("body", TEXT);
+        let zid = schema_builder.add_text_field("zid", STRING);
+        let tag = schema_builder.add_text_field("tag", STRING);
+        let link = schema_builder.add_text_field("link", STRING);
+        let created = schema_builder.add_date_field("created", INDEXED);
+        let linked = schema_builder.add_u64_field("linked", INDEXED);
         let schema = schema_builder.build();
         let index = Index::create_in_ram(schema.clone());
 
+        let git = {
+            let c = config.borrow();
+            if c.git().enabled() {
+                let root = Path::new(c.repo_path());
+                let repo = Repository::open_or_init(root, c.git().branch())?;
+                Some(Rc::new(repo))
+            } else {
+                None
+            }
+        };
+
         let mut kasten = Kasten {
             config,
             schema,
@@ -51,16 +105,24 @@ impl Kasten {
             title,
             body,
             path,
+            zid,
+            tag,
+            link,
+            created,
+            linked,
 
             zettels: vec![],
             backlinks: HashMap::new(),
+            git,
+            listeners: Rc::new(RefCell::new(vec![])),
+            #[cfg(feature = "semantic")]
+            semantic: Rc::new(RefCell::new(None)),
         };
         kasten.build()?;
         Ok(kasten)
     }
 
     fn build(&mut self) -> Result<(), anyhow::Error> {
-        self.build_index()?;
         let mut zettels = vec![];
         let mut backlinks: HashMap<String, Vec<usize>> = HashMap::new();
         for entry in self.iter_from_disk() {
@@ -76,100 +138,365 @@ impl Kasten {
         }
         self.zettels = zettels;
         self.backlinks = backlinks;
+        self.build_index()?;
+        self.changed();
         Ok(())
     }
 
-    fn build_index(&self) -> Result<(), anyhow::Error> {
-        {
-            let mut index_writer = self.index.writer(50_000_000)?;
-            index_writer.delete_all_documents()?;
-            index_writer.commit()?;
+    /// Call `f` after zettels are created, saved or reloaded. It is called
+    /// while the kasten is borrowed, so it should only queue a refresh.
+    pub fn connect_changed<F: Fn() + 'static>(&self, f: F) {
+        self.listeners.borrow_mut().push(Box::new(f));
+    }
+
+    fn changed(&self) {
+        for f in self.listeners.borrow().iter() {
+            f();
         }
+    }
 
+    /// Drop everything loaded in memory and rebuild it from the disk.
+    pub fn reindex(&mut self) -> Result<(), anyhow::Error> {
+        self.build()
+    }
+
+    /// Index all the zettels with one writer and one commit, a writer for
+    /// each of them is too slow for a large repo.
+    fn build_index(&self) -> Result<(), anyhow::Error> {
+        let mut index_writer = self.index.writer(50_000_000)?;
+        index_writer.delete_all_documents()?;
         for entry in self.zettels.iter() {
-            let z = entry.borrow();
-            self.add_doc(&z)?;
+            index_writer.add_document(self.doc(&entry.borrow()))?;
         }
+        index_writer.commit()?;
         Ok(())
     }
 
-    fn add_doc(&self, z: &Zettel) -> Result<(), anyhow::Error> {
+    /// Replace the documents of the zettels, or add them if they are new,
+    /// like when the zettel is saved or the number of the zettels that
+    /// link to it changes.
+    fn update_docs(&self, zettels: &[&Zettel]) -> Result<(), anyhow::Error> {
         let mut index_writer = self.index.writer(50_000_000)?;
+        for z in zettels {
+            index_writer.delete_term(Term::from_field_text(self.zid, z.zid()));
+            index_writer.add_document(self.doc(z))?;
+        }
+        index_writer.commit()?;
+        Ok(())
+    }
+
+    /// Documents of the zettel and of the zettels that it links or linked
+    /// to, whose numbers of links may change.
+    fn update_linked_docs(
+        &self,
+        z: &Zettel,
+        targets: HashSet<String>,
+    ) -> Result<(), anyhow::Error> {
+        let targets: Vec<_> = targets
+            .iter()
+            .filter(|t| t.as_str() != z.zid())
+            .filter_map(|t| self.get(t))
+            .collect();
+        let targets: Vec<_> = targets.iter().map(|t| t.borrow()).collect();
+        let mut docs = vec![z];
+        docs.extend(targets.iter().map(|t| &**t));
+        self.update_docs(&docs)
+    }
+
+    fn doc(&self, z: &Zettel) -> Document {
         let title = self.title;
         let body = self.body;
         let mut doc = Document::default();
         doc.add_text(title, z.title());
+        for alias in z.aliases() {
+            doc.add_text(title, alias);
+        }
         doc.add_text(body, z.content());
         if let Some(p) = z.path().to_str() {
             doc.add_text(self.path, p);
         }
-        index_writer.add_document(doc)?;
-        index_writer.commit()?;
-        Ok(())
+        doc.add_text(self.zid, z.zid());
+        for tag in z.tags() {
+            doc.add_text(self.tag, tag.to_lowercase());
+        }
+        for link in z.link_to_iter() {
+            doc.add_text(self.link, link);
+        }
+        if let Some(date) = z.date().and_then(to_date_time) {
+            doc.add_date(self.created, date);
+        }
+        let linked = self.backlinks.get(z.zid()).map_or(0, |v| v.len());
+        doc.add_u64(self.linked, linked as u64);
+        doc
     }
 
+    /// Paths of the zettels whose title matches the keywords, see
+    /// [`crate::query`] for the syntax.
     pub fn search_title(
         &self,
         kw: &str,
     ) -> Result<HashSet<String>, anyhow::Error> {
-        let reader = self
-            .index
-            .reader_builder()
-            .reload_policy(tantivy::ReloadPolicy::OnCommit)
-            .try_into()?;
-        let searcher = reader.searcher();
-        let query_parser =
-            QueryParser::for_index(&self.index, vec![self.title]);
-        let query = query_parser.parse_query(kw)?;
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
-        let mut set = HashSet::new();
-        for (_score, doc_address) in top_docs {
-            let retrieved_doc: Document = searcher.doc(doc_address)?;
-            if let Some(Value::Str(s)) = retrieved_doc.get_first(self.path) {
-                set.insert(s.to_string());
+        let paths = match query::parse(kw)? {
+            Some(q) => {
+                let limit = self.config.borrow().search().limit();
+                self.search_paths(&q, &[self.title], limit)?
+            }
+            None => vec![],
+        };
+        Ok(paths.into_iter().collect())
+    }
+
+    /// Zettels to complete a link by the keyword, the ones whose title,
+    /// aliases or zid contain it come before the other hits of the search.
+    pub fn complete_title(
+        &self,
+        kw: &str,
+        limit: usize,
+    ) -> Vec<Rc<RefCell<Zettel>>> {
+        let lower = kw.to_lowercase();
+        let contains = |z: &Zettel| {
+            z.title().to_lowercase().contains(&lower)
+                || z.aliases()
+                    .iter()
+                    .any(|a| a.to_lowercase().contains(&lower))
+                || z.zid().to_lowercase().contains(&lower)
+        };
+        let mut found: Vec<_> = self
+            .zettels
+            .iter()
+            .filter(|z| contains(&z.borrow()))
+            .take(limit)
+            .cloned()
+            .collect();
+        let hits = match kw.trim() {
+            "" => HashSet::new(),
+            kw => self.search_title(kw).unwrap_or_default(),
+        };
+        for z in self.zettels.iter() {
+            if found.len() >= limit {
+                break;
+            }
+            let hit = z
+                .borrow()
+                .path()
+                .to_str()
+                .map_or(false, |p| hits.contains(p));
+            if hit && !found.iter().any(|f| Rc::ptr_eq(f, z)) {
+                found.push(z.clone());
             }
         }
-        Ok(set)
+        found
     }
 
     pub fn iter(&self) -> Iter<'_, Rc<RefCell<Zettel>>> {
         self.zettels.iter()
     }
 
+    /// Find a zettel by its zid, the `@/` prefix is optional.
+    pub fn get(&self, zid: &str) -> Option<Rc<RefCell<Zettel>>> {
+        let zid = if zid.starts_with("@/") {
+            zid.to_string()
+        } else {
+            format!("@/{}", zid)
+        };
+        self.zettels
+            .iter()
+            .find(|z| z.borrow().zid() == zid)
+            .cloned()
+    }
+
+    /// Find a zettel by its title or one of its aliases, ignoring case,
+    /// like the name of a `[[Name]]` link.
+    pub fn resolve(&self, name: &str) -> Option<Rc<RefCell<Zettel>>> {
+        self.zettels
+            .iter()
+            .find(|z| z.borrow().is_named(name))
+            .cloned()
+    }
+
     fn iter_from_disk(&self) -> SyncDiskIter {
         let c = (*self.config).borrow();
-        SyncDiskIter {
-            inner: None,
-            repo_path: c.repo_path().to_string(),
-        }
+        SyncDiskIter::new(c.repo_path(), vec![self.templates_dir()])
     }
 
     pub fn create(
         &mut self,
         title: &str,
     ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
-        let path = self.new_path();
-        if let Some(dir) = path.as_path().parent() {
-            create_dir_all(dir)?;
+        self.create_from_template(title, None, None)
+    }
+
+    /// Track a zettel that just created on disk.
+    fn insert(
+        &mut self,
+        z: Zettel,
+    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+        self.commit(&z, &format!("Create {}", z.title()))?;
+        for link_to in z.link_to_iter() {
+            self.backlinks
+                .entry(link_to.to_string())
+                .or_default()
+                .push(self.zettels.len());
         }
-        let z = Zettel::create(&self.repo_path(), &path, title)?;
-        self.add_doc(&z)?;
+        // The zettels it links to are linked by one more.
+        self.update_linked_docs(&z, z.link_to_iter().cloned().collect())?;
         let z = Rc::new(RefCell::new(z));
         self.zettels.push(z.clone());
+        self.changed();
         Ok(z)
     }
 
+    /// Write the zettel and update its links and documents, the other
+    /// zettels are not read again.
     pub fn save(&mut self, zettel: &Zettel) -> Result<(), anyhow::Error> {
         zettel.save()?;
-        self.build()?;
+        self.commit(zettel, &format!("Update {}", zettel.title()))?;
+        let i = match self
+            .zettels
+            .iter()
+            .position(|z| z.borrow().zid() == zettel.zid())
+        {
+            Some(i) => i,
+            None => return self.build(),
+        };
+        // A copy of the tracked zettel, the tracked one is borrowed by the
+        // caller otherwise.
+        if !std::ptr::eq(&*self.zettels[i].borrow(), zettel) {
+            *self.zettels[i].borrow_mut() = zettel.clone();
+        }
+
+        let mut targets = HashSet::new();
+        for (target, sources) in self.backlinks.iter_mut() {
+            let len = sources.len();
+            sources.retain(|&s| s != i);
+            if sources.len() != len {
+                targets.insert(target.clone());
+            }
+        }
+        self.backlinks.retain(|_, sources| !sources.is_empty());
+        for link_to in zettel.link_to_iter() {
+            self.backlinks
+                .entry(link_to.to_string())
+                .or_default()
+                .push(i);
+            targets.insert(link_to.to_string());
+        }
+        self.update_linked_docs(zettel, targets)?;
+        self.changed();
+        Ok(())
+    }
+
+    fn commit(
+        &self,
+        zettel: &Zettel,
+        message: &str,
+    ) -> Result<(), anyhow::Error> {
+        if let Some(git) = self.git.as_ref() {
+            git.commit_file(zettel.path(), message)?;
+        }
         Ok(())
     }
 
-    fn new_path(&self) -> PathBuf {
+    fn git(&self) -> Result<&Repository, anyhow::Error> {
+        self.git
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("git is not enabled for the repo"))
+    }
+
+    /// Revisions of the zettel, newest first.
+    pub fn history(
+        &self,
+        zettel: &Zettel,
+    ) -> Result<Vec<Revision>, anyhow::Error> {
+        self.git()?.history(zettel.path())
+    }
+
+    /// Diff from the revision to the current version of the zettel.
+    pub fn diff(
+        &self,
+        zettel: &Zettel,
+        rev: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.git()?.diff(zettel.path(), rev)
+    }
+
+    /// Restore the zettel to the revision and commit it as a new version.
+    pub fn restore(
+        &mut self,
+        zettel: &Zettel,
+        rev: &str,
+    ) -> Result<(), anyhow::Error> {
+        let git = self.git()?;
+        git.restore(zettel.path(), rev)?;
+        let message = format!("Restore {} to {}", zettel.title(), rev);
+        git.commit_file(zettel.path(), &message)?;
+        self.build()
+    }
+
+    pub fn pull(&mut self) -> Result<(), anyhow::Error> {
+        let (remote, branch) = self.remote()?;
+        self.git()?.pull(&remote, &branch)?;
+        self.build()
+    }
+
+    pub fn push(&self) -> Result<(), anyhow::Error> {
+        let (remote, branch) = self.remote()?;
+        self.git()?.push(&remote, &branch)
+    }
+
+    fn remote(&self) -> Result<(String, String), anyhow::Error> {
+        let c = self.config.borrow();
+        let remote = c
+            .git()
+            .remote()
+            .ok_or_else(|| anyhow::anyhow!("git remote is not configured"))?;
+        Ok((remote.to_string(), c.git().branch().to_string()))
+    }
+
+    /// Id of a new zettel, generated by the configured scheme.
+    fn new_id(&self, title: &str) -> String {
+        match self.config.borrow().note().id() {
+            IdScheme::Timestamp => id::timestamp(),
+            IdScheme::Ulid => id::ulid(),
+            IdScheme::Slug => id::slugify(title),
+            IdScheme::Folgezettel => self.next_id(None).unwrap_or_default(),
+        }
+    }
+
+    /// Path of a new zettel with the id, the id gets a suffix if it is
+    /// already taken.
+    fn new_path(&self, id: &str) -> Result<(String, PathBuf), anyhow::Error> {
         let c = (*self.config).borrow();
-        let path = c.repo_path();
-        let gen = Local::now().format("%Y%m%d%H%M%S").to_string();
-        Path::new(path).join(format!("notes/{}.md", gen))
+        let note = c.note();
+        let now = Local::now();
+        let mut dir = Path::new(c.repo_path()).join(note.dir());
+        match note.layout() {
+            Layout::Flat => {}
+            Layout::Year => dir.push(now.format("%Y").to_string()),
+            Layout::YearMonth => {
+                dir.push(now.format("%Y").to_string());
+                dir.push(now.format("%m").to_string());
+            }
+        }
+
+        let mut candidate = id.to_string();
+        let mut n = 1;
+        while self.is_taken(&candidate, &dir) {
+            if note.collision() == Collision::Error {
+                return Err(anyhow::anyhow!("note id {} already exists", id));
+            }
+            candidate = format!("{}-{}", id, n);
+            n += 1;
+        }
+        let path = dir.join(format!("{}.md", candidate));
+        Ok((candidate, path))
+    }
+
+    /// Whether the id is used by a zettel or its file exists in `dir`.
+    fn is_taken(&self, id: &str, dir: &Path) -> bool {
+        let zid = format!("@/{}", id);
+        dir.join(format!("{}.md", id)).exists()
+            || self.zettels.iter().any(|z| z.borrow().zid() == zid)
     }
 
     pub fn repo_path(&self) -> String {
@@ -189,22 +516,38 @@ impl Kasten {
     }
 }
 
+/// Time the file is modified at, `None` if it is unknown.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Date and time of a header as a UTC one, the offset is ignored.
+fn to_date_time(date: &Datetime) -> Option<DateTime> {
+    let d = date.date?;
+    let day =
+        NaiveDate::from_ymd_opt(d.year as i32, d.month as u32, d.day as u32)?;
+    let time = match date.time {
+        Some(t) => {
+            day.and_hms_opt(t.hour as u32, t.minute as u32, t.second as u32)?
+        }
+        None => day.and_hms_opt(0, 0, 0)?,
+    };
+    Some(DateTime::from_timestamp_secs(time.and_utc().timestamp()))
+}
+
 impl IntoIterator for Kasten {
     type Item = Result<Zettel, anyhow::Error>;
     type IntoIter = SyncDiskIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        let c = (*self.config).borrow();
-        SyncDiskIter {
-            inner: None,
-            repo_path: c.repo_path().to_string(),
-        }
+        self.iter_from_disk()
     }
 }
 
 pub struct SyncDiskIter {
     inner: Option<std::vec::IntoIter<DirEntry>>,
     repo_path: String,
+    excludes: Vec<PathBuf>,
 }
 
 impl Iterator for SyncDiskIter {
@@ -230,6 +573,24 @@ impl Iterator for SyncDiskIter {
 }
 
 impl SyncDiskIter {
+    fn new(repo_path: &str, excludes: Vec<PathBuf>) -> Self {
+        SyncDiskIter {
+            inner: None,
+            repo_path: repo_path.to_string(),
+            excludes,
+        }
+    }
+
+    /// Hidden entries like `.git` and temporary files of saving are skipped.
+    fn is_skipped(&self, path: &Path) -> bool {
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with('.'))
+            .unwrap_or(false);
+        hidden || self.excludes.iter().any(|e| e == path)
+    }
+
     fn scan_markdowns(&self) -> Result<Vec<DirEntry>, anyhow::Error> {
         let buf = Path::new(&self.repo_path);
         let mut dirs = vec![buf.to_path_buf()];
@@ -238,9 +599,13 @@ impl SyncDiskIter {
             let rd = fs::read_dir(cur)?;
             for entry in rd {
                 let item = entry?;
-                if item.path().is_dir() {
-                    dirs.push(item.path().to_path_buf());
-                } else {
+                let path = item.path();
+                if self.is_skipped(&path) {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().map(|e| e == "md").unwrap_or(false) {
                     result.push(item);
                 }
             }
@@ -271,3 +636,138 @@ impl SyncDiskIter {
         Ok(ze)
     }
 }
+
+/// Config of a repo in `dir` for tests, `config` is appended to the minimal
+/// one.
+#[cfg(test)]
+pub(crate) fn temp_config(dir: &Path, config: &str) -> Rc<RefCell<Config>> {
+    let config = format!(
+        "[repo]\npath = {:?}\n\
+         [shortcut]\nfind = \"<Control>p\"\ninsert = \"<Control>i\"\n\
+         quit = \"<Control>q\"\n{}",
+        dir.to_str().unwrap(),
+        config
+    );
+    Rc::new(RefCell::new(config.parse().unwrap()))
+}
+
+/// A kasten in an empty temporary repo.
+#[cfg(test)]
+pub(crate) fn temp_kasten(config: &str) -> (tempfile::TempDir, Kasten) {
+    let dir = tempfile::tempdir().unwrap();
+    let kasten = Kasten::new(temp_config(dir.path(), config)).unwrap();
+    (dir, kasten)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, fs, rc::Rc};
+
+    use chrono::Local;
+
+    use super::{temp_config, temp_kasten, Kasten};
+    use crate::zettel::Zettel;
+
+    #[test]
+    fn test_resolve_by_alias() {
+        let dir = tempfile::tempdir().unwrap();
+        let md = "+++\ntitle = \"Zettelkasten\"\n\
+                  aliases = [\"Slip box\", \"Über Notes\"]\n+++\n";
+        fs::write(dir.path().join("zk.md"), md).unwrap();
+        let kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+
+        let zk = kasten.get("zk.md").unwrap();
+        assert!(zk.borrow().is_named("zettelkasten"));
+        assert!(zk.borrow().is_named(" SLIP BOX "));
+        assert!(zk.borrow().is_named("über notes"));
+        assert!(!zk.borrow().is_named("slip"));
+
+        for name in ["Zettelkasten", "slip box", "ÜBER NOTES"] {
+            let found = kasten.resolve(name).unwrap();
+            assert_eq!(found.borrow().zid(), "@/zk.md");
+        }
+        assert!(kasten.resolve("Box").is_none());
+    }
+
+    #[test]
+    fn test_save_updates_links_and_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = |title: &str, body: &str| {
+            format!("+++\ntitle = \"{}\"\n+++\n{}\n", title, body)
+        };
+        fs::write(dir.path().join("a.md"), note("a", "[B](@/b.md)")).unwrap();
+        fs::write(dir.path().join("b.md"), note("b", "")).unwrap();
+        fs::write(dir.path().join("c.md"), note("c", "")).unwrap();
+        let mut kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+        let zids = |zs: Vec<Rc<RefCell<Zettel>>>| -> Vec<String> {
+            zs.iter().map(|z| z.borrow().zid().to_string()).collect()
+        };
+
+        let a = kasten.get("@/a.md").unwrap();
+        let mut z = a.borrow().clone();
+        z.set_title("Apple");
+        z.set_content("[C](@/c.md)").unwrap();
+        kasten.save(&z).unwrap();
+
+        assert_eq!(zids(kasten.search("title:apple").unwrap()), ["@/a.md"]);
+        assert!(kasten.search("title:a").unwrap().is_empty());
+        let b = kasten.get("@/b.md").unwrap();
+        let c = kasten.get("@/c.md").unwrap();
+        assert!(kasten.iter_backlinks(&b.borrow()).is_empty());
+        assert_eq!(zids(kasten.iter_backlinks(&c.borrow())), ["@/a.md"]);
+        let mut orphans = zids(kasten.search("orphan:true").unwrap());
+        orphans.sort();
+        assert_eq!(orphans, ["@/a.md", "@/b.md"]);
+        assert_eq!(kasten.get("@/a.md").unwrap().borrow().title(), "Apple");
+    }
+
+    #[test]
+    fn test_new_note_paths() {
+        let (dir, mut kasten) = temp_kasten("");
+        let z = kasten.create("Default").unwrap();
+        let z = z.borrow();
+        assert!(z.id().is_none());
+        assert_eq!(z.path().parent().unwrap(), dir.path().join("notes"));
+        let stem = z.path().file_stem().unwrap().to_str().unwrap();
+        assert_eq!(stem.len(), 14);
+        assert_eq!(z.zid(), format!("@/notes/{}.md", stem));
+
+        let today = Local::now();
+        for (layout, sub) in [
+            ("year", today.format("%Y").to_string()),
+            ("year-month", today.format("%Y/%m").to_string()),
+        ] {
+            let config =
+                format!("[note]\nid = \"slug\"\nlayout = {:?}\n", layout);
+            let (dir, mut kasten) = temp_kasten(&config);
+            let z = kasten.create("An Idea").unwrap();
+            let z = z.borrow();
+            assert_eq!(z.zid(), "@/an-idea");
+            assert_eq!(
+                z.path(),
+                dir.path().join("notes").join(sub).join("an-idea.md")
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_note_collision() {
+        let (dir, mut kasten) = temp_kasten("[note]\nid = \"slug\"\n");
+        let zids: Vec<String> = (0..2)
+            .map(|_| kasten.create("Idea").unwrap().borrow().zid().to_string())
+            .collect();
+        assert_eq!(zids, ["@/idea", "@/idea-1"]);
+        // A file of the id is taken too, even if it is not tracked.
+        fs::write(dir.path().join("notes/idea-2.md"), "").unwrap();
+        let z = kasten.create("Idea").unwrap();
+        assert_eq!(z.borrow().zid(), "@/idea-3");
+        assert!(dir.path().join("notes/idea-3.md").is_file());
+
+        let config = "[note]\nid = \"slug\"\ncollision = \"error\"\n";
+        let (_dir, mut kasten) = temp_kasten(config);
+        kasten.create("Idea").unwrap();
+        let err = kasten.create("Idea").unwrap_err();
+        assert_eq!(err.to_string(), "note id idea already exists");
+        assert_eq!(kasten.iter().count(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{modified, to_date_time, Kasten};
+use crate::query;
+use crate::zettel::Zettel;
+
+/// File of the saved searches, relative to the repo.
+const SEARCHES_FILE: &str = ".rustybrain/searches.toml";
+
+/// Order of the zettels found by a saved search.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Recently modified first.
+    #[default]
+    Modified,
+    /// Recently created first, by the date in the header.
+    Created,
+    Title,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 3] =
+        [SortBy::Modified, SortBy::Created, SortBy::Title];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortBy::Modified => "Modified",
+            SortBy::Created => "Created",
+            SortBy::Title => "Title",
+        }
+    }
+
+    pub fn sort(&self, zettels: &mut [Rc<RefCell<Zettel>>]) {
+        match self {
+            SortBy::Modified => zettels.sort_by_cached_key(|z| {
+                let path = z.borrow().path().to_path_buf();
+                Reverse(modified(&path))
+            }),
+            SortBy::Created => zettels.sort_by_cached_key(|z| {
+                Reverse(z.borrow().date().and_then(to_date_time))
+            }),
+            SortBy::Title => zettels
+                .sort_by_cached_key(|z| z.borrow().title().to_lowercase()),
+        }
+    }
+}
+
+/// Query that is kept under a name, shown as a folder of the zettels that
+/// match it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SavedSearch {
+    name: String,
+    query: String,
+    #[serde(default)]
+    sort: SortBy,
+}
+
+impl SavedSearch {
+    pub fn new(name: &str, query: &str) -> Self {
+        SavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+            sort: SortBy::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn sort(&self) -> SortBy {
+        self.sort
+    }
+
+    pub fn set_sort(&mut self, sort: SortBy) {
+        self.sort = sort;
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SearchesFile {
+    #[serde(default, rename = "search")]
+    searches: Vec<SavedSearch>,
+}
+
+impl Kasten {
+    fn searches_path(&self) -> PathBuf {
+        Path::new(&self.repo_path()).join(SEARCHES_FILE)
+    }
+
+    /// Saved searches of the repo, none if the file doesn't exist.
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>, anyhow::Error> {
+        let path = self.searches_path();
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let file: SearchesFile = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(file.searches)
+    }
+
+    /// Replace the saved searches of the repo.
+    pub fn set_saved_searches(
+        &self,
+        searches: &[SavedSearch],
+    ) -> Result<(), anyhow::Error> {
+        let path = self.searches_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = SearchesFile {
+            searches: searches.to_vec(),
+        };
+        fs::write(&path, toml::to_string(&file)?)?;
+        if let Some(git) = self.git.as_ref() {
+            git.commit_file(&path, "Update saved searches")?;
+        }
+        Ok(())
+    }
+
+    /// All the zettels that match the saved search, in its order.
+    pub fn run_saved_search(
+        &self,
+        search: &SavedSearch,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        let mut zettels = match query::parse(search.query())? {
+            Some(q) => self.search_all(&q)?,
+            None => vec![],
+        };
+        search.sort().sort(&mut zettels);
+        Ok(zettels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::super::{temp_config, temp_kasten, Kasten};
+    use super::{SavedSearch, SortBy, SEARCHES_FILE};
+
+    #[test]
+    fn test_saved_searches_file() {
+        let (dir, kasten) = temp_kasten("");
+        assert!(kasten.saved_searches().unwrap().is_empty());
+
+        let path = dir.path().join(SEARCHES_FILE);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "[[search]]\nname = \"Rust\"\nquery = \"tag:rust\"\n\n\
+             [[search]]\nname = \"Orphans\"\nquery = \"orphan:true\"\n\
+             sort = \"title\"\n",
+        )
+        .unwrap();
+        let mut searches = kasten.saved_searches().unwrap();
+        assert_eq!(searches.len(), 2);
+        assert_eq!(searches[0].query(), "tag:rust");
+        assert_eq!(searches[0].sort(), SortBy::Modified);
+        assert_eq!(searches[1].sort(), SortBy::Title);
+
+        searches[0].set_sort(SortBy::Created);
+        searches.push(SavedSearch::new("Recent", "created:>2023"));
+        kasten.set_saved_searches(&searches).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("sort = \"created\""));
+        assert_eq!(kasten.saved_searches().unwrap(), searches);
+    }
+
+    #[test]
+    fn test_run_saved_search_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        // Written in the order of modification, oldest first.
+        for (title, date) in [
+            ("c", "2023-02-01"),
+            ("a", "2023-03-01"),
+            ("b", "2023-01-01"),
+        ] {
+            let md = format!(
+                "+++\ntitle = \"{}\"\ndate = {}\ntags = [\"x\"]\n+++\n",
+                title, date
+            );
+            fs::write(dir.path().join(format!("{}.md", title)), md).unwrap();
+            sleep(Duration::from_millis(20));
+        }
+        let kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+
+        let mut search = SavedSearch::new("X", "tag:x");
+        for (sort, expected) in [
+            (SortBy::Modified, ["b", "a", "c"]),
+            (SortBy::Created, ["a", "c", "b"]),
+            (SortBy::Title, ["a", "b", "c"]),
+        ] {
+            search.set_sort(sort);
+            let titles: Vec<String> = kasten
+                .run_saved_search(&search)
+                .unwrap()
+                .iter()
+                .map(|z| z.borrow().title().to_string())
+                .collect();
+            assert_eq!(titles, expected, "sorted by {}", sort.name());
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Bound;
+use std::rc::Rc;
+
+use chrono::NaiveDate;
+use tantivy::collector::TopDocs;
+use tantivy::query::{
+    AllQuery, BooleanQuery, Occur, Query as TantivyQuery, QueryParser,
+    RangeQuery, TermQuery,
+};
+use tantivy::schema::{Field, IndexRecordOption, Value};
+use tantivy::{DateTime, Document, Term};
+
+use super::Kasten;
+use crate::query::{self, Query, TextField};
+use crate::zettel::Zettel;
+
+impl Kasten {
+    /// Zettels that match the query, best first, see [`crate::query`] for
+    /// the syntax. Words without a field are searched in titles and bodies.
+    pub fn search(
+        &self,
+        query: &str,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        match query::parse(query)? {
+            Some(q) => self.search_query(&q),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Zettels that match the parsed query, best first.
+    pub fn search_query(
+        &self,
+        query: &Query,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        let limit = self.config.borrow().search().limit();
+        self.search_limit(query, limit)
+    }
+
+    /// All the zettels that match the parsed query, best first.
+    pub fn search_all(
+        &self,
+        query: &Query,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        self.search_limit(query, self.zettels.len())
+    }
+
+    fn search_limit(
+        &self,
+        query: &Query,
+        limit: usize,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        let defaults = [self.title, self.body];
+        let paths = self.search_paths(query, &defaults, limit)?;
+        Ok(self.zettels_of_paths(&paths))
+    }
+
+    /// Zettels at the paths, in their order.
+    pub(super) fn zettels_of_paths(
+        &self,
+        paths: &[String],
+    ) -> Vec<Rc<RefCell<Zettel>>> {
+        let by_path: HashMap<String, &Rc<RefCell<Zettel>>> = self
+            .zettels
+            .iter()
+            .filter_map(|z| {
+                let p = z.borrow().path().to_str()?.to_string();
+                Some((p, z))
+            })
+            .collect();
+        paths
+            .iter()
+            .filter_map(|p| by_path.get(p).map(|z| (*z).clone()))
+            .collect()
+    }
+
+    /// Paths of at most `limit` zettels that match the query, best first,
+    /// the words without a field are searched in `defaults`.
+    pub(super) fn search_paths(
+        &self,
+        query: &Query,
+        defaults: &[Field],
+        limit: usize,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let query = self.to_tantivy(query, defaults)?;
+        self.top_paths(&*query, limit)
+    }
+
+    /// Paths of the `limit` best zettels of the tantivy query.
+    pub(super) fn top_paths(
+        &self,
+        query: &dyn TantivyQuery,
+        limit: usize,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let reader = self
+            .index
+            .reader_builder()
+            .reload_policy(tantivy::ReloadPolicy::OnCommit)
+            .try_into()?;
+        let searcher = reader.searcher();
+        // A collector of no docs panics.
+        let top_docs =
+            searcher.search(query, &TopDocs::with_limit(limit.max(1)))?;
+        let mut paths = vec![];
+        for (_score, doc_address) in top_docs {
+            let retrieved_doc: Document = searcher.doc(doc_address)?;
+            if let Some(Value::Str(s)) = retrieved_doc.get_first(self.path) {
+                paths.push(s.to_string());
+            }
+        }
+        Ok(paths)
+    }
+
+    fn to_tantivy(
+        &self,
+        query: &Query,
+        defaults: &[Field],
+    ) -> Result<Box<dyn TantivyQuery>, anyhow::Error> {
+        let term = |field, text: &str| -> Box<dyn TantivyQuery> {
+            Box::new(TermQuery::new(
+                Term::from_field_text(field, text),
+                IndexRecordOption::Basic,
+            ))
+        };
+        let q: Box<dyn TantivyQuery> = match query {
+            Query::Text { field, text, .. } => {
+                let fields = match field {
+                    Some(TextField::Title) => vec![self.title],
+                    Some(TextField::Body) => vec![self.body],
+                    Some(TextField::Path) => vec![self.path],
+                    None => defaults.to_vec(),
+                };
+                // The words are searched as a phrase so the parser of
+                // tantivy only tokenizes them.
+                let words: String = text
+                    .chars()
+                    .map(|c| if c == '"' || c == '\\' { ' ' } else { c })
+                    .collect();
+                QueryParser::for_index(&self.index, fields)
+                    .parse_query(&format!("\"{}\"", words))?
+            }
+            Query::Tag(tag) => term(self.tag, tag),
+            Query::LinksTo(zid) => term(self.link, zid),
+            Query::Created(range) => {
+                let from = range.from().map_or(Bound::Unbounded, |d| {
+                    Bound::Included(day_start(d))
+                });
+                let until = range.until().map_or(Bound::Unbounded, |d| {
+                    Bound::Excluded(day_start(d))
+                });
+                Box::new(RangeQuery::new_date_bounds(
+                    "created".to_string(),
+                    from,
+                    until,
+                ))
+            }
+            Query::Orphan(orphan) => {
+                let q: Box<dyn TantivyQuery> = Box::new(TermQuery::new(
+                    Term::from_field_u64(self.linked, 0),
+                    IndexRecordOption::Basic,
+                ));
+                if *orphan {
+                    q
+                } else {
+                    not(q)
+                }
+            }
+            Query::And(qs) | Query::Or(qs) => {
+                let occur = match query {
+                    Query::And(_) => Occur::Must,
+                    _ => Occur::Should,
+                };
+                let mut clauses = vec![];
+                for q in qs {
+                    clauses.push((occur, self.to_tantivy(q, defaults)?));
+                }
+                Box::new(BooleanQuery::new(clauses))
+            }
+            Query::Not(q) => not(self.to_tantivy(q, defaults)?),
+        };
+        Ok(q)
+    }
+}
+
+/// Query of all the documents but the ones of `q`.
+fn not(q: Box<dyn TantivyQuery>) -> Box<dyn TantivyQuery> {
+    let all: Box<dyn TantivyQuery> = Box::new(AllQuery);
+    Box::new(BooleanQuery::new(vec![
+        (Occur::Must, all),
+        (Occur::MustNot, q),
+    ]))
+}
+
+/// Midnight of the day, in UTC like the dates of the headers.
+fn day_start(date: NaiveDate) -> DateTime {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    DateTime::from_timestamp_secs(midnight.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::super::{temp_config, Kasten};
+
+    fn write(dir: &Path, title: &str, date: &str, body: &str) {
+        let md = format!(
+            "+++\ntitle = \"{}\"\ndate = {}\n+++\n{}\n",
+            title, date, body
+        );
+        fs::write(dir.join(format!("{}.md", title)), md).unwrap();
+    }
+
+    fn titles(kasten: &Kasten, query: &str) -> Vec<String> {
+        let mut titles: Vec<String> = kasten
+            .search(query)
+            .unwrap()
+            .iter()
+            .map(|z| z.borrow().title().to_string())
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn test_search_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a", "2023-01-10", "Links to [B](@/b.md).");
+        write(dir.path(), "b", "2023-06-01", "See https://example.com/x.");
+        write(
+            dir.path(),
+            "c",
+            "2023-03-01",
+            "[B](@/b.md) and [A](@/a.md).",
+        );
+        let kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+
+        assert_eq!(titles(&kasten, "created:>2023-02"), ["b", "c"]);
+        assert_eq!(titles(&kasten, "created:<2023-03-01"), ["a"]);
+        assert_eq!(titles(&kasten, "links-to:b.md"), ["a", "c"]);
+        assert_eq!(titles(&kasten, "orphan:true"), ["c"]);
+        assert_eq!(titles(&kasten, "orphan:false"), ["a", "b"]);
+        assert_eq!(titles(&kasten, "NOT links-to:a.md"), ["a", "b"]);
+        assert_eq!(titles(&kasten, "-created:2023-06 -orphan:true"), ["a"]);
+        assert_eq!(
+            titles(&kasten, "https://example.com/x created:2023"),
+            ["b"]
+        );
+    }
+
+    #[test]
+    fn test_insert_reindexes_link_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "b", "2023-06-01", "Nothing here.");
+        fs::create_dir(dir.path().join("templates")).unwrap();
+        let template = dir.path().join("templates/link.md");
+        fs::write(template, "{{clipboard}}\n").unwrap();
+        let mut kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+        assert_eq!(titles(&kasten, "orphan:true"), ["b"]);
+
+        let link = Some("[B](@/b.md)");
+        kasten
+            .create_from_template("New", Some("link"), link)
+            .unwrap();
+        assert_eq!(titles(&kasten, "orphan:true"), ["New"]);
+        assert_eq!(titles(&kasten, "links-to:b.md"), ["New"]);
+        let b = kasten.get("b.md").unwrap();
+        assert_eq!(kasten.iter_backlinks(&b.borrow()).len(), 1);
+    }
+}
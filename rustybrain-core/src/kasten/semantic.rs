@@ -0,0 +1,174 @@
+#[cfg(not(feature = "semantic"))]
+use std::{cell::RefCell, rc::Rc};
+
+use super::Kasten;
+#[cfg(not(feature = "semantic"))]
+use crate::zettel::Zettel;
+
+#[cfg(feature = "semantic")]
+pub(super) use enabled::State;
+
+impl Kasten {
+    /// Whether notes can be searched by meaning, the app is built with the
+    /// `semantic` feature and it is enabled in the config.
+    pub fn semantic_enabled(&self) -> bool {
+        cfg!(feature = "semantic") && self.config.borrow().semantic().enabled()
+    }
+}
+
+#[cfg(not(feature = "semantic"))]
+impl Kasten {
+    /// Zettels closest in meaning to the zettel, closest first.
+    pub fn similar(
+        &self,
+        _zettel: &Zettel,
+        _limit: usize,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        anyhow::bail!("rustybrain is built without the semantic feature")
+    }
+
+    /// Zettels closest in meaning to the query, closest first.
+    pub fn semantic_search(
+        &self,
+        _query: &str,
+        _limit: usize,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        anyhow::bail!("rustybrain is built without the semantic feature")
+    }
+}
+
+#[cfg(feature = "semantic")]
+mod enabled {
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+
+    use super::Kasten;
+    use crate::config::ConfigLoader;
+    use crate::semantic::{self, Embedder, Embeddings};
+    use crate::zettel::Zettel;
+
+    /// File of the vectors of the notes, relative to the repo.
+    const EMBEDDINGS_FILE: &str = ".rustybrain/embeddings.json";
+
+    /// Model and vectors, loaded on the first semantic search.
+    pub struct State {
+        embedder: Embedder,
+        embeddings: Embeddings,
+    }
+
+    impl Kasten {
+        /// Zettels closest in meaning to the zettel, closest first.
+        pub fn similar(
+            &self,
+            zettel: &Zettel,
+            limit: usize,
+        ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+            let zid = zettel.zid().to_string();
+            let hash = text_hash(zettel);
+            self.with_semantic(|state| {
+                let nearest = match state.embeddings.get(&zid, hash) {
+                    Some(v) => state.embeddings.nearest(v, limit + 1),
+                    None => vec![],
+                };
+                Ok(self.to_zettels(nearest, Some(&zid), limit))
+            })
+        }
+
+        /// Zettels closest in meaning to the query, closest first.
+        pub fn semantic_search(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+            self.with_semantic(|state| {
+                let v = state.embedder.embed(query)?;
+                let nearest = state.embeddings.nearest(&v, limit);
+                Ok(self.to_zettels(nearest, None, limit))
+            })
+        }
+
+        fn to_zettels(
+            &self,
+            nearest: Vec<(String, f32)>,
+            skip: Option<&str>,
+            limit: usize,
+        ) -> Vec<Rc<RefCell<Zettel>>> {
+            nearest
+                .into_iter()
+                .filter(|(zid, _)| Some(zid.as_str()) != skip)
+                .filter_map(|(zid, _)| self.get(&zid))
+                .take(limit)
+                .collect()
+        }
+
+        /// Run `f` with the model and the vectors of all the zettels, the
+        /// model is loaded and the changed zettels are embedded first.
+        fn with_semantic<T, F>(&self, f: F) -> Result<T, anyhow::Error>
+        where
+            F: FnOnce(&mut State) -> Result<T, anyhow::Error>,
+        {
+            let config = self.config.borrow().semantic().clone();
+            if !config.enabled() {
+                anyhow::bail!("semantic search is not enabled in the config");
+            }
+            let mut state = self.semantic.borrow_mut();
+            if state.is_none() {
+                let dir = ConfigLoader::new().dir().join(config.model());
+                let embedder = Embedder::load(&dir).map_err(|e| {
+                    anyhow::anyhow!(
+                        "load the model in {} failed: {}",
+                        dir.display(),
+                        e
+                    )
+                })?;
+                let embeddings = Embeddings::load(&self.embeddings_path())?;
+                *state = Some(State {
+                    embedder,
+                    embeddings,
+                });
+            }
+            let state = state.as_mut().unwrap();
+            self.update_embeddings(state)?;
+            f(state)
+        }
+
+        /// Embed the zettels that changed since they were embedded.
+        fn update_embeddings(
+            &self,
+            state: &mut State,
+        ) -> Result<(), anyhow::Error> {
+            let mut changed = false;
+            let mut zids = HashSet::new();
+            for z in self.zettels.iter() {
+                let z = z.borrow();
+                let hash = text_hash(&z);
+                zids.insert(z.zid().to_string());
+                if state.embeddings.get(z.zid(), hash).is_none() {
+                    let v = state.embedder.embed(&text(&z))?;
+                    state.embeddings.insert(z.zid(), hash, v);
+                    changed = true;
+                }
+            }
+            changed |= state.embeddings.retain(|zid| zids.contains(zid));
+            if changed {
+                state.embeddings.save(&self.embeddings_path())?;
+            }
+            Ok(())
+        }
+
+        fn embeddings_path(&self) -> PathBuf {
+            Path::new(&self.repo_path()).join(EMBEDDINGS_FILE)
+        }
+    }
+
+    fn text(z: &Zettel) -> String {
+        format!("{}\n\n{}", z.title(), z.content())
+    }
+
+    /// Hash of the embedded text, a vector is stale once it differs.
+    fn text_hash(z: &Zettel) -> u64 {
+        semantic::text_hash(&text(z))
+    }
+}
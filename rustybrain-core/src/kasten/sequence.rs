@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::Kasten;
+use crate::config::IdScheme;
+use crate::id;
+use crate::zettel::Zettel;
+
+/// A zettel in the sequence tree with its children in order.
+#[derive(Clone)]
+pub struct SequenceNode {
+    zettel: Rc<RefCell<Zettel>>,
+    children: Vec<SequenceNode>,
+}
+
+impl SequenceNode {
+    pub fn zettel(&self) -> &Rc<RefCell<Zettel>> {
+        &self.zettel
+    }
+
+    pub fn children(&self) -> &[SequenceNode] {
+        &self.children
+    }
+}
+
+type Sequences = HashMap<Option<String>, Vec<Rc<RefCell<Zettel>>>>;
+
+impl Kasten {
+    /// Parent of the zettel in its sequence.
+    pub fn parent(&self, z: &Zettel) -> Option<Rc<RefCell<Zettel>>> {
+        let zids = self.zids();
+        Self::parent_zid(z, &zids).and_then(|zid| self.get(&zid))
+    }
+
+    /// Children of the zettel in order.
+    pub fn children(&self, z: &Zettel) -> Vec<Rc<RefCell<Zettel>>> {
+        self.sequences()
+            .remove(&Some(z.zid().to_string()))
+            .unwrap_or_default()
+    }
+
+    /// Zettels that share the parent with the zettel in order, including
+    /// itself.
+    pub fn siblings(&self, z: &Zettel) -> Vec<Rc<RefCell<Zettel>>> {
+        let zids = self.zids();
+        self.sequences()
+            .remove(&Self::parent_zid(z, &zids))
+            .unwrap_or_default()
+    }
+
+    /// Next free folgezettel id under the parent, or at the top level if
+    /// `parent` is `None`. `None` is returned if the parent is not
+    /// identified by a folgezettel id.
+    pub fn next_id(&self, parent: Option<&Zettel>) -> Option<String> {
+        let parent = match parent {
+            Some(p) => Some(p.id().filter(|id| id::is_folgezettel(id))?),
+            None => None,
+        };
+        let ids: Vec<String> = self
+            .zettels
+            .iter()
+            .filter_map(|z| z.borrow().id().map(|s| s.to_string()))
+            .collect();
+        Some(id::folgezettel_next(parent, ids.iter().map(|s| s.as_str())))
+    }
+
+    /// Create a zettel as the last child of the parent, it gets the next
+    /// folgezettel id if that is the configured scheme.
+    pub fn create_in_sequence(
+        &mut self,
+        title: &str,
+        parent: Option<&Zettel>,
+    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+        let folgezettel =
+            self.config.borrow().note().id() == IdScheme::Folgezettel;
+        let id = match self.next_id(parent).filter(|_| folgezettel) {
+            Some(id) => id,
+            None => self.new_id(title),
+        };
+        let parent_zid = parent.map(|p| p.zid().to_string());
+        let implied = id::is_folgezettel(&id)
+            && id::folgezettel_parent(&id).map(|p| format!("@/{}", p))
+                == parent_zid;
+        let parent_zid = if implied {
+            None
+        } else {
+            Some(parent_zid.unwrap_or_default())
+        };
+        self.create_with_id(&id, title, parent_zid.as_deref(), None, None)
+    }
+
+    /// Zettels that are in sequences as a tree.
+    ///
+    /// A zettel is in a sequence if it has a parent or children, has a
+    /// folgezettel id, or has the `parent` header set.
+    pub fn sequence_tree(&self) -> Vec<SequenceNode> {
+        let mut sequences = self.sequences();
+        let roots = sequences.remove(&None).unwrap_or_default();
+        roots
+            .into_iter()
+            .map(|z| Self::sequence_node(z, &mut sequences))
+            .collect()
+    }
+
+    /// Move the zettel to `index` among the children of `parent`, or the top
+    /// level if `parent` is `None`, the positions of the new siblings are
+    /// rewritten.
+    pub fn move_in_sequence(
+        &mut self,
+        zid: &str,
+        parent: Option<&str>,
+        index: usize,
+    ) -> Result<(), anyhow::Error> {
+        let z = self
+            .get(zid)
+            .ok_or_else(|| anyhow::anyhow!("note {} not found", zid))?;
+        let zid = z.borrow().zid().to_string();
+        let parent = match parent {
+            Some(p) => Some(
+                self.get(p)
+                    .ok_or_else(|| anyhow::anyhow!("note {} not found", p))?,
+            ),
+            None => None,
+        };
+        let parent_zid = parent.as_ref().map(|p| p.borrow().zid().to_string());
+
+        // Parents read from the headers may already form a cycle.
+        let zids = self.zids();
+        let mut visited = HashSet::new();
+        let mut cur = parent_zid.clone();
+        while let Some(p) = cur {
+            if p == zid {
+                return Err(anyhow::anyhow!("can't move a note under itself"));
+            }
+            if !visited.insert(p.clone()) {
+                break;
+            }
+            cur = self
+                .get(&p)
+                .and_then(|z| Self::parent_zid(&z.borrow(), &zids));
+        }
+
+        let mut siblings =
+            self.sequences().remove(&parent_zid).unwrap_or_default();
+        siblings.retain(|s| s.borrow().zid() != zid);
+        siblings.insert(index.min(siblings.len()), z.clone());
+        z.borrow_mut()
+            .set_parent(Some(parent_zid.as_deref().unwrap_or("")));
+        for (i, s) in siblings.iter().enumerate() {
+            let mut s = s.borrow_mut();
+            s.set_position(Some(i as i64));
+            s.save()?;
+            self.commit(&s, &format!("Move {}", s.title()))?;
+        }
+        self.build()
+    }
+
+    fn zids(&self) -> HashSet<String> {
+        self.zettels
+            .iter()
+            .map(|z| z.borrow().zid().to_string())
+            .collect()
+    }
+
+    /// Children of every zettel in order, keyed by the zid of the parent,
+    /// `None` for the zettels at the top level of sequences.
+    fn sequences(&self) -> Sequences {
+        let zids = self.zids();
+        let mut sequences: Sequences = HashMap::new();
+        for z in self.zettels.iter() {
+            let parent = Self::parent_zid(&z.borrow(), &zids);
+            sequences.entry(parent).or_default().push(z.clone());
+        }
+        for children in sequences.values_mut() {
+            children
+                .sort_by(|a, b| Self::sequence_cmp(&a.borrow(), &b.borrow()));
+        }
+        let has_children: HashSet<String> =
+            sequences.keys().flatten().cloned().collect();
+        if let Some(roots) = sequences.get_mut(&None) {
+            roots.retain(|z| {
+                let z = z.borrow();
+                z.parent().is_some()
+                    || z.id().map(id::is_folgezettel).unwrap_or(false)
+                    || has_children.contains(z.zid())
+            });
+        }
+        sequences
+    }
+
+    /// Zid of the parent, zettels with a missing parent are at the top level.
+    fn parent_zid(z: &Zettel, zids: &HashSet<String>) -> Option<String> {
+        let parent = match z.parent() {
+            Some("") => return None,
+            Some(p) if p.starts_with("@/") => p.to_string(),
+            Some(p) => format!("@/{}", p),
+            None => format!("@/{}", id::folgezettel_parent(z.id()?)?),
+        };
+        if parent != z.zid() && zids.contains(&parent) {
+            Some(parent)
+        } else {
+            None
+        }
+    }
+
+    fn sequence_cmp(a: &Zettel, b: &Zettel) -> Ordering {
+        let pos = |z: &Zettel| z.position().unwrap_or(i64::MAX);
+        pos(a)
+            .cmp(&pos(b))
+            .then_with(|| match (a.id(), b.id()) {
+                (Some(x), Some(y)) => id::folgezettel_cmp(x, y),
+                _ => Ordering::Equal,
+            })
+            .then_with(|| a.title().cmp(b.title()))
+    }
+
+    fn sequence_node(
+        z: Rc<RefCell<Zettel>>,
+        sequences: &mut Sequences,
+    ) -> SequenceNode {
+        let zid = z.borrow().zid().to_string();
+        let children = sequences
+            .remove(&Some(zid))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| Self::sequence_node(c, sequences))
+            .collect();
+        SequenceNode {
+            zettel: z,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::super::{temp_config, temp_kasten, Kasten};
+
+    const FOLGEZETTEL: &str = "[note]\nid = \"folgezettel\"\n";
+
+    fn titles(kasten: &Kasten, zid: &str) -> Vec<String> {
+        let z = kasten.get(zid).unwrap();
+        let children = kasten.children(&z.borrow());
+        children
+            .iter()
+            .map(|c| c.borrow().title().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_create_in_sequence_and_next_id() {
+        let (_dir, mut kasten) = temp_kasten(FOLGEZETTEL);
+        let one = kasten.create("One").unwrap();
+        kasten.create("Two").unwrap();
+        assert_eq!(one.borrow().id(), Some("1"));
+
+        let child = kasten.create_in_sequence("Child", Some(&one.borrow()));
+        let child = child.unwrap();
+        assert_eq!(child.borrow().id(), Some("1a"));
+        assert_eq!(child.borrow().parent(), None);
+        assert_eq!(kasten.next_id(Some(&one.borrow())).as_deref(), Some("1b"));
+        assert_eq!(kasten.next_id(None).as_deref(), Some("3"));
+
+        let tree = kasten.sequence_tree();
+        let roots: Vec<String> = tree
+            .iter()
+            .map(|n| n.zettel().borrow().title().to_string())
+            .collect();
+        assert_eq!(roots, vec!["One", "Two"]);
+        assert_eq!(tree[0].children().len(), 1);
+        assert_eq!(titles(&kasten, "1"), vec!["Child"]);
+    }
+
+    #[test]
+    fn test_move_in_sequence() {
+        let (_dir, mut kasten) = temp_kasten(FOLGEZETTEL);
+        let one = kasten.create("One").unwrap();
+        kasten.create("Two").unwrap();
+        kasten.create_in_sequence("A", Some(&one.borrow())).unwrap();
+        kasten.create_in_sequence("B", Some(&one.borrow())).unwrap();
+        assert_eq!(titles(&kasten, "1"), vec!["A", "B"]);
+
+        kasten.move_in_sequence("2", Some("1"), 1).unwrap();
+        assert_eq!(titles(&kasten, "1"), vec!["A", "Two", "B"]);
+        let two = kasten.get("2").unwrap();
+        assert_eq!(two.borrow().parent(), Some("@/1"));
+        assert_eq!(two.borrow().position(), Some(1));
+
+        kasten.move_in_sequence("1b", Some("1"), 0).unwrap();
+        assert_eq!(titles(&kasten, "1"), vec!["B", "A", "Two"]);
+
+        assert!(kasten.move_in_sequence("1", Some("2"), 0).is_err());
+        assert!(kasten.move_in_sequence("1", Some("1"), 0).is_err());
+    }
+
+    #[test]
+    fn test_move_next_to_a_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, parent) in [("a", "b.md"), ("b", "a.md"), ("c", "")] {
+            let md = format!(
+                "+++\ntitle = \"{}\"\nparent = \"{}\"\n+++\n",
+                name, parent
+            );
+            fs::write(dir.path().join(format!("{}.md", name)), md).unwrap();
+        }
+        let mut kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+        kasten.move_in_sequence("c.md", Some("a.md"), 0).unwrap();
+        assert_eq!(titles(&kasten, "a.md"), vec!["c", "b"]);
+        assert!(kasten.move_in_sequence("a.md", Some("b.md"), 0).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use tantivy::query::MoreLikeThisQuery;
+use tantivy::schema::Value;
+
+use super::Kasten;
+use crate::zettel::Zettel;
+
+impl Kasten {
+    /// Zettels that share rare words with the body of the zettel, by the
+    /// BM25 score of its most telling terms, best first. The zettel itself
+    /// and the ones it already links to are left out.
+    pub fn suggested_links(
+        &self,
+        zettel: &Zettel,
+        limit: usize,
+    ) -> Result<Vec<Rc<RefCell<Zettel>>>, anyhow::Error> {
+        let mut skip: HashSet<&str> =
+            zettel.link_to_iter().map(|s| s.as_str()).collect();
+        skip.insert(zettel.zid());
+        let query = MoreLikeThisQuery::builder()
+            .with_min_doc_frequency(1)
+            .with_min_term_frequency(1)
+            .with_min_word_length(3)
+            .with_max_query_terms(25)
+            .with_document_fields(vec![(
+                self.body,
+                vec![Value::Str(zettel.content().to_string())],
+            )]);
+        let paths = self.top_paths(&query, limit + skip.len())?;
+        Ok(self
+            .zettels_of_paths(&paths)
+            .into_iter()
+            .filter(|z| !skip.contains(z.borrow().zid()))
+            .take(limit)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::super::{temp_config, Kasten};
+
+    #[test]
+    fn test_suggested_links() {
+        let dir = tempfile::tempdir().unwrap();
+        for (title, body) in [
+            (
+                "a",
+                "Ownership and lifetimes of the borrow checker, [B](@/b.md).",
+            ),
+            ("b", "The borrow checker checks lifetimes."),
+            ("c", "Ownership and lifetimes in Rust."),
+            ("d", "Tomato sauce for pasta."),
+        ] {
+            let md = format!("+++\ntitle = \"{}\"\n+++\n{}\n", title, body);
+            fs::write(dir.path().join(format!("{}.md", title)), md).unwrap();
+        }
+        let kasten = Kasten::new(temp_config(dir.path(), "")).unwrap();
+
+        let a = kasten.get("a.md").unwrap();
+        let titles: Vec<String> = kasten
+            .suggested_links(&a.borrow(), 5)
+            .unwrap()
+            .iter()
+            .map(|z| z.borrow().title().to_string())
+            .collect();
+        assert_eq!(titles, ["c"]);
+    }
+}
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::Kasten;
+use crate::config::IdScheme;
+use crate::template::{self, Template, Vars};
+use crate::zettel::Zettel;
+
+impl Kasten {
+    /// Templates in the templates directory of the repo.
+    pub fn templates(&self) -> Result<Vec<Template>, anyhow::Error> {
+        template::load(&self.templates_dir())
+    }
+
+    /// Create a zettel from the template, the template configured for the
+    /// folder is used when `template` is `None`.
+    pub fn create_from_template(
+        &mut self,
+        title: &str,
+        template: Option<&str>,
+        clipboard: Option<&str>,
+    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+        let id = self.new_id(title);
+        self.create_with_id(&id, title, None, template, clipboard)
+    }
+
+    /// Create a zettel with the id, set the parent of it in a sequence if
+    /// `parent` is given.
+    pub(super) fn create_with_id(
+        &mut self,
+        id: &str,
+        title: &str,
+        parent: Option<&str>,
+        template: Option<&str>,
+        clipboard: Option<&str>,
+    ) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+        let (id, path) = self.new_path(id)?;
+        if let Some(dir) = path.as_path().parent() {
+            create_dir_all(dir)?;
+        }
+        // Notes of the default scheme are linked by their paths, the id is
+        // only in the file name.
+        let id = match self.config.borrow().note().id() {
+            IdScheme::Timestamp => None,
+            _ => Some(id),
+        };
+        let mut z =
+            Zettel::create(&self.repo_path(), &path, id.as_deref(), title)?;
+        let name = match template {
+            Some(name) => Some(name.to_string()),
+            None => self.folder_template(&path),
+        };
+        if let Some(name) = name.as_ref() {
+            self.apply_template(&mut z, name, clipboard)?;
+        }
+        if parent.is_some() {
+            z.set_parent(parent);
+        }
+        if name.is_some() || parent.is_some() {
+            z.save()?;
+        }
+        self.insert(z)
+    }
+
+    pub(super) fn templates_dir(&self) -> PathBuf {
+        let c = self.config.borrow();
+        Path::new(c.repo_path()).join(c.template().dir())
+    }
+
+    /// Template configured for the folder that the path is in.
+    pub(super) fn folder_template(&self, path: &Path) -> Option<String> {
+        let c = self.config.borrow();
+        let folder = path
+            .parent()?
+            .strip_prefix(c.repo_path())
+            .ok()?
+            .to_str()?
+            .to_string();
+        c.template().for_folder(&folder).map(|s| s.to_string())
+    }
+
+    pub(super) fn apply_template(
+        &self,
+        z: &mut Zettel,
+        name: &str,
+        clipboard: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let path = self.templates_dir().join(format!("{}.md", name));
+        let template = Template::from_path(&path)?;
+        let vars = Vars {
+            title: z.title().to_string(),
+            date: z.date().map(|d| d.to_string()).unwrap_or_default(),
+            zid: z.zid().to_string(),
+            clipboard: clipboard.unwrap_or("").to_string(),
+        };
+        let (header, body) = template.render(&vars)?;
+        z.merge_header(&header)?;
+        z.set_content(&body)?;
+        Ok(())
+    }
+}
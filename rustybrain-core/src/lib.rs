@@ -1,6 +1,18 @@
 pub mod config;
+pub mod draft;
+pub mod fuzzy;
+pub mod git;
+pub mod highlight;
+pub mod id;
+pub mod import;
 pub mod kasten;
+pub mod math;
 pub mod md;
+pub mod position;
+pub mod query;
+#[cfg(feature = "semantic")]
+pub mod semantic;
+pub mod template;
 pub mod zettel;
 
 #[cfg(test)]
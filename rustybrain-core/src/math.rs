@@ -0,0 +1,390 @@
+//! Math between `$` or `$$` in the text of notes, rendered to Pango markup
+//! with Unicode symbols that the editor draws into a picture.
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+/// A formula in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Math {
+    range: Range<usize>,
+    tex: String,
+    display: bool,
+}
+
+impl Math {
+    /// Byte range of the formula including its delimiters.
+    pub fn range(&self) -> &Range<usize> {
+        &self.range
+    }
+
+    /// TeX source between the delimiters.
+    pub fn tex(&self) -> &str {
+        &self.tex
+    }
+
+    /// Whether it is between `$$`.
+    pub fn display(&self) -> bool {
+        self.display
+    }
+}
+
+/// Formulas in the text, the ranges are offset by `offset`.
+///
+/// An inline formula doesn't start or end by a space, doesn't span a blank
+/// line and its closing `$` isn't followed by a digit, so prices like
+/// `$5 and $6` are not math. Escaped `\$` is never a delimiter.
+pub fn find_math(text: &str, offset: usize) -> Vec<Math> {
+    let mut found = vec![];
+    let mut start = 0;
+    // The text after a `$$` that is not closed is scanned again for inline
+    // math, there is no `$$` left in it.
+    while let Some(open) = scan(text, start, offset, &mut found) {
+        start = open + 2;
+    }
+    found
+}
+
+/// Push the formulas from the byte `start` of the text, the `$$` that is
+/// not closed is returned.
+fn scan(
+    text: &str,
+    start: usize,
+    offset: usize,
+    found: &mut Vec<Math>,
+) -> Option<usize> {
+    // Byte index of the opening `$` and whether it is `$$`.
+    let mut open: Option<(usize, bool)> = None;
+    let mut prev = None;
+    let mut chars = text[start..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let i = start + i;
+        let before = prev;
+        prev = Some(c);
+        if c == '\n' && before == Some('\n') {
+            if let Some((_, false)) = open {
+                open = None;
+            }
+            continue;
+        }
+        if c != '$' || before == Some('\\') {
+            continue;
+        }
+
+        let next = chars.peek().map(|&(_, c)| c);
+        match open {
+            None if next == Some('$') => {
+                chars.next();
+                prev = next;
+                open = Some((i, true));
+            }
+            None => {
+                if next.map_or(false, |c| !c.is_whitespace()) {
+                    open = Some((i, false));
+                }
+            }
+            Some((o, true)) => {
+                if next == Some('$') {
+                    chars.next();
+                    prev = next;
+                    let tex = text[o + 2..i].trim();
+                    if !tex.is_empty() {
+                        found.push(Math {
+                            range: offset + o..offset + i + 2,
+                            tex: tex.to_string(),
+                            display: true,
+                        });
+                    }
+                    open = None;
+                }
+            }
+            Some((o, false)) => {
+                let spaced = before.map_or(true, char::is_whitespace);
+                let digit = next.map_or(false, |c| c.is_ascii_digit());
+                if !spaced && !digit {
+                    found.push(Math {
+                        range: offset + o..offset + i + 1,
+                        tex: text[o + 1..i].to_string(),
+                        display: false,
+                    });
+                    open = None;
+                }
+            }
+        }
+    }
+    match open {
+        Some((o, true)) => Some(o),
+        _ => None,
+    }
+}
+
+/// Pango markup of the TeX, commands are replaced by Unicode symbols and
+/// scripts are raised or lowered.
+///
+/// Unknown commands are kept as they are.
+pub fn to_markup(tex: &str) -> String {
+    let mut chars = tex.chars().peekable();
+    let mut markup = String::new();
+    while chars.peek().is_some() {
+        markup.push_str(&atom(&mut chars));
+    }
+    markup
+}
+
+/// Markup of the next atom, a group, a command or a character.
+fn atom(chars: &mut Peekable<Chars>) -> String {
+    let c = match chars.next() {
+        Some(c) => c,
+        None => return String::new(),
+    };
+    match c {
+        '{' => {
+            let mut group = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == '}' {
+                    chars.next();
+                    break;
+                }
+                group.push_str(&atom(chars));
+            }
+            group
+        }
+        '}' => String::new(),
+        '^' => format!("<sup>{}</sup>", atom(chars)),
+        '_' => format!("<sub>{}</sub>", atom(chars)),
+        '\\' => command(chars),
+        '~' => "\u{a0}".to_string(),
+        c => escape(c),
+    }
+}
+
+fn command(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_alphabetic() {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    if name.is_empty() {
+        return match chars.next() {
+            Some(',') => "\u{2009}".to_string(),
+            Some(':' | ';' | ' ') => " ".to_string(),
+            Some('!') => String::new(),
+            Some('\\') => "\n".to_string(),
+            Some(c) => escape(c),
+            None => "\\".to_string(),
+        };
+    }
+    // Spaces after a command name only end it.
+    while chars.peek() == Some(&' ') {
+        chars.next();
+    }
+    match name.as_str() {
+        "frac" | "dfrac" | "tfrac" => {
+            let (num, den) = (atom(chars), atom(chars));
+            format!("<sup>{}</sup>\u{2044}<sub>{}</sub>", num, den)
+        }
+        "sqrt" => {
+            let radicand = atom(chars);
+            format!("\u{221a}<span overline=\"single\">{}</span>", radicand)
+        }
+        "mathbf" | "boldsymbol" => format!("<b>{}</b>", atom(chars)),
+        "mathit" => format!("<i>{}</i>", atom(chars)),
+        "mathrm" | "text" | "textrm" | "mathbb" => atom(chars),
+        "operatorname" => atom(chars),
+        "left" | "right" => match chars.peek() {
+            Some('.') => {
+                chars.next();
+                String::new()
+            }
+            _ => atom(chars),
+        },
+        _ => match symbol(&name) {
+            Some(s) => s.to_string(),
+            None => format!("\\{}", name),
+        },
+    }
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    let s = match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "times" => "×",
+        "cdot" => "·",
+        "pm" => "±",
+        "mp" => "∓",
+        "div" => "÷",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "propto" => "∝",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "iint" => "∬",
+        "oint" => "∮",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "forall" => "∀",
+        "exists" => "∃",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "emptyset" | "varnothing" => "∅",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" => "⇔",
+        "implies" => "⟹",
+        "iff" => "⟺",
+        "mapsto" => "↦",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "circ" => "∘",
+        "prime" => "′",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "quad" => "\u{2003}",
+        "qquad" => "\u{2003}\u{2003}",
+        "sin" => "sin",
+        "cos" => "cos",
+        "tan" => "tan",
+        "log" => "log",
+        "ln" => "ln",
+        "exp" => "exp",
+        "lim" => "lim",
+        "max" => "max",
+        "min" => "min",
+        "det" => "det",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_math, to_markup};
+
+    #[test]
+    fn test_find_math() {
+        let text = "a $x^2$ b $$\\sum_i i$$ \\$y$ $ z$ costs $5 and $6.";
+        let found = find_math(text, 10);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].tex(), "x^2");
+        assert!(!found[0].display());
+        assert_eq!(found[0].range(), &(12..17));
+        assert_eq!(found[1].tex(), "\\sum_i i");
+        assert!(found[1].display());
+        assert_eq!(&text[found[1].range().start - 10..][..2], "$$");
+
+        assert!(find_math("$a\n\nb$ and $$", 0).is_empty());
+        let found = find_math("$$ open $b$", 0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].tex(), "b");
+    }
+
+    #[test]
+    fn test_find_math_non_ascii() {
+        assert!(find_math("价格 $x 是 中文", 0).is_empty());
+        assert!(find_math("The cost is $5 — cheap", 0).is_empty());
+        assert!(find_math("Costs $5 — or €6 $", 0).is_empty());
+
+        let text = "面积 $πr²$ 和 $$\\sum_{é} 1$$ — fin";
+        let found = find_math(text, 3);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].tex(), "πr²");
+        assert_eq!(&text[found[0].range().start - 3..][..1], "$");
+        assert_eq!(found[1].tex(), "\\sum_{é} 1");
+        let end = found[1].range().end - 3;
+        assert_eq!(&text[end - 2..end], "$$");
+        assert_eq!(&text[end..], " — fin");
+    }
+
+    #[test]
+    fn test_to_markup() {
+        assert_eq!(
+            to_markup("x^2 + \\alpha_{i}"),
+            "x<sup>2</sup> + α<sub>i</sub>"
+        );
+        assert_eq!(
+            to_markup("\\frac{a}{b} < \\sqrt x"),
+            "<sup>a</sup>\u{2044}<sub>b</sub> &lt; \
+             \u{221a}<span overline=\"single\">x</span>"
+        );
+        assert_eq!(to_markup("\\left( \\foo \\right."), "( \\foo");
+    }
+}
@@ -1,4 +1,8 @@
+use std::ops::Range;
+
+pub use tree_sitter::InputEdit;
 pub use tree_sitter::Node;
+pub use tree_sitter::Point;
 pub use tree_sitter::Tree;
 pub use tree_sitter::TreeCursor;
 
@@ -7,7 +11,361 @@ pub fn parse(
     old_tree: Option<&tree_sitter::Tree>,
 ) -> Result<Option<tree_sitter::Tree>, anyhow::Error> {
     let mut parser = tree_sitter::Parser::new();
-    parser.set_language(tree_sitter_markdown::language())?;
+    parser
+        .set_language(tree_sitter_markdown::language())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
     let tree = parser.parse(text, old_tree);
     Ok(tree)
 }
+
+/// Edit of inserting `text` at the byte offset and the point.
+pub fn insert_edit(start_byte: usize, start: Point, text: &str) -> InputEdit {
+    let new_end = match text.rfind('\n') {
+        Some(i) => Point::new(
+            start.row + text.matches('\n').count(),
+            text.len() - i - 1,
+        ),
+        None => Point::new(start.row, start.column + text.len()),
+    };
+    InputEdit {
+        start_byte,
+        old_end_byte: start_byte,
+        new_end_byte: start_byte + text.len(),
+        start_position: start,
+        old_end_position: start,
+        new_end_position: new_end,
+    }
+}
+
+/// Edit of deleting the text between the byte offsets and the points.
+pub fn delete_edit(
+    start_byte: usize,
+    start: Point,
+    end_byte: usize,
+    end: Point,
+) -> InputEdit {
+    InputEdit {
+        start_byte,
+        old_end_byte: end_byte,
+        new_end_byte: start_byte,
+        start_position: start,
+        old_end_position: end,
+        new_end_position: start,
+    }
+}
+
+/// Apply the edits on the old tree and parse the text incrementally.
+///
+/// Byte ranges of the new text that need to be restyled are returned, they
+/// are ranges that the syntax changed and the ranges of inserted text,
+/// sorted and merged.
+pub fn reparse(
+    text: &str,
+    old_tree: Option<Tree>,
+    edits: &[InputEdit],
+) -> Result<(Option<Tree>, Vec<Range<usize>>), anyhow::Error> {
+    let old_tree = old_tree.map(|mut t| {
+        for edit in edits {
+            t.edit(edit);
+        }
+        t
+    });
+    let tree = parse(text, old_tree.as_ref())?;
+    let mut ranges: Vec<Range<usize>> = match (old_tree.as_ref(), &tree) {
+        (Some(old), Some(new)) => old
+            .changed_ranges(new)
+            .map(|r| r.start_byte..r.end_byte)
+            .chain(edits.iter().map(|e| e.start_byte..e.new_end_byte))
+            .collect(),
+        _ => vec![0..text.len()],
+    };
+    Ok((tree, merge_ranges(&mut ranges, text.len())))
+}
+
+/// Sort ranges and merge the overlapping or adjacent ones, clamped to `len`.
+pub fn merge_ranges(
+    ranges: &mut [Range<usize>],
+    len: usize,
+) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = vec![];
+    for r in ranges.iter() {
+        let r = r.start.min(len)..r.end.min(len);
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// Pad the cells of a GFM table to align its columns, the delimiter row
+/// keeps the alignment of the columns.
+///
+/// The text is returned unchanged if it is not a table.
+pub fn format_table(text: &str) -> String {
+    let newline = if text.ends_with('\n') { "\n" } else { "" };
+    let rows: Vec<Vec<String>> = text
+        .lines()
+        .map(table_cells)
+        .filter(|r| !r.is_empty())
+        .collect();
+    let aligns: Vec<Option<Align>> = match rows.get(1) {
+        Some(delimiter) => match delimiter.iter().map(|c| align(c)).collect() {
+            Some(aligns) => aligns,
+            None => return text.to_string(),
+        },
+        None => return text.to_string(),
+    };
+
+    let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for (_, row) in rows.iter().enumerate().filter(|(i, _)| *i != 1) {
+        for (c, cell) in row.iter().enumerate() {
+            widths[c] = widths[c].max(cell.chars().count());
+        }
+    }
+
+    let mut lines = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|c| {
+                let a = aligns.get(c).copied().flatten();
+                let w = widths[c];
+                if i == 1 {
+                    return delimiter_cell(a, w);
+                }
+                let cell = row.get(c).map(|s| s.as_str()).unwrap_or("");
+                let pad = w - cell.chars().count();
+                match a {
+                    Some(Align::Right) => {
+                        format!("{}{}", " ".repeat(pad), cell)
+                    }
+                    Some(Align::Center) => format!(
+                        "{}{}{}",
+                        " ".repeat(pad / 2),
+                        cell,
+                        " ".repeat(pad - pad / 2)
+                    ),
+                    _ => format!("{}{}", cell, " ".repeat(pad)),
+                }
+            })
+            .collect();
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    lines.join("\n") + newline
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Trimmed cells of a table row, pipes escaped by `\\` don't split cells.
+fn table_cells(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(l) if !l.ends_with('\\') => l,
+        _ => line,
+    };
+    if line.is_empty() {
+        return vec![];
+    }
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+/// Alignment of a delimiter cell, `None` if the cell is not a delimiter.
+fn align(cell: &str) -> Option<Option<Align>> {
+    let left = cell.starts_with(':');
+    let right = cell.len() > 1 && cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (left, right) {
+        (true, true) => Some(Align::Center),
+        (true, false) => Some(Align::Left),
+        (false, true) => Some(Align::Right),
+        (false, false) => None,
+    })
+}
+
+fn delimiter_cell(align: Option<Align>, width: usize) -> String {
+    match align {
+        Some(Align::Left) => format!(":{}", "-".repeat(width - 1)),
+        Some(Align::Center) => format!(":{}:", "-".repeat(width - 2)),
+        Some(Align::Right) => format!("{}:", "-".repeat(width - 1)),
+        None => "-".repeat(width),
+    }
+}
+
+/// Split front-matter between `delimiter` lines from the body.
+pub fn split_front_matter<'a>(
+    content: &'a str,
+    delimiter: &str,
+) -> (Option<&'a str>, &'a str) {
+    let mut lines = content.split_inclusive('\n');
+    let start = match lines.next() {
+        Some(first) if first.trim_end() == delimiter => first.len(),
+        _ => return (None, content),
+    };
+    let mut offset = start;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            let front = &content[start..offset];
+            return (Some(front), &content[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// Link that is being typed at the end of a text, `[[title` or the target
+/// of `[text](@/path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkQuery {
+    start: usize,
+    query: String,
+    target: bool,
+}
+
+impl LinkQuery {
+    /// Byte offset of the `[[` or the `@/` in the text.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// What is typed after the `[[` or the `@/`.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Whether only the target is typed, the text of the link is there.
+    pub fn target(&self) -> bool {
+        self.target
+    }
+}
+
+/// Link that the text ends in the middle of, in its last line.
+pub fn link_query(text: &str) -> Option<LinkQuery> {
+    let line = &text[text.rfind('\n').map_or(0, |i| i + 1)..];
+    let offset = text.len() - line.len();
+    let title = line.rfind("[[").map(|i| (i, i + 2, false));
+    let target = line.rfind("](@/").map(|i| (i + 2, i + 4, true));
+    let (start, query_start, target) = match (title, target) {
+        (Some(a), Some(b)) => {
+            if a.0 > b.0 {
+                a
+            } else {
+                b
+            }
+        }
+        (a, b) => a.or(b)?,
+    };
+    let query = &line[query_start..];
+    let ended = if target {
+        query.contains(|c: char| c == ')' || c.is_whitespace())
+    } else {
+        query.contains(|c| c == '[' || c == ']')
+    };
+    if ended {
+        return None;
+    }
+    Some(LinkQuery {
+        start: offset + start,
+        query: query.to_string(),
+        target,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_table, insert_edit, link_query, merge_ranges};
+    use super::{parse, reparse, split_front_matter, Point};
+
+    #[test]
+    fn test_split_front_matter() {
+        let (front, body) = split_front_matter("---\na: 1\n---\nbody\n", "---");
+        assert_eq!(front, Some("a: 1\n"));
+        assert_eq!(body, "body\n");
+
+        let (front, body) = split_front_matter("no front-matter\n", "---");
+        assert_eq!(front, None);
+        assert_eq!(body, "no front-matter\n");
+    }
+
+    #[test]
+    fn test_insert_edit() {
+        let edit = insert_edit(3, Point::new(1, 2), "ab\ncde");
+        assert_eq!(edit.new_end_byte, 9);
+        assert_eq!(edit.new_end_position, Point::new(2, 3));
+
+        let edit = insert_edit(3, Point::new(1, 2), "中");
+        assert_eq!(edit.new_end_byte, 6);
+        assert_eq!(edit.new_end_position, Point::new(1, 5));
+    }
+
+    #[test]
+    fn test_reparse() {
+        let old = "# title\n\nsome text\n";
+        let new = "# title\n\nsome *text*\n";
+        let tree = parse(old, None).unwrap();
+        let edits = [
+            insert_edit(14, Point::new(2, 5), "*"),
+            insert_edit(19, Point::new(2, 10), "*"),
+        ];
+        let (tree, ranges) = reparse(new, tree, &edits).unwrap();
+        assert!(tree.is_some());
+        assert!(ranges.iter().any(|r| r.contains(&14)));
+        assert!(ranges.iter().any(|r| r.contains(&19)));
+        assert!(ranges.iter().all(|r| r.end <= new.len()));
+
+        let mut ranges = vec![5..8, 0..2, 1..3, 7..20];
+        assert_eq!(merge_ranges(&mut ranges, 10), vec![0..3, 5..10]);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let table = "|a|中文|c|\n|:-|:-:|--:|\n|long cell|x|\\| y|\n";
+        assert_eq!(
+            format_table(table),
+            "| a         | 中文  |    c |\n\
+             | :-------- | :-: | ---: |\n\
+             | long cell |  x  | \\| y |\n"
+        );
+        assert_eq!(format_table("a | b\n"), "a | b\n");
+        assert_eq!(format_table("| a |\n| b |"), "| a |\n| b |");
+    }
+
+    #[test]
+    fn test_link_query() {
+        let q = link_query("a\nsee [[rust bo").unwrap();
+        assert_eq!((q.start(), q.query(), q.target()), (6, "rust bo", false));
+
+        let q = link_query("[Rust](@/notes/ru").unwrap();
+        assert_eq!((q.start(), q.query(), q.target()), (7, "notes/ru", true));
+
+        let q = link_query("[[a]] [b](@/").unwrap();
+        assert_eq!((q.start(), q.query(), q.target()), (10, "", true));
+
+        assert_eq!(link_query("[[a]] done"), None);
+        assert_eq!(link_query("[b](@/c.md) [[x\ny"), None);
+        assert_eq!(link_query("[b](@/c d"), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! Mapping positions in a text between byte offsets, character offsets and
+//! lines.
+//!
+//! Tree-sitter counts bytes while GTK text buffers count characters, they
+//! differ as soon as a note has non-ASCII text. Buffers also count one
+//! character for every embedded object such as an image, which is not in
+//! the text.
+use tree_sitter::Point;
+
+/// Index of lines in a text for mapping positions.
+///
+/// Offsets that are out of the text are clamped to its end, byte offsets in
+/// the middle of a character are moved to the start of it.
+pub struct Positions<'a> {
+    text: &'a str,
+    /// Byte and character offset of the start of every line.
+    lines: Vec<(usize, usize)>,
+    /// Character offsets of the text that an object is embedded before,
+    /// sorted.
+    objects: Vec<usize>,
+}
+
+impl<'a> Positions<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut lines = vec![(0, 0)];
+        let mut chars = 0;
+        for (i, c) in text.char_indices() {
+            chars += 1;
+            if c == '\n' {
+                lines.push((i + 1, chars));
+            }
+        }
+        Positions {
+            text,
+            lines,
+            objects: vec![],
+        }
+    }
+
+    /// Positions of the text with objects embedded before the character
+    /// offsets, character offsets count the objects then.
+    pub fn with_objects(text: &'a str, mut objects: Vec<usize>) -> Self {
+        objects.sort_unstable();
+        Positions {
+            objects,
+            ..Self::new(text)
+        }
+    }
+
+    /// Character offset of the byte offset.
+    pub fn byte_to_char(&self, byte: usize) -> usize {
+        let byte = self.floor(byte);
+        let (start, chars) = self.lines[self.line_of_byte(byte)];
+        let char = chars + self.text[start..byte].chars().count();
+        char + self.objects.partition_point(|o| *o <= char)
+    }
+
+    /// Byte offset of the character offset, an offset of an object is
+    /// mapped to the text after it.
+    pub fn char_to_byte(&self, char: usize) -> usize {
+        // The object `i` is at `objects[i] + i` counting the objects.
+        let before = self
+            .objects
+            .iter()
+            .enumerate()
+            .take_while(|(i, o)| *o + i < char)
+            .count();
+        let char = char - before;
+        let line = match self.lines.binary_search_by_key(&char, |l| l.1) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let (start, chars) = self.lines[line];
+        self.text[start..]
+            .char_indices()
+            .nth(char - chars)
+            .map(|(i, _)| start + i)
+            .unwrap_or(self.text.len())
+    }
+
+    /// Line and character column of the byte offset, objects are not
+    /// counted.
+    pub fn byte_to_line_col(&self, byte: usize) -> (usize, usize) {
+        let byte = self.floor(byte);
+        let line = self.line_of_byte(byte);
+        let start = self.lines[line].0;
+        (line, self.text[start..byte].chars().count())
+    }
+
+    /// Byte offset of the line and character column, the column is clamped
+    /// to the end of the line.
+    pub fn line_col_to_byte(&self, line: usize, col: usize) -> usize {
+        let start = match self.lines.get(line) {
+            Some(l) => l.0,
+            None => return self.text.len(),
+        };
+        let end = self.line_end(line);
+        self.text[start..end]
+            .char_indices()
+            .nth(col)
+            .map(|(i, _)| start + i)
+            .unwrap_or(end)
+    }
+
+    /// Tree-sitter point of the byte offset, its column is in bytes.
+    pub fn byte_to_point(&self, byte: usize) -> Point {
+        let byte = self.floor(byte);
+        let line = self.line_of_byte(byte);
+        Point::new(line, byte - self.lines[line].0)
+    }
+
+    /// Byte offset of the tree-sitter point.
+    pub fn point_to_byte(&self, point: Point) -> usize {
+        match self.lines.get(point.row) {
+            Some(l) => {
+                self.floor((l.0 + point.column).min(self.line_end(point.row)))
+            }
+            None => self.text.len(),
+        }
+    }
+
+    /// Character offset of the tree-sitter point.
+    pub fn point_to_char(&self, point: Point) -> usize {
+        self.byte_to_char(self.point_to_byte(point))
+    }
+
+    fn line_of_byte(&self, byte: usize) -> usize {
+        match self.lines.binary_search_by_key(&byte, |l| l.0) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        }
+    }
+
+    /// Byte offset of the end of the line, before its newline.
+    fn line_end(&self, line: usize) -> usize {
+        match self.lines.get(line + 1) {
+            Some(next) => next.0 - 1,
+            None => self.text.len(),
+        }
+    }
+
+    fn floor(&self, byte: usize) -> usize {
+        let mut byte = byte.min(self.text.len());
+        while !self.text.is_char_boundary(byte) {
+            byte -= 1;
+        }
+        byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Positions;
+    use tree_sitter::Point;
+
+    #[test]
+    fn test_ascii() {
+        let p = Positions::new("ab\ncd");
+        assert_eq!(p.byte_to_char(4), 4);
+        assert_eq!(p.char_to_byte(4), 4);
+        assert_eq!(p.byte_to_line_col(4), (1, 1));
+        assert_eq!(p.line_col_to_byte(1, 1), 4);
+        assert_eq!(p.byte_to_point(4), Point::new(1, 1));
+    }
+
+    #[test]
+    fn test_multi_byte() {
+        // `中` is 3 bytes, `é` is 2 bytes and `🦀` is 4 bytes.
+        let text = "# 中文\n\ncafé *🦀* end\n";
+        let p = Positions::new(text);
+
+        let star = text.find('*').unwrap();
+        assert_eq!(star, 16);
+        assert_eq!(p.byte_to_char(star), 11);
+        assert_eq!(p.char_to_byte(11), star);
+        assert_eq!(p.byte_to_line_col(star), (2, 5));
+        assert_eq!(p.line_col_to_byte(2, 5), star);
+        assert_eq!(p.byte_to_point(star), Point::new(2, 6));
+        assert_eq!(p.point_to_byte(Point::new(2, 6)), star);
+        assert_eq!(p.point_to_char(Point::new(2, 6)), 11);
+
+        let end = text.find(" end").unwrap();
+        assert_eq!(p.byte_to_char(end), 14);
+        assert_eq!(p.char_to_byte(14), end);
+
+        // In the middle of `中`.
+        assert_eq!(p.byte_to_char(3), 2);
+        assert_eq!(p.byte_to_char(text.len()), text.chars().count());
+        assert_eq!(p.char_to_byte(100), text.len());
+        assert_eq!(p.line_col_to_byte(0, 100), "# 中文".len());
+        assert_eq!(p.line_col_to_byte(9, 0), text.len());
+    }
+
+    #[test]
+    fn test_objects() {
+        // Objects after `![a](x)` and at the end, `|` marks them:
+        // `é ![a](x)|\nb|`
+        let text = "é ![a](x)\nb";
+        let p = Positions::with_objects(text, vec![9, 11]);
+        assert_eq!(p.byte_to_char(0), 0);
+        assert_eq!(p.byte_to_char(10), 10);
+        assert_eq!(p.byte_to_char(11), 11);
+        assert_eq!(p.byte_to_char(text.len()), 13);
+
+        assert_eq!(p.char_to_byte(9), 10);
+        assert_eq!(p.char_to_byte(10), 10);
+        assert_eq!(p.char_to_byte(11), 11);
+        assert_eq!(p.char_to_byte(12), text.len());
+        assert_eq!(p.char_to_byte(13), text.len());
+    }
+}
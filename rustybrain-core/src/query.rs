@@ -0,0 +1,593 @@
+//! Query language of the search.
+//!
+//! Words are searched in the title and the body, a note has to match all of
+//! them. Other than that a query can have:
+//!
+//! - `"a phrase"`, the words in that order.
+//! - `title:`, `body:` and `path:` to search a word or phrase in one field.
+//! - `tag:rust`, notes with the tag.
+//! - `created:2023-01-01`, notes of a date in the header, `>`, `>=`, `<`,
+//!   `<=` before the date and ranges `2023-01..2023-03` compare it. A date
+//!   can be a year, a month or a day.
+//! - `links-to:notes/a.md`, notes that link to the zid.
+//! - `orphan:true`, notes that no note links to.
+//! - `OR` between two parts, `AND` is the default.
+//! - `-` or `NOT` before a part to exclude it.
+//! - Parentheses to group parts.
+//!
+//! Parsing is lenient, a missing quote or parenthesis is closed at the end
+//! and stray operators are ignored. A `name:` that is not a field is part of
+//! the words, like in `http://example.com`. Only the values of the fields
+//! that can't be understood are errors.
+use std::fmt;
+use std::ops::Range;
+
+use chrono::{Datelike, NaiveDate};
+
+/// Text field searched by the words of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    Title,
+    Body,
+    Path,
+}
+
+/// Days from `from` until `until`, an end that is `None` is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    from: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// First day in the range.
+    pub fn from(&self) -> Option<NaiveDate> {
+        self.from
+    }
+
+    /// First day after the range.
+    pub fn until(&self) -> Option<NaiveDate> {
+        self.until
+    }
+}
+
+/// Parsed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// Words in the field, in the default fields of the search if `None`.
+    /// The words have to be in order if it is a phrase.
+    Text {
+        field: Option<TextField>,
+        text: String,
+        phrase: bool,
+    },
+    Tag(String),
+    Created(DateRange),
+    LinksTo(String),
+    Orphan(bool),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+impl Query {
+    /// Whether the query is more than words to search, the ones that a
+    /// plain search treats as is.
+    pub fn is_structured(&self) -> bool {
+        match self {
+            Query::Text {
+                field: None,
+                phrase: false,
+                ..
+            } => false,
+            Query::And(qs) => qs.iter().any(Query::is_structured),
+            _ => true,
+        }
+    }
+}
+
+/// Part of a query that can't be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    message: String,
+    range: Range<usize>,
+    column: usize,
+}
+
+impl QueryError {
+    fn new(query: &str, range: Range<usize>, message: String) -> Self {
+        let column = query[..range.start].chars().count() + 1;
+        QueryError {
+            message,
+            range,
+            column,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte range of the part in the query.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Column of the part in the query, from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Parse the query, `None` if it has nothing to search.
+pub fn parse(query: &str) -> Result<Option<Query>, QueryError> {
+    let tokens = tokenize(query);
+    let mut parser = Parser {
+        query,
+        tokens,
+        pos: 0,
+    };
+    let mut parts = vec![];
+    while parser.pos < parser.tokens.len() {
+        match parser.or()? {
+            Some(q) => parts.push(q),
+            // A stray `)` or `OR`.
+            None => parser.pos += 1,
+        }
+    }
+    Ok(join(parts, Query::And))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term {
+        field: Option<(String, Range<usize>)>,
+        value: String,
+        range: Range<usize>,
+        quoted: bool,
+        negated: bool,
+    },
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+                continue;
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+                continue;
+            }
+            _ => {}
+        }
+
+        let mut negated = false;
+        let mut value_start = start;
+        if c == '-' {
+            chars.next();
+            match chars.peek() {
+                Some(&(i, c)) if !c.is_whitespace() && c != ')' => {
+                    negated = true;
+                    value_start = i;
+                }
+                _ => continue,
+            }
+        }
+
+        let mut field = None;
+        let mut quoted = false;
+        let mut value = String::new();
+        let mut end = value_start;
+        if let Some(&(_, '"')) = chars.peek() {
+            chars.next();
+            quoted = true;
+        }
+        while let Some(&(i, c)) = chars.peek() {
+            if quoted && c == '"' {
+                chars.next();
+                end = i + 1;
+                break;
+            }
+            if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                break;
+            }
+            chars.next();
+            end = i + c.len_utf8();
+            if !quoted && c == ':' && field.is_none() && is_field_name(&value) {
+                field = Some((value.clone(), value_start..i));
+                value.clear();
+                if let Some(&(_, '"')) = chars.peek() {
+                    chars.next();
+                    quoted = true;
+                }
+                continue;
+            }
+            value.push(c);
+        }
+        if field.is_none() && !quoted && !negated {
+            let keyword = match value.as_str() {
+                "AND" => Some(Token::And),
+                "OR" => Some(Token::Or),
+                "NOT" => Some(Token::Not),
+                _ => None,
+            };
+            if let Some(keyword) = keyword {
+                tokens.push(keyword);
+                continue;
+            }
+        }
+        tokens.push(Token::Term {
+            field,
+            value,
+            range: value_start..end.max(value_start),
+            quoted,
+            negated,
+        });
+    }
+    tokens
+}
+
+fn is_field_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphabetic() || c == '-')
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Option<Query>, QueryError> {
+        let mut parts = vec![];
+        parts.extend(self.and()?);
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            parts.extend(self.and()?);
+        }
+        Ok(join(parts, Query::Or))
+    }
+
+    fn and(&mut self) -> Result<Option<Query>, QueryError> {
+        let mut parts = vec![];
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                Some(Token::And) => self.pos += 1,
+                Some(_) => parts.extend(self.unary()?),
+            }
+        }
+        Ok(join(parts, Query::And))
+    }
+
+    fn unary(&mut self) -> Result<Option<Query>, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => Ok(None),
+                _ => Ok(self.unary()?.map(|q| Query::Not(Box::new(q)))),
+            };
+        }
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                let q = self.or()?;
+                if self.peek() == Some(&Token::Close) {
+                    self.pos += 1;
+                }
+                Ok(q)
+            }
+            Some(Token::Term {
+                field,
+                value,
+                range,
+                quoted,
+                negated,
+            }) => {
+                self.pos += 1;
+                let q = self.term(field, value, range, quoted)?;
+                Ok(if negated {
+                    q.map(|q| Query::Not(Box::new(q)))
+                } else {
+                    q
+                })
+            }
+            _ => {
+                self.pos += 1;
+                Ok(None)
+            }
+        }
+    }
+
+    fn term(
+        &self,
+        field: Option<(String, Range<usize>)>,
+        value: String,
+        range: Range<usize>,
+        quoted: bool,
+    ) -> Result<Option<Query>, QueryError> {
+        let text = |field, text: &str| {
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(Query::Text {
+                field,
+                text: text.to_string(),
+                phrase: quoted,
+            })
+        };
+        let (name, name_range) = match field {
+            Some(field) if !value.is_empty() => field,
+            // A word that ends with a colon, like `Note:`.
+            Some(_) => return Ok(text(None, &self.query[range])),
+            None => return Ok(text(None, &value)),
+        };
+        let value_range = name_range.end + 1..range.end;
+        let q = match name.to_lowercase().as_str() {
+            "title" => text(Some(TextField::Title), &value),
+            "body" => text(Some(TextField::Body), &value),
+            "path" => text(Some(TextField::Path), &value),
+            "tag" => {
+                Some(Query::Tag(value.trim_start_matches('#').to_lowercase()))
+            }
+            "links-to" => Some(Query::LinksTo(if value.starts_with("@/") {
+                value
+            } else {
+                format!("@/{}", value)
+            })),
+            "created" => {
+                Some(Query::Created(self.date_range(&value, value_range)?))
+            }
+            "orphan" => match value.to_lowercase().as_str() {
+                "true" | "yes" => Some(Query::Orphan(true)),
+                "false" | "no" => Some(Query::Orphan(false)),
+                _ => {
+                    return Err(self.error(
+                        value_range,
+                        format!("`{}` is not true or false", value),
+                    ))
+                }
+            },
+            // Not a field, like the scheme of a URL.
+            _ => text(None, &format!("{}:{}", name, value)),
+        };
+        Ok(q)
+    }
+
+    fn date_range(
+        &self,
+        value: &str,
+        range: Range<usize>,
+    ) -> Result<DateRange, QueryError> {
+        let day = |s: &str, offset: usize| {
+            parse_period(s).ok_or_else(|| {
+                let start = range.start + offset;
+                self.error(
+                    start..start + s.len(),
+                    format!(
+                        "invalid date `{}`, expected YYYY, YYYY-MM or \
+                         YYYY-MM-DD",
+                        s
+                    ),
+                )
+            })
+        };
+        if let Some((a, b)) = value.split_once("..") {
+            let from = match a {
+                "" => None,
+                a => Some(day(a, 0)?.0),
+            };
+            let until = match b {
+                "" => None,
+                b => Some(day(b, a.len() + 2)?.1),
+            };
+            return Ok(DateRange { from, until });
+        }
+        for op in [">=", "<=", ">", "<", "="] {
+            if let Some(s) = value.strip_prefix(op) {
+                let (start, next) = day(s, op.len())?;
+                let (from, until) = match op {
+                    ">=" => (Some(start), None),
+                    "<=" => (None, Some(next)),
+                    ">" => (Some(next), None),
+                    "<" => (None, Some(start)),
+                    _ => (Some(start), Some(next)),
+                };
+                return Ok(DateRange { from, until });
+            }
+        }
+        let (start, next) = day(value, 0)?;
+        Ok(DateRange {
+            from: Some(start),
+            until: Some(next),
+        })
+    }
+
+    fn error(&self, range: Range<usize>, message: String) -> QueryError {
+        QueryError::new(self.query, range, message)
+    }
+}
+
+/// First day of the year, month or day, and the first day after it.
+fn parse_period(s: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = s.split('-').collect();
+    let num = |i: usize| -> Option<u32> {
+        let p = parts.get(i)?;
+        if p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        p.parse().ok()
+    };
+    let year = num(0)? as i32;
+    match parts.len() {
+        1 => Some((
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        )),
+        2 => {
+            let start = NaiveDate::from_ymd_opt(year, num(1)?, 1)?;
+            let next = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(year, start.month() + 1, 1)?
+            };
+            Some((start, next))
+        }
+        3 => {
+            let start = NaiveDate::from_ymd_opt(year, num(1)?, num(2)?)?;
+            Some((start, start.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+fn join(mut parts: Vec<Query>, f: fn(Vec<Query>) -> Query) -> Option<Query> {
+    match parts.len() {
+        0 => None,
+        1 => parts.pop(),
+        _ => Some(f(parts)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{parse, DateRange, Query, TextField};
+
+    fn text(field: Option<TextField>, text: &str, phrase: bool) -> Query {
+        Query::Text {
+            field,
+            text: text.to_string(),
+            phrase,
+        }
+    }
+
+    fn day(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("  ").unwrap(), None);
+        let q = parse("rust book").unwrap().unwrap();
+        assert!(!q.is_structured());
+        assert_eq!(
+            q,
+            Query::And(vec![
+                text(None, "rust", false),
+                text(None, "book", false)
+            ])
+        );
+
+        let q = parse(r#"title:"rust book" OR tag:#Lang -orphan:true"#);
+        assert_eq!(
+            q.unwrap().unwrap(),
+            Query::Or(vec![
+                text(Some(TextField::Title), "rust book", true),
+                Query::And(vec![
+                    Query::Tag("lang".to_string()),
+                    Query::Not(Box::new(Query::Orphan(true))),
+                ]),
+            ])
+        );
+
+        let q = parse("(links-to:a.md OR NOT body:x").unwrap().unwrap();
+        assert_eq!(
+            q,
+            Query::Or(vec![
+                Query::LinksTo("@/a.md".to_string()),
+                Query::Not(Box::new(text(Some(TextField::Body), "x", false))),
+            ])
+        );
+
+        // Stray operators and words with colons are kept as text.
+        let q = parse(") OR Note: 10:30 AND").unwrap().unwrap();
+        assert_eq!(
+            q,
+            Query::And(vec![
+                text(None, "Note:", false),
+                text(None, "10:30", false)
+            ])
+        );
+
+        // Names that are not fields are words too.
+        let q = parse("http://example.com/a tittle:rust").unwrap().unwrap();
+        assert!(!q.is_structured());
+        assert_eq!(
+            q,
+            Query::And(vec![
+                text(None, "http://example.com/a", false),
+                text(None, "tittle:rust", false)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_created() {
+        let range = |q: &str| match parse(q).unwrap().unwrap() {
+            Query::Created(r) => r,
+            q => panic!("{:?}", q),
+        };
+        assert_eq!(
+            range("created:>2023-01-01"),
+            DateRange {
+                from: day(2023, 1, 2),
+                until: None
+            }
+        );
+        assert_eq!(
+            range("created:<=2023-12"),
+            DateRange {
+                from: None,
+                until: day(2024, 1, 1)
+            }
+        );
+        assert_eq!(
+            range("created:2022..2023-02"),
+            DateRange {
+                from: day(2022, 1, 1),
+                until: day(2023, 3, 1)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let e = parse("rust created:>2023-13-01").unwrap_err();
+        assert_eq!(e.range(), 14..24);
+        assert_eq!(e.column(), 15);
+        assert!(e.message().contains("2023-13-01"));
+
+        let e = parse("orphan:maybe").unwrap_err();
+        assert_eq!(e.range(), 7..12);
+    }
+}
@@ -0,0 +1,189 @@
+//! Embeddings of notes by a local BERT model, so notes can be searched by
+//! meaning rather than by words. Nothing is downloaded, the model is read
+//! from a directory.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use serde::{Deserialize, Serialize};
+use tokenizers::{Tokenizer, TruncationParams};
+
+/// Tokens of a text that are embedded at most, the rest is cut.
+const MAX_TOKENS: usize = 256;
+
+/// Sentence embedding model, run on the CPU.
+pub struct Embedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl Embedder {
+    /// Load the model from a directory of `config.json`, `tokenizer.json`
+    /// and `model.safetensors`, like the ones of sentence-transformers.
+    pub fn load(dir: &Path) -> Result<Self, anyhow::Error> {
+        let device = Device::Cpu;
+        let config: Config = serde_json::from_str(&fs::read_to_string(
+            dir.join("config.json"),
+        )?)?;
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(anyhow::Error::msg)?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(anyhow::Error::msg)?;
+        // The weights are mapped from the file, it is not changed meanwhile.
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[dir.join("model.safetensors")],
+                DTYPE,
+                &device,
+            )?
+        };
+        let model = BertModel::load(vb, &config)?;
+        Ok(Embedder {
+            model,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// Mean of the embeddings of the tokens of the text, of unit length.
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>, anyhow::Error> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(anyhow::Error::msg)?;
+        let ids =
+            Tensor::new(encoding.get_ids(), &self.device)?.unsqueeze(0)?;
+        let type_ids = ids.zeros_like()?;
+        let output = self.model.forward(&ids, &type_ids)?;
+        let (_, n, _) = output.dims3()?;
+        let mean = (output.sum(1)? / n as f64)?;
+        let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?;
+        let unit = mean.broadcast_div(&norm)?;
+        Ok(unit.squeeze(0)?.to_vec1::<f32>()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Entry {
+    /// Hash of the text that the vector is of.
+    hash: u64,
+    vector: Vec<f32>,
+}
+
+/// Vectors of the notes by zid, kept in a file so only the notes that
+/// changed are embedded again.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Embeddings {
+    entries: HashMap<String, Entry>,
+}
+
+impl Embeddings {
+    /// Read the vectors from the file, none if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        if !path.exists() {
+            return Ok(Embeddings::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Vector of the note, if it is of the text of the hash.
+    pub fn get(&self, zid: &str, hash: u64) -> Option<&[f32]> {
+        self.entries
+            .get(zid)
+            .filter(|e| e.hash == hash)
+            .map(|e| e.vector.as_slice())
+    }
+
+    pub fn insert(&mut self, zid: &str, hash: u64, vector: Vec<f32>) {
+        self.entries.insert(zid.to_string(), Entry { hash, vector });
+    }
+
+    /// Keep only the vectors of the notes that `keep` is true for, whether
+    /// any is removed.
+    pub fn retain<F: Fn(&str) -> bool>(&mut self, keep: F) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|zid, _| keep(zid));
+        self.entries.len() != len
+    }
+
+    /// Zids of the `limit` notes closest to the vector, closest first, with
+    /// their cosine similarity.
+    pub fn nearest(&self, vector: &[f32], limit: usize) -> Vec<(String, f32)> {
+        let mut scores: Vec<(String, f32)> = self
+            .entries
+            .iter()
+            .map(|(zid, e)| (zid.clone(), dot(vector, &e.vector)))
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores.truncate(limit);
+        scores
+    }
+}
+
+/// FNV-1a hash of the text, it stays the same across Rust versions, so the
+/// saved vectors are not embedded again after an upgrade.
+pub fn text_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Cosine similarity of vectors of unit length.
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{text_hash, Embeddings};
+
+    #[test]
+    fn test_text_hash() {
+        assert_eq!(text_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(text_hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(text_hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_embeddings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embeddings.json");
+        let mut e = Embeddings::load(&path).unwrap();
+        e.insert("@/a.md", 1, vec![1.0, 0.0]);
+        e.insert("@/b.md", 2, vec![0.0, 1.0]);
+        e.insert("@/c.md", 3, vec![0.6, 0.8]);
+        e.save(&path).unwrap();
+
+        let mut e = Embeddings::load(&path).unwrap();
+        assert_eq!(e.get("@/a.md", 1), Some(&[1.0, 0.0][..]));
+        assert_eq!(e.get("@/a.md", 2), None);
+        let zids: Vec<String> = e
+            .nearest(&[1.0, 0.0], 2)
+            .into_iter()
+            .map(|(z, _)| z)
+            .collect();
+        assert_eq!(zids, vec!["@/a.md", "@/c.md"]);
+
+        assert!(e.retain(|zid| zid != "@/a.md"));
+        assert!(!e.retain(|zid| zid != "@/a.md"));
+        assert_eq!(e.nearest(&[1.0, 0.0], 1)[0].0, "@/c.md");
+    }
+}
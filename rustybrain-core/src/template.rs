@@ -0,0 +1,119 @@
+//! Templates for new notes.
+//!
+//! A template is a markdown file under the templates directory of the repo.
+//! Placeholders like `{{title}}`, `{{date}}`, `{{zid}}` and `{{clipboard}}`
+//! are replaced when a note is created from it, and its optional `+++`
+//! front-matter provides default header values of the note.
+use std::fs;
+use std::path::Path;
+
+use toml::value::Table;
+
+use crate::md::split_front_matter;
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    name: String,
+    header: Option<String>,
+    body: String,
+}
+
+/// Values of placeholders in a template.
+#[derive(Debug, Clone, Default)]
+pub struct Vars {
+    pub title: String,
+    pub date: String,
+    pub zid: String,
+    pub clipboard: String,
+}
+
+impl Template {
+    pub fn from_path(path: &Path) -> Result<Self, anyhow::Error> {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid template name"))?
+            .to_string();
+        let raw = fs::read_to_string(path)?;
+        let (header, body) = split_front_matter(&raw, "+++");
+        Ok(Template {
+            name,
+            header: header.map(|h| h.to_string()),
+            body: body.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Render header defaults and body with the vars.
+    pub fn render(
+        &self,
+        vars: &Vars,
+    ) -> Result<(Table, String), anyhow::Error> {
+        let header = match self.header.as_ref() {
+            Some(h) => toml::from_str(&substitute(h, vars))?,
+            None => Table::new(),
+        };
+        Ok((header, substitute(&self.body, vars)))
+    }
+}
+
+/// Load all templates in the directory, sorted by name.
+pub fn load(dir: &Path) -> Result<Vec<Template>, anyhow::Error> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut templates = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map(|e| e == "md").unwrap_or(false) {
+            templates.push(Template::from_path(&path)?);
+        }
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Replace `{{name}}` placeholders, unknown ones are kept as is.
+fn substitute(s: &str, vars: &Vars) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        out.push_str(&rest[..start]);
+        match rest[start + 2..end].trim() {
+            "title" => out.push_str(&vars.title),
+            "date" => out.push_str(&vars.date),
+            "zid" => out.push_str(&vars.zid),
+            "clipboard" => out.push_str(&vars.clipboard),
+            _ => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{substitute, Vars};
+
+    #[test]
+    fn test_substitute() {
+        let vars = Vars {
+            title: "Rust".to_string(),
+            date: "2023-01-01".to_string(),
+            zid: "@/notes/1.md".to_string(),
+            clipboard: "".to_string(),
+        };
+        assert_eq!(
+            substitute("# {{ title }} {{date}} [{{zid}}] {{unknown}}", &vars),
+            "# Rust 2023-01-01 [@/notes/1.md] {{unknown}}"
+        );
+    }
+}
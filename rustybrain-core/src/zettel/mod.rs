@@ -3,13 +3,15 @@ use std::fs::rename;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::Cursor;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::slice::Iter;
 use std::str::FromStr;
 
 use chrono::Local;
 use serde::{Deserialize, Serialize};
-use toml::value::Datetime;
+use toml::value::{Datetime, Table};
+use tree_sitter::InputEdit;
 use tree_sitter::Node;
 use tree_sitter::Tree;
 use tree_sitter::TreeCursor;
@@ -29,20 +31,49 @@ pub struct Zettel {
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ZettelHeader {
+    /// Stable id of the zettel, the zid is derived from the path without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     title: String,
     date: Option<Datetime>,
 
+    /// Other names of the zettel, found by search and links as the title.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
+    /// Zid of the parent in a sequence, empty means top level, the
+    /// folgezettel id decides the parent if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+
+    /// Order among siblings in a sequence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    position: Option<i64>,
+
+    /// Other fields in the header that are kept as is.
+    #[serde(flatten)]
+    extra: Table,
+
     #[allow(dead_code)]
     #[serde(skip)]
     raw: String,
 }
 
 impl ZettelHeader {
-    pub fn new(title: &str) -> Self {
+    pub fn new(id: Option<&str>, title: &str) -> Self {
         let today = Local::now().format("%Y-%m-%d").to_string();
         Self {
+            id: id.map(|s| s.to_string()),
             title: title.to_string(),
             date: Some(Datetime::from_str(&today).unwrap()),
+            aliases: vec![],
+            tags: vec![],
+            parent: None,
+            position: None,
+            extra: Table::new(),
             raw: "".to_string(),
         }
     }
@@ -86,7 +117,10 @@ impl Zettel {
         let header = ZettelHeader::from_cursor(&mut cursor)?;
         let mut content: String = String::new();
         cursor.read_to_string(&mut content)?;
-        let id = Self::in_repo_path(path, repo_path)?;
+        let id = match header.id.as_ref() {
+            Some(id) => format!("@/{}", id),
+            None => Self::in_repo_path(path, repo_path)?,
+        };
         let tree = crate::md::parse(&content, None)?;
         let mut z = Zettel {
             id,
@@ -100,21 +134,25 @@ impl Zettel {
         Ok(z)
     }
 
+    /// Create the zettel on disk, its zid is `@/<id>` if `id` is given, or
+    /// the path in repo otherwise.
     pub fn create(
         repo_path: &str,
         path: &Path,
+        id: Option<&str>,
         title: &str,
     ) -> Result<Self, anyhow::Error> {
-        Self::create_and_insert(path, title)?;
+        Self::create_and_insert(path, id, title)?;
         Self::from_md(repo_path, path)
     }
 
     fn create_and_insert(
         path: &Path,
+        id: Option<&str>,
         title: &str,
     ) -> Result<(), anyhow::Error> {
         let mut file = File::create(path)?;
-        let header = ZettelHeader::new(title);
+        let header = ZettelHeader::new(id, title);
         Self::write_header(&mut file, &header)?;
         Ok(())
     }
@@ -152,6 +190,11 @@ impl Zettel {
         &self.id
     }
 
+    /// Id stored in the header, `None` for zettels identified by path.
+    pub fn id(&self) -> Option<&str> {
+        self.header.id.as_deref()
+    }
+
     pub fn tree(&self) -> Option<&Tree> {
         self.tree.as_ref()
     }
@@ -206,10 +249,81 @@ impl Zettel {
         &self.content
     }
 
+    pub fn aliases(&self) -> &[String] {
+        &self.header.aliases
+    }
+
+    pub fn set_aliases(&mut self, aliases: Vec<String>) {
+        self.header.aliases = aliases;
+    }
+
+    /// Whether the name is the title or an alias, ignoring case.
+    pub fn is_named(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        self.title().to_lowercase() == name
+            || self.aliases().iter().any(|a| a.to_lowercase() == name)
+    }
+
     pub fn set_title(&mut self, title: &str) {
         self.header.title = title.to_string();
     }
 
+    pub fn date(&self) -> Option<&Datetime> {
+        self.header.date.as_ref()
+    }
+
+    pub fn set_date(&mut self, date: Datetime) {
+        self.header.date = Some(date);
+    }
+
+    pub fn parent(&self) -> Option<&str> {
+        self.header.parent.as_deref()
+    }
+
+    pub fn set_parent(&mut self, parent: Option<&str>) {
+        self.header.parent = parent.map(|s| s.to_string());
+    }
+
+    pub fn position(&self) -> Option<i64> {
+        self.header.position
+    }
+
+    pub fn set_position(&mut self, position: Option<i64>) {
+        self.header.position = position;
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.header.tags
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.header.tags = tags;
+    }
+
+    /// Take the header of the zettel reloaded from the disk, like the
+    /// parent and the position moved in a sequence, the content is kept.
+    pub fn reload_header(&mut self, reloaded: &Zettel) {
+        self.header = reloaded.header.clone();
+    }
+
+    /// Fill the header with `defaults` for the fields that are not set.
+    pub fn merge_header(
+        &mut self,
+        defaults: &Table,
+    ) -> Result<(), anyhow::Error> {
+        let mut header = match toml::Value::try_from(&self.header)? {
+            toml::Value::Table(t) => t,
+            _ => return Err(anyhow::anyhow!("invalid header")),
+        };
+        for (k, v) in defaults.iter() {
+            if !header.contains_key(k) {
+                header.insert(k.to_string(), v.clone());
+            }
+        }
+        self.header = toml::Value::Table(header).try_into()?;
+        Ok(())
+    }
+
     pub fn set_content(&mut self, content: &str) -> Result<(), anyhow::Error> {
         self.tree = crate::md::parse(content, None)?;
         self.content = content.to_string();
@@ -217,6 +331,21 @@ impl Zettel {
         Ok(())
     }
 
+    /// Set the content that changed by the edits, the old tree is reused to
+    /// parse it. Byte ranges that need to be restyled are returned.
+    pub fn edit_content(
+        &mut self,
+        content: &str,
+        edits: &[InputEdit],
+    ) -> Result<Vec<Range<usize>>, anyhow::Error> {
+        let (tree, ranges) =
+            crate::md::reparse(content, self.tree.take(), edits)?;
+        self.tree = tree;
+        self.content = content.to_string();
+        self.parse_links_to();
+        Ok(ranges)
+    }
+
     fn parse_links_to(&mut self) {
         let mut link_to: Vec<String> = vec![];
         for node in self.walk_iter() {
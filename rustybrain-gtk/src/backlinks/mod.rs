@@ -4,6 +4,7 @@ use std::rc::Rc;
 use gtk::prelude::*;
 use gtk::ListBox;
 use gtk::ScrolledWindow;
+use relm4::send;
 use relm4::ComponentUpdate;
 use relm4::Widgets;
 use rustybrain_core::kasten::Kasten;
@@ -11,18 +12,37 @@ use rustybrain_core::zettel::Zettel;
 
 use crate::AppModel;
 
+/// Similar notes shown at most.
+const SIMILAR_LIMIT: usize = 10;
+/// Suggested links shown at most.
+const SUGGESTED_LIMIT: usize = 5;
+
 pub struct Model {
     kasten: Rc<RefCell<Kasten>>,
-    zettel: Option<Rc<RefCell<Zettel>>>,
+    /// Titles and zids of the notes that link to the zettel.
+    backlinks: Vec<(String, String)>,
+    /// Notes close in meaning, or why there are none, `None` if semantic
+    /// search is not enabled.
+    similar: Option<Result<Vec<(String, String)>, String>>,
+    /// Notes of the same words that the zettel doesn't link to yet.
+    suggested: Result<Vec<(String, String)>, String>,
+    version: usize,
 }
 
 pub enum Msg {
     ChangeZettel(Rc<RefCell<Zettel>>),
+    Open(String),
+    /// Insert a link to the note of the zid in the editor.
+    InsertLink(String),
 }
 
 pub struct Backlinks {
     window: ScrolledWindow,
-    layout: ListBox,
+    backlinks: ListBox,
+    similar_section: gtk::Box,
+    similar: ListBox,
+    suggested: ListBox,
+    version: usize,
 }
 
 impl relm4::Model for Model {
@@ -37,7 +57,10 @@ impl ComponentUpdate<AppModel> for Model {
     fn init_model(parent_model: &AppModel) -> Self {
         Model {
             kasten: parent_model.kasten.clone(),
-            zettel: None,
+            backlinks: vec![],
+            similar: None,
+            suggested: Ok(vec![]),
+            version: 0,
         }
     }
 
@@ -46,45 +69,194 @@ impl ComponentUpdate<AppModel> for Model {
         msg: Self::Msg,
         _components: &Self::Components,
         _sender: relm4::Sender<Self::Msg>,
-        _parent_sender: relm4::Sender<super::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
     ) {
         match msg {
-            Msg::ChangeZettel(z) => self.zettel = Some(z),
+            Msg::ChangeZettel(z) => self.change_zettel(&z.borrow()),
+            Msg::Open(zid) => {
+                if let Some(z) = self.kasten.borrow().get(&zid) {
+                    send!(parent_sender, super::Msg::ChangeZettel(z));
+                }
+            }
+            Msg::InsertLink(zid) => {
+                if let Some(z) = self.kasten.borrow().get(&zid) {
+                    send!(parent_sender, super::Msg::InsertZettel(z));
+                }
+            }
         }
     }
 }
 
+impl Model {
+    fn change_zettel(&mut self, z: &Zettel) {
+        let kasten = self.kasten.borrow();
+        self.backlinks = titles(&kasten.iter_backlinks(z));
+        self.similar = if kasten.semantic_enabled() {
+            Some(
+                kasten
+                    .similar(z, SIMILAR_LIMIT)
+                    .map(|zs| titles(&zs))
+                    .map_err(|e| e.to_string()),
+            )
+        } else {
+            None
+        };
+        self.suggested = kasten
+            .suggested_links(z, SUGGESTED_LIMIT)
+            .map(|zs| titles(&zs))
+            .map_err(|e| e.to_string());
+        self.version += 1;
+    }
+}
+
 impl Widgets<Model, super::AppModel> for Backlinks {
     type Root = gtk::ScrolledWindow;
 
     fn init_view(
         _model: &Model,
         _components: &(),
-        _sender: relm4::Sender<Msg>,
+        sender: relm4::Sender<Msg>,
     ) -> Self {
-        let layout = ListBox::builder().build();
+        let layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .build();
+        let (backlinks_section, backlinks) = section("Backlinks", &sender);
+        let (similar_section, similar) = section("Similar notes", &sender);
+        let (suggested_section, suggested) =
+            section("Suggested links", &sender);
+        layout.append(&backlinks_section);
+        layout.append(&similar_section);
+        layout.append(&suggested_section);
         let window = ScrolledWindow::builder()
             .width_request(200)
             .child(&layout)
             .build();
-        Backlinks { window, layout }
+        Backlinks {
+            window,
+            backlinks,
+            similar_section,
+            similar,
+            suggested,
+            version: 0,
+        }
     }
 
     fn root_widget(&self) -> Self::Root {
         self.window.clone()
     }
 
-    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
-        while let Some(c) = self.layout.last_child() {
-            self.layout.remove(&c);
+    fn view(&mut self, model: &Model, sender: relm4::Sender<Msg>) {
+        if self.version == model.version {
+            return;
+        }
+        self.version = model.version;
+        fill(&self.backlinks, &model.backlinks);
+        self.similar_section.set_visible(model.similar.is_some());
+        match model.similar.as_ref() {
+            Some(Ok(similar)) => fill(&self.similar, similar),
+            Some(Err(e)) => {
+                fill(&self.similar, &[]);
+                self.similar.append(&error_label(e));
+            }
+            None => {}
         }
-        if let Some(z) = model.zettel.as_ref() {
-            for item in model.kasten.borrow().iter_backlinks(&z.borrow()) {
-                let z = &item.borrow();
-                let label = gtk::Label::builder().label(z.title()).build();
-                let row = gtk::ListBoxRow::builder().child(&label).build();
-                self.layout.append(&row);
+        match model.suggested.as_ref() {
+            Ok(suggested) => {
+                fill_with_insert(&self.suggested, suggested, &sender);
+            }
+            Err(e) => {
+                fill(&self.suggested, &[]);
+                self.suggested.append(&error_label(e));
             }
         }
     }
 }
+
+/// Section of the pane with a heading, the list activates the note of the
+/// row, its zid is the name of the row.
+fn section(title: &str, sender: &relm4::Sender<Msg>) -> (gtk::Box, ListBox) {
+    let heading = gtk::Label::builder()
+        .label(title)
+        .halign(gtk::Align::Start)
+        .build();
+    heading.add_css_class("heading");
+    let list = ListBox::builder().build();
+    let s = sender.clone();
+    list.connect_row_activated(move |_, row| {
+        if !row.widget_name().is_empty() {
+            send!(s, Msg::Open(row.widget_name().to_string()));
+        }
+    });
+    let section = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .build();
+    section.append(&heading);
+    section.append(&list);
+    (section, list)
+}
+
+/// Replace the rows of the list by the notes.
+fn fill(list: &ListBox, notes: &[(String, String)]) {
+    while let Some(c) = list.last_child() {
+        list.remove(&c);
+    }
+    for (title, zid) in notes {
+        let label = gtk::Label::builder().label(title).build();
+        let row = gtk::ListBoxRow::builder().child(&label).name(zid).build();
+        list.append(&row);
+    }
+}
+
+/// Replace the rows of the list by the notes, each with a button that
+/// inserts a link to its note.
+fn fill_with_insert(
+    list: &ListBox,
+    notes: &[(String, String)],
+    sender: &relm4::Sender<Msg>,
+) {
+    fill(list, &[]);
+    for (title, zid) in notes {
+        let label = gtk::Label::builder()
+            .label(title)
+            .hexpand(true)
+            .halign(gtk::Align::Start)
+            .build();
+        let button = gtk::Button::builder()
+            .icon_name("insert-link-symbolic")
+            .tooltip_text("Insert link")
+            .build();
+        button.add_css_class("flat");
+        let s = sender.clone();
+        let z = zid.clone();
+        button.connect_clicked(move |_| send!(s, Msg::InsertLink(z.clone())));
+        let layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .build();
+        layout.append(&label);
+        layout.append(&button);
+        let row = gtk::ListBoxRow::builder().child(&layout).name(zid).build();
+        list.append(&row);
+    }
+}
+
+fn error_label(e: &str) -> gtk::Label {
+    let label = gtk::Label::builder()
+        .label(e)
+        .wrap(true)
+        .halign(gtk::Align::Start)
+        .build();
+    label.add_css_class("dim-label");
+    label
+}
+
+fn titles(zettels: &[Rc<RefCell<Zettel>>]) -> Vec<(String, String)> {
+    zettels
+        .iter()
+        .map(|z| {
+            let z = z.borrow();
+            (z.title().to_string(), z.zid().to_string())
+        })
+        .collect()
+}
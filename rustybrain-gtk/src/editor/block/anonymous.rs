@@ -4,6 +4,7 @@ use gtk::TextBuffer;
 use gtk::TextMark;
 use gtk::TextView;
 use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
 
 pub struct Anonymous {
     left: TextMark,
@@ -11,7 +12,7 @@ pub struct Anonymous {
 }
 
 impl Blocking for Anonymous {
-    fn from_node(_node: &Node, _buffer: &TextBuffer) -> Self {
+    fn from_node(_node: &Node, _buffer: &TextBuffer, _pos: &Positions) -> Self {
         Anonymous {
             left: TextMark::new(None, true),
             right: TextMark::new(None, true),
@@ -2,6 +2,8 @@ use gtk::prelude::*;
 use gtk::TextIter;
 use gtk::TextMark;
 use gtk::TextView;
+use rustybrain_core::highlight;
+use rustybrain_core::position::Positions;
 
 use super::Blocking;
 
@@ -16,14 +18,17 @@ impl Blocking for Codeblock {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
         let mut content = None;
         let child = Self::node_child_by_kind(node, "code_fence_content");
         if let Some(cnt_node) = child {
-            content = Some(CodeblockContent::from_node(&cnt_node, buffer));
+            let mut cnt = CodeblockContent::from_node(&cnt_node, buffer, pos);
+            cnt.lang = Self::lang(node, buffer, pos);
+            content = Some(cnt);
         }
 
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
 
         Codeblock {
             content,
@@ -73,6 +78,21 @@ impl Blocking for Codeblock {
 }
 
 impl Codeblock {
+    /// First word of the info string of the fence, such as `rust`.
+    fn lang(
+        node: &rustybrain_core::md::Node,
+        buffer: &gtk::TextBuffer,
+        pos: &Positions,
+    ) -> Option<String> {
+        let info = Self::node_child_by_kind(node, "info_string")?;
+        let start =
+            buffer.iter_at_offset(pos.byte_to_char(info.start_byte()) as i32);
+        let end =
+            buffer.iter_at_offset(pos.byte_to_char(info.end_byte()) as i32);
+        let text = buffer.text(&start, &end, true);
+        text.split_whitespace().next().map(|s| s.to_string())
+    }
+
     fn begin_line(
         &self,
         buffer: &gtk::TextBuffer,
@@ -117,15 +137,21 @@ impl Codeblock {
 struct CodeblockContent {
     right: TextMark,
     left: TextMark,
+    lang: Option<String>,
 }
 
 impl Blocking for CodeblockContent {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
-        CodeblockContent { left, right }
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        CodeblockContent {
+            left,
+            right,
+            lang: None,
+        }
     }
 
     fn left(&self) -> &gtk::TextMark {
@@ -140,5 +166,34 @@ impl Blocking for CodeblockContent {
         let start = self.start(buffer);
         let end = self.end(buffer);
         buffer.apply_tag_by_name("code-block", &start, &end);
+        self.highlight(buffer);
+    }
+}
+
+impl CodeblockContent {
+    /// Tag the tokens of the code, the whole content is highlighted again
+    /// whenever the block is remounted after an edit in it.
+    fn highlight(&self, buffer: &gtk::TextBuffer) {
+        let lang = match self.lang.as_ref() {
+            Some(lang) => lang,
+            None => return,
+        };
+        let start = self.start(buffer);
+        let code = buffer.text(&start, &self.end(buffer), true);
+        let tokens = match highlight::highlight(lang, &code) {
+            Ok(tokens) => tokens,
+            Err(_) => return,
+        };
+        let pos = Positions::new(&code);
+        let offset = start.offset();
+        for (range, name) in tokens {
+            let s = offset + pos.byte_to_char(range.start) as i32;
+            let e = offset + pos.byte_to_char(range.end) as i32;
+            buffer.apply_tag_by_name(
+                &format!("code-{}", name.replace('.', "-")),
+                &buffer.iter_at_offset(s),
+                &buffer.iter_at_offset(e),
+            );
+        }
     }
 }
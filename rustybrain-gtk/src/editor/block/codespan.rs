@@ -0,0 +1,45 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
+
+use super::Blocking;
+
+/// Inline code between one or more backticks.
+pub struct CodeSpan {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for CodeSpan {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        CodeSpan { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "code",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show_endpoint_n(buffer, self.leading(buffer, '`'));
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.hide_endpoint_n(buffer, self.leading(buffer, '`'));
+    }
+}
@@ -0,0 +1,57 @@
+use std::cell::RefCell;
+
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextChildAnchor;
+use gtk::TextIter;
+use gtk::TextView;
+
+/// Widget embedded in the buffer after the source of a block, such as an
+/// image or rendered math.
+///
+/// Its anchor takes a character in the buffer but not in the text of the
+/// zettel.
+pub struct Embed {
+    anchor: RefCell<Option<TextChildAnchor>>,
+}
+
+impl Embed {
+    pub fn new() -> Self {
+        Embed {
+            anchor: RefCell::new(None),
+        }
+    }
+
+    /// Embed the widget at the iter, marks with right gravity at the iter
+    /// move over it.
+    pub fn attach(
+        &self,
+        view: &TextView,
+        buffer: &TextBuffer,
+        iter: &mut TextIter,
+        widget: &impl IsA<gtk::Widget>,
+    ) {
+        let anchor = buffer.create_child_anchor(iter);
+        view.add_child_at_anchor(widget, &anchor);
+        *self.anchor.borrow_mut() = Some(anchor);
+    }
+
+    /// Remove the widget and its anchor from the buffer.
+    pub fn detach(&self, buffer: &TextBuffer) {
+        if let Some(anchor) = self.anchor.borrow_mut().take() {
+            if !anchor.is_deleted() {
+                let mut start = buffer.iter_at_child_anchor(&anchor);
+                let mut end = start;
+                end.forward_char();
+                buffer.delete(&mut start, &mut end);
+            }
+        }
+    }
+
+    /// Iter of the anchor, `None` if nothing is embedded.
+    pub fn iter(&self, buffer: &TextBuffer) -> Option<TextIter> {
+        let anchor = self.anchor.borrow();
+        let anchor = anchor.as_ref().filter(|a| !a.is_deleted())?;
+        Some(buffer.iter_at_child_anchor(anchor))
+    }
+}
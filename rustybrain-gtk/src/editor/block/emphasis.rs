@@ -1,4 +1,5 @@
 use gtk::{traits::TextBufferExt, TextMark};
+use rustybrain_core::position::Positions;
 
 use super::Blocking;
 
@@ -11,8 +12,9 @@ impl Blocking for Emphasis {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Emphasis { left, right }
     }
 
@@ -50,8 +52,9 @@ impl Blocking for StrongEmphasis {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Self { left, right }
     }
 
@@ -79,3 +82,43 @@ impl Blocking for StrongEmphasis {
         self.hide_endpoint_n(buffer, 2);
     }
 }
+
+pub struct Strikethrough {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Strikethrough {
+    fn from_node(
+        node: &rustybrain_core::md::Node,
+        buffer: &gtk::TextBuffer,
+        pos: &Positions,
+    ) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        Self { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        buffer.apply_tag_by_name(
+            "strikethrough",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_in(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.show_endpoint_n(buffer, self.leading(buffer, '~'));
+    }
+
+    fn cursor_out(&self, _view: &gtk::TextView, buffer: &gtk::TextBuffer) {
+        self.hide_endpoint_n(buffer, self.leading(buffer, '~'));
+    }
+}
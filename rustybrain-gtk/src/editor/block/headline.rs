@@ -5,6 +5,7 @@ use gtk::TextIter;
 use gtk::TextMark;
 use gtk::TextView;
 use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
 
 pub struct Headline {
     left: TextMark,
@@ -15,11 +16,11 @@ pub struct Headline {
 }
 
 impl Blocking for Headline {
-    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         let mut content = None;
         if let Some(child) = Self::node_child_by_kind(node, "heading_content") {
-            content = Some(Content::from_node(&child, buffer));
+            content = Some(Content::from_node(&child, buffer, pos));
         }
         Headline {
             left,
@@ -82,12 +83,18 @@ impl Blocking for Headline {
 }
 
 impl Headline {
-    pub fn set_number(&mut self, n: u8, node: &Node, buffer: &TextBuffer) {
+    pub fn set_number(
+        &mut self,
+        n: u8,
+        node: &Node,
+        buffer: &TextBuffer,
+        pos: &Positions,
+    ) {
         if let Some(content) = self.content.as_mut() {
             content.set_number(n)
         }
 
-        self.marker = Some(Marker::from_node(node, buffer));
+        self.marker = Some(Marker::from_node(node, buffer, pos));
     }
 }
 
@@ -97,8 +104,8 @@ struct Marker {
 }
 
 impl Blocking for Marker {
-    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Marker { left, right }
     }
 
@@ -154,8 +161,8 @@ struct Content {
 }
 
 impl Blocking for Content {
-    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Content {
             left,
             right,
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
+
+use super::embed::Embed;
+use super::Blocking;
+
+/// `![alt](path)`, the image is shown after its source, which is hidden
+/// unless the cursor is in it.
+pub struct Image {
+    left: TextMark,
+    right: TextMark,
+    link: String,
+
+    file: Option<PathBuf>,
+    max_width: i32,
+    embed: Embed,
+}
+
+impl Blocking for Image {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        let link = match Self::node_child_by_kind(node, "link_destination") {
+            Some(n) => {
+                let start = pos.byte_to_char(n.start_byte()) as i32;
+                let end = pos.byte_to_char(n.end_byte()) as i32;
+                buffer
+                    .text(
+                        &buffer.iter_at_offset(start),
+                        &buffer.iter_at_offset(end),
+                        true,
+                    )
+                    .to_string()
+            }
+            None => String::new(),
+        };
+        Image {
+            left,
+            right,
+            link,
+            file: None,
+            max_width: 0,
+            embed: Embed::new(),
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        let file = match self.file.as_ref() {
+            Some(file) => file,
+            None => return,
+        };
+        let picture = gtk::Picture::for_filename(file);
+        picture.set_can_shrink(true);
+        if let Some(paintable) = picture.paintable() {
+            let (w, h) =
+                (paintable.intrinsic_width(), paintable.intrinsic_height());
+            if w > 0 && h > 0 {
+                let width = w.min(self.max_width);
+                picture.set_size_request(width, h * width / w);
+            }
+        }
+
+        // The picture is after the source, the right mark moves over it.
+        self.embed
+            .attach(view, buffer, &mut self.end(buffer), &picture);
+        self.hide_source(buffer);
+    }
+
+    fn umount(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show_source(buffer);
+        self.embed.detach(buffer);
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show_source(buffer);
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.hide_source(buffer);
+    }
+}
+
+impl Image {
+    pub fn link(&self) -> &str {
+        &self.link
+    }
+
+    /// Show the image of the file, scaled down to `max_width`.
+    pub fn set_file(&mut self, file: Option<PathBuf>, max_width: i32) {
+        self.file = file;
+        self.max_width = max_width;
+    }
+
+    /// Source of the image, it is up to the picture once it is shown.
+    fn source(&self, buffer: &TextBuffer) -> Option<(TextIter, TextIter)> {
+        Some((self.start(buffer), self.embed.iter(buffer)?))
+    }
+
+    fn hide_source(&self, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.source(buffer) {
+            buffer.apply_tag_by_name("hidden", &start, &end);
+        }
+    }
+
+    fn show_source(&self, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.source(buffer) {
+            buffer.remove_tag_by_name("hidden", &start, &end);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use gtk::{traits::TextBufferExt, TextMark};
+use rustybrain_core::position::Positions;
 
 use super::Blocking;
 
@@ -23,18 +24,19 @@ impl Blocking for Link {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
 
         let mut text = None;
         let mut dest = None;
 
         if let Some(tn) = Self::node_child_by_kind(node, "link_text") {
-            text = Some(LinkText::from_node(&tn, buffer));
+            text = Some(LinkText::from_node(&tn, buffer, pos));
         }
 
         if let Some(dn) = Self::node_child_by_kind(node, "link_destination") {
-            dest = Some(LinkDest::from_node(&dn, buffer));
+            dest = Some(LinkDest::from_node(&dn, buffer, pos));
         }
 
         Link {
@@ -95,8 +97,9 @@ impl Blocking for LinkText {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Self { left, right }
     }
 
@@ -129,8 +132,9 @@ impl Blocking for LinkDest {
     fn from_node(
         node: &rustybrain_core::md::Node,
         buffer: &gtk::TextBuffer,
+        pos: &Positions,
     ) -> Self {
-        let (left, right) = Self::node_endpoint(node, buffer);
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
         Self { left, right }
     }
 
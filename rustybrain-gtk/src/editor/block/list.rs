@@ -0,0 +1,270 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
+
+use super::Blocking;
+
+/// Deepest level of lists that gets its own indentation.
+const MAX_DEPTH: usize = 6;
+
+/// Item of a bullet, ordered or task list.
+pub struct ListItem {
+    left: TextMark,
+    right: TextMark,
+    depth: usize,
+
+    marker: Option<Marker>,
+    checkbox: Option<Checkbox>,
+}
+
+impl Blocking for ListItem {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        let marker = Self::node_child_by_kind(node, "list_marker")
+            .map(|n| Marker::from_node(&n, buffer, pos));
+        let checkbox = Self::task_marker(node)
+            .map(|n| Checkbox::from_node(&n, buffer, pos));
+
+        let mut depth = 0;
+        let mut parent = node.parent();
+        while let Some(p) = parent {
+            if p.kind().ends_with("_list") {
+                depth += 1;
+            }
+            parent = p.parent();
+        }
+
+        ListItem {
+            left,
+            right,
+            depth: depth.clamp(1, MAX_DEPTH),
+            marker,
+            checkbox,
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            &format!("list-{}", self.depth),
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        if let Some(marker) = self.marker.as_ref() {
+            marker.mount(view, buffer);
+        }
+        if let Some(checkbox) = self.checkbox.as_ref() {
+            checkbox.mount(view, buffer);
+            if checkbox.is_checked(buffer) {
+                buffer.apply_tag_by_name(
+                    "task-done",
+                    &checkbox.end(buffer),
+                    &self.end(buffer),
+                );
+            }
+        }
+    }
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        let (start, end) = (self.start(buffer), self.end(buffer));
+        buffer.remove_tag_by_name(
+            &format!("list-{}", self.depth),
+            &start,
+            &end,
+        );
+        buffer.remove_tag_by_name("task-done", &start, &end);
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+        if let Some(marker) = self.marker.as_ref() {
+            marker.umount(view, buffer);
+        }
+        if let Some(checkbox) = self.checkbox.as_ref() {
+            checkbox.umount(view, buffer);
+        }
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        // The bullet of a task is hidden behind its checkbox.
+        if let (Some(marker), Some(_)) = (&self.marker, &self.checkbox) {
+            marker.show(buffer);
+        }
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        if let (Some(marker), Some(_)) = (&self.marker, &self.checkbox) {
+            marker.hide(buffer);
+        }
+    }
+}
+
+impl ListItem {
+    /// The task marker is in the item or at the start of its paragraph.
+    fn task_marker<'a>(node: &'a Node) -> Option<Node<'a>> {
+        let kind = "task_list_item_marker";
+        Self::node_child_by_kind(node, kind).or_else(|| {
+            let paragraph = Self::node_child_by_kind(node, "paragraph")?;
+            let marker = paragraph.child(0).filter(|n| n.kind() == kind)?;
+            Some(marker)
+        })
+    }
+
+    /// Toggle the checkbox of the task if the iter is on it, the source is
+    /// changed to `[x]` or `[ ]`.
+    pub fn toggle_checkbox(
+        &self,
+        buffer: &TextBuffer,
+        iter: &TextIter,
+    ) -> bool {
+        match self.checkbox.as_ref() {
+            Some(checkbox) if checkbox.contains(buffer, iter) => {
+                checkbox.toggle(buffer);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Marker {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Marker {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        Marker { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "list-marker",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn umount(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show(buffer);
+        buffer.remove_tag_by_name(
+            "list-marker",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+}
+
+impl Marker {
+    fn hide(&self, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "hidden",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn show(&self, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "hidden",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+}
+
+/// `[ ]` or `[x]` of a task.
+struct Checkbox {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Checkbox {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        Checkbox { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "checkbox",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn umount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "checkbox",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+}
+
+impl Checkbox {
+    /// Iter of the character between the brackets.
+    fn state(&self, buffer: &TextBuffer) -> Option<(TextIter, TextIter)> {
+        let text = buffer.text(&self.start(buffer), &self.end(buffer), true);
+        let i = text.find('[')?;
+        let mut start = self.start(buffer);
+        start.forward_chars(text[..i].chars().count() as i32 + 1);
+        let mut end = start;
+        end.forward_char();
+        Some((start, end))
+    }
+
+    fn is_checked(&self, buffer: &TextBuffer) -> bool {
+        match self.state(buffer) {
+            Some((start, end)) => {
+                matches!(buffer.text(&start, &end, true).as_str(), "x" | "X")
+            }
+            None => false,
+        }
+    }
+
+    fn contains(&self, buffer: &TextBuffer, iter: &TextIter) -> bool {
+        self.start(buffer).offset() <= iter.offset()
+            && iter.offset() < self.end(buffer).offset()
+    }
+
+    fn toggle(&self, buffer: &TextBuffer) {
+        let checked = self.is_checked(buffer);
+        if let Some((mut start, mut end)) = self.state(buffer) {
+            buffer.begin_user_action();
+            buffer.delete(&mut start, &mut end);
+            buffer.insert(&mut start, if checked { " " } else { "x" });
+            buffer.end_user_action();
+        }
+    }
+}
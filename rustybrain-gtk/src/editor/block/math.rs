@@ -0,0 +1,166 @@
+use gtk::graphene;
+use gtk::pango::FontDescription;
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::math;
+use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
+
+use super::embed::Embed;
+use super::Blocking;
+
+/// `$...$` or `$$...$$` in a paragraph, the rendered formula is shown after
+/// its source, which is hidden unless the cursor is in it.
+pub struct Math {
+    left: TextMark,
+    right: TextMark,
+    tex: String,
+    display: bool,
+
+    embed: Embed,
+}
+
+impl Blocking for Math {
+    /// Math of the whole text of the node.
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        let text = buffer.text(
+            &buffer.iter_at_mark(&left),
+            &buffer.iter_at_mark(&right),
+            true,
+        );
+        let display = text.starts_with("$$");
+        let tex = text.trim_matches('$').trim().to_string();
+        Math {
+            left,
+            right,
+            tex,
+            display,
+            embed: Embed::new(),
+        }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, view: &TextView, buffer: &TextBuffer) {
+        let layout = view.create_pango_layout(None);
+        // Formulas are as large as the text of paragraphs.
+        let font = buffer.tag_table().lookup("p").and_then(|tag| {
+            tag.property::<Option<FontDescription>>("font-desc")
+        });
+        if let Some(font) = font {
+            layout.set_font_description(Some(&font));
+        }
+        let markup = math::to_markup(&self.tex);
+        if self.display {
+            layout.set_markup(&format!(
+                "<span size=\"larger\">{}</span>",
+                markup
+            ));
+        } else {
+            layout.set_markup(&markup);
+        }
+
+        let (_, extents) = layout.pixel_extents();
+        let (width, height) = (extents.width().max(1), extents.height().max(1));
+        let snapshot = gtk::Snapshot::new();
+        snapshot.append_layout(&layout, &view.style_context().color());
+        let size = graphene::Size::new(width as f32, height as f32);
+        let paintable = match snapshot.to_paintable(Some(&size)) {
+            Some(paintable) => paintable,
+            None => return,
+        };
+        let picture = gtk::Picture::for_paintable(&paintable);
+        picture.set_can_shrink(false);
+        picture.set_size_request(width, height);
+        picture.add_css_class("math");
+
+        self.embed
+            .attach(view, buffer, &mut self.end(buffer), &picture);
+        self.hide_source(buffer);
+    }
+
+    fn umount(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show_source(buffer);
+        self.embed.detach(buffer);
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.show_source(buffer);
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        self.hide_source(buffer);
+    }
+}
+
+impl Math {
+    /// Blocks of the formulas in the text of the paragraph, code spans are
+    /// skipped.
+    pub fn from_paragraph(
+        node: &Node,
+        text: &str,
+        buffer: &TextBuffer,
+        pos: &Positions,
+    ) -> Vec<Self> {
+        let range = node.byte_range();
+        let mut code = vec![];
+        for i in 0..node.child_count() {
+            if let Some(child) = node.child(i) {
+                if child.kind() == "code_span" {
+                    code.push(child.byte_range());
+                }
+            }
+        }
+        math::find_math(&text[range.clone()], range.start)
+            .into_iter()
+            .filter(|m| {
+                let r = m.range();
+                !code.iter().any(|c| c.start < r.end && r.start < c.end)
+            })
+            .map(|m| {
+                let mark = |byte: usize| {
+                    let mark = TextMark::builder().left_gravity(false).build();
+                    let iter =
+                        buffer.iter_at_offset(pos.byte_to_char(byte) as i32);
+                    buffer.add_mark(&mark, &iter);
+                    mark
+                };
+                Math {
+                    left: mark(m.range().start),
+                    right: mark(m.range().end),
+                    tex: m.tex().to_string(),
+                    display: m.display(),
+                    embed: Embed::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn source(&self, buffer: &TextBuffer) -> Option<(TextIter, TextIter)> {
+        Some((self.start(buffer), self.embed.iter(buffer)?))
+    }
+
+    fn hide_source(&self, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.source(buffer) {
+            buffer.apply_tag_by_name("hidden", &start, &end);
+        }
+    }
+
+    fn show_source(&self, buffer: &TextBuffer) {
+        if let Some((start, end)) = self.source(buffer) {
+            buffer.remove_tag_by_name("hidden", &start, &end);
+        }
+    }
+}
@@ -1,8 +1,17 @@
 mod anonymous;
 mod codeblock;
+mod codespan;
+mod embed;
 mod emphasis;
 mod headline;
+mod image;
 mod link;
+mod list;
+mod math;
+mod quote;
+mod table;
+
+use std::path::PathBuf;
 
 use gtk::prelude::*;
 use gtk::TextBuffer;
@@ -10,21 +19,48 @@ use gtk::TextIter;
 use gtk::TextMark;
 use gtk::TextView;
 use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
 
 use anonymous::Anonymous;
 use headline::Headline;
 
 use self::codeblock::Codeblock;
+use self::codespan::CodeSpan;
 use self::emphasis::Emphasis;
+use self::emphasis::Strikethrough;
 use self::emphasis::StrongEmphasis;
+use self::image::Image;
 use self::link::Link;
+use self::list::ListItem;
+use self::math::Math;
+use self::quote::{BlockQuote, ThematicBreak};
+use self::table::Table;
+
+pub use self::table::move_to_cell;
+
+/// Byte range of the block that the node builds, headline markers build the
+/// whole headline.
+pub fn node_range(node: &Node) -> std::ops::Range<usize> {
+    if node.kind().starts_with("atx_h") && node.kind().ends_with("_marker") {
+        if let Some(p) = node.parent() {
+            return p.byte_range();
+        }
+    }
+    node.byte_range()
+}
 
 pub trait Blocking {
-    fn node_endpoint(node: &Node, buffer: &TextBuffer) -> (TextMark, TextMark) {
+    fn node_endpoint(
+        node: &Node,
+        buffer: &TextBuffer,
+        pos: &Positions,
+    ) -> (TextMark, TextMark) {
         let left = TextMark::builder().left_gravity(false).build();
         let right = TextMark::builder().left_gravity(false).build();
-        let start = buffer.iter_at_offset(node.start_byte() as i32);
-        let end = buffer.iter_at_offset(node.end_byte() as i32);
+        let start =
+            buffer.iter_at_offset(pos.byte_to_char(node.start_byte()) as i32);
+        let end =
+            buffer.iter_at_offset(pos.byte_to_char(node.end_byte()) as i32);
         buffer.add_mark(&left, &start);
         buffer.add_mark(&right, &end);
         (left, right)
@@ -46,7 +82,7 @@ pub trait Blocking {
             .last()
     }
 
-    fn from_node(node: &Node, buffer: &TextBuffer) -> Self;
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self;
 
     fn left(&self) -> &TextMark;
     fn right(&self) -> &TextMark;
@@ -128,6 +164,19 @@ pub trait Blocking {
         buffer.remove_tag_by_name("hidden", &e_start, &e_end);
     }
 
+    /// Count of the `c` at the start of the block, such as the backticks of
+    /// a code span.
+    fn leading(&self, buffer: &gtk::TextBuffer, c: char) -> i32 {
+        let mut iter = self.start(buffer);
+        let end = self.end(buffer);
+        let mut n = 0;
+        while iter < end && iter.char() == c {
+            n += 1;
+            iter.forward_char();
+        }
+        n
+    }
+
     fn endpoint(
         &self,
         buffer: &gtk::TextBuffer,
@@ -155,36 +204,69 @@ pub enum Block {
     Link(Link),
     Emphasis(Emphasis),
     StrongEmphasis(StrongEmphasis),
+    Strikethrough(Strikethrough),
+    CodeSpan(CodeSpan),
+    Table(Table),
+    Image(Image),
+    Math(Math),
+    ListItem(ListItem),
+    BlockQuote(BlockQuote),
+    ThematicBreak(ThematicBreak),
     Anonymous(Anonymous),
 }
 
 impl Blocking for Block {
-    fn from_node(node: &Node, buffer: &TextBuffer) -> Self {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
         for n in 1..8 {
             if node.kind() == format!("atx_h{}_marker", n) {
                 if let Some(p) = node.parent().as_ref() {
-                    let mut headline = Headline::from_node(p, buffer);
-                    headline.set_number(n, node, buffer);
+                    let mut headline = Headline::from_node(p, buffer, pos);
+                    headline.set_number(n, node, buffer, pos);
                     return Self::Headline(headline);
                 }
             }
         }
         if node.kind() == "fenced_code_block" {
-            return Self::Codeblock(Codeblock::from_node(node, buffer));
+            return Self::Codeblock(Codeblock::from_node(node, buffer, pos));
         }
         if node.kind() == "link" {
-            return Self::Link(Link::from_node(node, buffer));
+            return Self::Link(Link::from_node(node, buffer, pos));
         }
         if node.kind() == "emphasis" {
-            return Self::Emphasis(Emphasis::from_node(node, buffer));
+            return Self::Emphasis(Emphasis::from_node(node, buffer, pos));
         }
         if node.kind() == "strong_emphasis" {
             return Self::StrongEmphasis(StrongEmphasis::from_node(
-                node, buffer,
+                node, buffer, pos,
+            ));
+        }
+        if node.kind() == "strikethrough" {
+            return Self::Strikethrough(Strikethrough::from_node(
+                node, buffer, pos,
+            ));
+        }
+        if node.kind() == "code_span" {
+            return Self::CodeSpan(CodeSpan::from_node(node, buffer, pos));
+        }
+        if node.kind() == "table" {
+            return Self::Table(Table::from_node(node, buffer, pos));
+        }
+        if node.kind() == "image" {
+            return Self::Image(Image::from_node(node, buffer, pos));
+        }
+        if node.kind() == "list_item" || node.kind() == "task_list_item" {
+            return Self::ListItem(ListItem::from_node(node, buffer, pos));
+        }
+        if node.kind() == "block_quote" {
+            return Self::BlockQuote(BlockQuote::from_node(node, buffer, pos));
+        }
+        if node.kind() == "thematic_break" {
+            return Self::ThematicBreak(ThematicBreak::from_node(
+                node, buffer, pos,
             ));
         }
 
-        Self::Anonymous(Anonymous::from_node(node, buffer))
+        Self::Anonymous(Anonymous::from_node(node, buffer, pos))
     }
 
     fn start(&self, buffer: &TextBuffer) -> TextIter {
@@ -195,6 +277,14 @@ impl Blocking for Block {
             Block::Link(l) => l.start(buffer),
             Block::Emphasis(e) => e.start(buffer),
             Block::StrongEmphasis(s) => s.start(buffer),
+            Block::Strikethrough(t) => t.start(buffer),
+            Block::CodeSpan(c) => c.start(buffer),
+            Block::Table(t) => t.start(buffer),
+            Block::Image(i) => i.start(buffer),
+            Block::Math(m) => m.start(buffer),
+            Block::ListItem(l) => l.start(buffer),
+            Block::BlockQuote(q) => q.start(buffer),
+            Block::ThematicBreak(t) => t.start(buffer),
         }
     }
 
@@ -206,6 +296,14 @@ impl Blocking for Block {
             Block::Link(l) => l.end(buffer),
             Block::Emphasis(e) => e.end(buffer),
             Block::StrongEmphasis(s) => s.end(buffer),
+            Block::Strikethrough(t) => t.end(buffer),
+            Block::CodeSpan(c) => c.end(buffer),
+            Block::Table(t) => t.end(buffer),
+            Block::Image(i) => i.end(buffer),
+            Block::Math(m) => m.end(buffer),
+            Block::ListItem(l) => l.end(buffer),
+            Block::BlockQuote(q) => q.end(buffer),
+            Block::ThematicBreak(t) => t.end(buffer),
         }
     }
 
@@ -217,6 +315,14 @@ impl Blocking for Block {
             Block::Link(l) => l.left(),
             Block::Emphasis(e) => e.left(),
             Block::StrongEmphasis(s) => s.left(),
+            Block::Strikethrough(t) => t.left(),
+            Block::CodeSpan(c) => c.left(),
+            Block::Table(t) => t.left(),
+            Block::Image(i) => i.left(),
+            Block::Math(m) => m.left(),
+            Block::ListItem(l) => l.left(),
+            Block::BlockQuote(q) => q.left(),
+            Block::ThematicBreak(t) => t.left(),
         }
     }
 
@@ -228,6 +334,14 @@ impl Blocking for Block {
             Block::Link(l) => l.right(),
             Block::Emphasis(e) => e.right(),
             Block::StrongEmphasis(s) => s.right(),
+            Block::Strikethrough(t) => t.right(),
+            Block::CodeSpan(c) => c.right(),
+            Block::Table(t) => t.right(),
+            Block::Image(i) => i.right(),
+            Block::Math(m) => m.right(),
+            Block::ListItem(l) => l.right(),
+            Block::BlockQuote(q) => q.right(),
+            Block::ThematicBreak(t) => t.right(),
         }
     }
 
@@ -239,6 +353,14 @@ impl Blocking for Block {
             Block::Link(l) => l.mount(view, buffer),
             Block::Emphasis(e) => e.mount(view, buffer),
             Block::StrongEmphasis(s) => s.mount(view, buffer),
+            Block::Strikethrough(t) => t.mount(view, buffer),
+            Block::CodeSpan(c) => c.mount(view, buffer),
+            Block::Table(t) => t.mount(view, buffer),
+            Block::Image(i) => i.mount(view, buffer),
+            Block::Math(m) => m.mount(view, buffer),
+            Block::ListItem(l) => l.mount(view, buffer),
+            Block::BlockQuote(q) => q.mount(view, buffer),
+            Block::ThematicBreak(t) => t.mount(view, buffer),
         }
     }
 
@@ -250,6 +372,14 @@ impl Blocking for Block {
             Block::Link(l) => l.umount(view, buffer),
             Block::Emphasis(e) => e.umount(view, buffer),
             Block::StrongEmphasis(s) => s.umount(view, buffer),
+            Block::Strikethrough(t) => t.umount(view, buffer),
+            Block::CodeSpan(c) => c.umount(view, buffer),
+            Block::Table(t) => t.umount(view, buffer),
+            Block::Image(i) => i.umount(view, buffer),
+            Block::Math(m) => m.umount(view, buffer),
+            Block::ListItem(l) => l.umount(view, buffer),
+            Block::BlockQuote(q) => q.umount(view, buffer),
+            Block::ThematicBreak(t) => t.umount(view, buffer),
         }
     }
 
@@ -261,6 +391,14 @@ impl Blocking for Block {
             Block::Link(l) => l.cursor_in(view, buffer),
             Block::Emphasis(e) => e.cursor_in(view, buffer),
             Block::StrongEmphasis(s) => s.cursor_in(view, buffer),
+            Block::Strikethrough(t) => t.cursor_in(view, buffer),
+            Block::CodeSpan(c) => c.cursor_in(view, buffer),
+            Block::Table(t) => t.cursor_in(view, buffer),
+            Block::Image(i) => i.cursor_in(view, buffer),
+            Block::Math(m) => m.cursor_in(view, buffer),
+            Block::ListItem(l) => l.cursor_in(view, buffer),
+            Block::BlockQuote(q) => q.cursor_in(view, buffer),
+            Block::ThematicBreak(t) => t.cursor_in(view, buffer),
         }
     }
 
@@ -272,6 +410,14 @@ impl Blocking for Block {
             Block::Link(l) => l.cursor_out(view, buffer),
             Block::Emphasis(e) => e.cursor_out(view, buffer),
             Block::StrongEmphasis(s) => s.cursor_out(view, buffer),
+            Block::Strikethrough(t) => t.cursor_out(view, buffer),
+            Block::CodeSpan(c) => c.cursor_out(view, buffer),
+            Block::Table(t) => t.cursor_out(view, buffer),
+            Block::Image(i) => i.cursor_out(view, buffer),
+            Block::Math(m) => m.cursor_out(view, buffer),
+            Block::ListItem(l) => l.cursor_out(view, buffer),
+            Block::BlockQuote(q) => q.cursor_out(view, buffer),
+            Block::ThematicBreak(t) => t.cursor_out(view, buffer),
         }
     }
 }
@@ -280,4 +426,54 @@ impl Block {
     pub fn is_anonymous(&self) -> bool {
         matches!(self, Block::Anonymous(_))
     }
+
+    /// Toggle the task checkbox at the iter, `false` if there is none.
+    pub fn toggle_checkbox(
+        &self,
+        buffer: &TextBuffer,
+        iter: &TextIter,
+    ) -> bool {
+        match self {
+            Block::ListItem(l) => l.toggle_checkbox(buffer, iter),
+            _ => false,
+        }
+    }
+
+    /// Blocks of the math in the text of a paragraph node, the text is the
+    /// whole content that the node is parsed from.
+    pub fn math_blocks(
+        node: &Node,
+        text: &str,
+        buffer: &TextBuffer,
+        pos: &Positions,
+    ) -> Vec<Block> {
+        if node.kind() != "paragraph" {
+            return vec![];
+        }
+        Math::from_paragraph(node, text, buffer, pos)
+            .into_iter()
+            .map(Block::Math)
+            .collect()
+    }
+
+    /// Resolve the file of the image block by its link, the image is shown
+    /// when the block is mounted.
+    pub fn resolve_image(
+        &mut self,
+        resolve: impl Fn(&str) -> Option<PathBuf>,
+        max_width: i32,
+    ) {
+        if let Block::Image(i) = self {
+            let file = resolve(i.link());
+            i.set_file(file, max_width);
+        }
+    }
+
+    /// Pad the columns if the block is a table, `false` if nothing changed.
+    pub fn format_table(&self, buffer: &TextBuffer) -> bool {
+        match self {
+            Block::Table(t) => t.format(buffer),
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,130 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::Node;
+use rustybrain_core::position::Positions;
+
+use super::Blocking;
+
+/// Block quote, its `>` markers are drawn as a bar at the side.
+pub struct BlockQuote {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for BlockQuote {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        BlockQuote { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "blockquote",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn umount(&self, _view: &TextView, buffer: &TextBuffer) {
+        let (start, end) = (self.start(buffer), self.end(buffer));
+        buffer.remove_tag_by_name("blockquote", &start, &end);
+        for (s, e) in self.markers(buffer) {
+            buffer.remove_tag_by_name("quote-bar", &s, &e);
+        }
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        for (s, e) in self.markers(buffer) {
+            buffer.remove_tag_by_name("quote-bar", &s, &e);
+        }
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        for (s, e) in self.markers(buffer) {
+            buffer.apply_tag_by_name("quote-bar", &s, &e);
+        }
+    }
+}
+
+impl BlockQuote {
+    /// The first `>` of every line in the quote.
+    fn markers(&self, buffer: &TextBuffer) -> Vec<(TextIter, TextIter)> {
+        let end = self.end(buffer);
+        let mut line = self.start(buffer);
+        line.set_line_offset(0);
+        let mut markers = vec![];
+        while line < end {
+            let mut iter = line;
+            while iter.char() == ' ' && !iter.ends_line() {
+                iter.forward_char();
+            }
+            if iter.char() == '>' {
+                let mut next = iter;
+                next.forward_char();
+                markers.push((iter, next));
+            }
+            if !line.forward_line() {
+                break;
+            }
+        }
+        markers
+    }
+}
+
+/// `---`, `***` or `___` that is drawn as a line.
+pub struct ThematicBreak {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for ThematicBreak {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        ThematicBreak { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, _buffer: &TextBuffer) {}
+
+    fn umount(&self, view: &TextView, buffer: &TextBuffer) {
+        self.cursor_in(view, buffer);
+        buffer.delete_mark(self.left());
+        buffer.delete_mark(self.right());
+    }
+
+    fn cursor_in(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.remove_tag_by_name(
+            "thematic-break",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+
+    fn cursor_out(&self, _view: &TextView, buffer: &TextBuffer) {
+        buffer.apply_tag_by_name(
+            "thematic-break",
+            &self.start(buffer),
+            &self.end(buffer),
+        );
+    }
+}
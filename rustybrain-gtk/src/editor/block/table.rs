@@ -0,0 +1,161 @@
+use gtk::prelude::*;
+use gtk::TextBuffer;
+use gtk::TextIter;
+use gtk::TextMark;
+use gtk::TextView;
+use rustybrain_core::md::{self, Node};
+use rustybrain_core::position::Positions;
+
+use super::Blocking;
+
+/// GFM table, it is laid out as a grid by the monospace font once the
+/// columns are padded.
+pub struct Table {
+    left: TextMark,
+    right: TextMark,
+}
+
+impl Blocking for Table {
+    fn from_node(node: &Node, buffer: &TextBuffer, pos: &Positions) -> Self {
+        let (left, right) = Self::node_endpoint(node, buffer, pos);
+        Table { left, right }
+    }
+
+    fn left(&self) -> &TextMark {
+        &self.left
+    }
+
+    fn right(&self) -> &TextMark {
+        &self.right
+    }
+
+    fn mount(&self, _view: &TextView, buffer: &TextBuffer) {
+        let (start, end) = (self.start(buffer), self.end(buffer));
+        buffer.apply_tag_by_name("table", &start, &end);
+
+        let mut line = start;
+        let mut row = 0;
+        while line < end {
+            let mut line_end = line;
+            if !line_end.ends_line() {
+                line_end.forward_to_line_end();
+            }
+            match row {
+                0 => buffer.apply_tag_by_name("table-header", &line, &line_end),
+                1 => buffer.apply_tag_by_name(
+                    "table-delimiter",
+                    &line,
+                    &line_end,
+                ),
+                _ => {}
+            }
+            for (s, e) in pipes(&line, &line_end) {
+                buffer.apply_tag_by_name("table-pipe", &s, &e);
+            }
+            row += 1;
+            if !line.forward_line() {
+                break;
+            }
+        }
+    }
+}
+
+impl Table {
+    /// Pad the columns of the table in the buffer, `false` if they are
+    /// aligned already.
+    pub fn format(&self, buffer: &TextBuffer) -> bool {
+        let (mut start, mut end) = (self.start(buffer), self.end(buffer));
+        let text = buffer.text(&start, &end, true);
+        let formatted = md::format_table(&text);
+        if formatted == text.as_str() {
+            return false;
+        }
+        let cursor = buffer.cursor_position();
+        buffer.begin_user_action();
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, &formatted);
+        buffer.end_user_action();
+        buffer.place_cursor(&buffer.iter_at_offset(cursor));
+        true
+    }
+}
+
+/// Unescaped pipes between the iters.
+fn pipes(start: &TextIter, end: &TextIter) -> Vec<(TextIter, TextIter)> {
+    let mut pipes = vec![];
+    let mut iter = *start;
+    let mut escaped = false;
+    while iter < *end {
+        let c = iter.char();
+        let mut next = iter;
+        next.forward_char();
+        if c == '|' && !escaped {
+            pipes.push((iter, next));
+        }
+        escaped = c == '\\' && !escaped;
+        iter = next;
+    }
+    pipes
+}
+
+/// Move the cursor to the next cell of the table it is in, or to the start
+/// of the current or previous cell if not `forward`.
+///
+/// `false` is returned if the cursor is not in a table or there is no cell
+/// to move to.
+pub fn move_to_cell(buffer: &TextBuffer, forward: bool) -> bool {
+    let tag = match buffer.tag_table().lookup("table") {
+        Some(tag) => tag,
+        None => return false,
+    };
+    let cursor = buffer.iter_at_offset(buffer.cursor_position());
+    if !cursor.has_tag(&tag) {
+        return false;
+    }
+    let mut iter = cursor;
+    loop {
+        let moved = if forward {
+            iter.forward_char()
+        } else {
+            iter.backward_char()
+        };
+        if !moved || !iter.has_tag(&tag) {
+            return false;
+        }
+        if iter.char() != '|' || is_escaped(&iter) {
+            continue;
+        }
+        let mut cell = iter;
+        cell.forward_char();
+        if cell.ends_line() || is_delimiter_row(buffer, &cell) {
+            continue;
+        }
+        if cell.char() == ' ' {
+            cell.forward_char();
+        }
+        if !forward && cell.offset() >= cursor.offset() {
+            continue;
+        }
+        buffer.place_cursor(&cell);
+        return true;
+    }
+}
+
+fn is_escaped(iter: &TextIter) -> bool {
+    let mut prev = *iter;
+    prev.backward_char() && prev.char() == '\\'
+}
+
+fn is_delimiter_row(buffer: &TextBuffer, iter: &TextIter) -> bool {
+    let mut start = *iter;
+    start.set_line_offset(0);
+    let mut end = start;
+    if !end.ends_line() {
+        end.forward_to_line_end();
+    }
+    let line = buffer.text(&start, &end, true);
+    line.contains('-')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | ':' | '-' | ' ' | '\t'))
+}
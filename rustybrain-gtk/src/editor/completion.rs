@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gdk::Key;
+use gtk::prelude::*;
+use gtk::{EventControllerKey, Inhibit, TextBuffer, TextIter, TextView};
+use relm4::send;
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::md::{self, LinkQuery};
+use rustybrain_core::zettel::Zettel;
+
+use super::Msg;
+
+/// Notes offered at most.
+const LIMIT: usize = 8;
+
+/// Note that a completed link goes to.
+enum Candidate {
+    Zettel(Rc<RefCell<Zettel>>),
+    /// A note of the title is created for the link.
+    New(String),
+}
+
+/// Popover of the notes to link to while a link is typed in the view.
+pub struct Completion {
+    popover: gtk::Popover,
+    list: gtk::ListBox,
+
+    query: Option<LinkQuery>,
+    candidates: Vec<Candidate>,
+    /// Line and start of the query that is dismissed by escape.
+    dismissed: Option<(i32, usize)>,
+}
+
+impl Completion {
+    pub fn new(view: &TextView, sender: relm4::Sender<Msg>) -> Self {
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        let s = sender.clone();
+        list.connect_row_activated(move |_, row| {
+            send!(s, Msg::Complete(row.index()))
+        });
+        let popover = gtk::Popover::builder()
+            .autohide(false)
+            .has_arrow(false)
+            .position(gtk::PositionType::Bottom)
+            .child(&list)
+            .build();
+        popover.set_parent(view);
+
+        // Keys are taken before the view while the popover is shown.
+        let keys = EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let (p, l) = (popover.clone(), list.clone());
+        keys.connect_key_pressed(move |_, key, _, _| {
+            if !p.is_visible() {
+                return Inhibit(false);
+            }
+            match key {
+                Key::Down | Key::Up => {
+                    select_next(&l, key == Key::Down);
+                    Inhibit(true)
+                }
+                Key::Return | Key::KP_Enter | Key::Tab => {
+                    if let Some(row) = l.selected_row() {
+                        send!(sender, Msg::Complete(row.index()));
+                    }
+                    Inhibit(true)
+                }
+                Key::Escape => {
+                    send!(sender, Msg::DismissCompletion);
+                    Inhibit(true)
+                }
+                _ => Inhibit(false),
+            }
+        });
+        view.add_controller(&keys);
+
+        Completion {
+            popover,
+            list,
+            query: None,
+            candidates: vec![],
+            dismissed: None,
+        }
+    }
+
+    /// Offer the notes for the link typed before the cursor, the popover
+    /// is hidden if there is none.
+    pub fn update(&mut self, view: &TextView, kasten: &Kasten) {
+        let buffer = view.buffer();
+        let (line, cursor) = line_to_cursor(&buffer);
+        let text = buffer.slice(&line, &cursor, true);
+        let query = md::link_query(&text)
+            .filter(|q| self.dismissed != Some((cursor.line(), q.start())));
+        let query = match query {
+            Some(query) => query,
+            None => return self.hide(),
+        };
+        if self.query.as_ref() != Some(&query) {
+            self.fill(&query, kasten);
+        }
+        self.query = Some(query);
+
+        let rect = view.iter_location(&cursor);
+        let (x, y) = view.buffer_to_window_coords(
+            gtk::TextWindowType::Widget,
+            rect.x(),
+            rect.y(),
+        );
+        self.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+            x,
+            y,
+            1,
+            rect.height(),
+        )));
+        self.popover.popup();
+    }
+
+    /// Hide the popover until the query is typed again.
+    pub fn dismiss(&mut self, view: &TextView) {
+        if let Some(query) = self.query.as_ref() {
+            let (_, cursor) = line_to_cursor(&view.buffer());
+            self.dismissed = Some((cursor.line(), query.start()));
+        }
+        self.hide();
+    }
+
+    /// Replace the typed link by the one to the note of the row, the note
+    /// is created first if it is a new one.
+    pub fn complete(
+        &mut self,
+        index: i32,
+        buffer: &TextBuffer,
+        kasten: &mut Kasten,
+    ) -> Result<(), anyhow::Error> {
+        let query = match self.query.take() {
+            Some(query) => query,
+            None => return Ok(()),
+        };
+        let zettel = match self.candidates.get(index as usize) {
+            Some(Candidate::Zettel(z)) => z.clone(),
+            Some(Candidate::New(title)) => kasten.create(title)?,
+            None => return Ok(()),
+        };
+        self.hide();
+
+        let (line, mut cursor) = line_to_cursor(buffer);
+        let text = buffer.slice(&line, &cursor, true);
+        let mut start = line;
+        start.forward_chars(text[..query.start()].chars().count() as i32);
+        let z = zettel.borrow();
+        let link = if query.target() {
+            let close = if cursor.char() == ')' { "" } else { ")" };
+            format!("{}{}", z.zid(), close)
+        } else {
+            // A link typed by an alias keeps it as the text.
+            let typed = query.query().trim().to_lowercase();
+            let text = z
+                .aliases()
+                .iter()
+                .find(|a| a.to_lowercase() == typed)
+                .map_or(z.title(), |a| a.as_str());
+            format!("[{}]({})", text, z.zid())
+        };
+        buffer.begin_user_action();
+        buffer.delete(&mut start, &mut cursor);
+        buffer.insert(&mut start, &link);
+        buffer.end_user_action();
+        Ok(())
+    }
+
+    fn hide(&mut self) {
+        self.query = None;
+        self.popover.popdown();
+    }
+
+    fn fill(&mut self, query: &LinkQuery, kasten: &Kasten) {
+        let q = query.query().trim();
+        // The note of the title or alias typed comes first.
+        let named = kasten.resolve(q);
+        self.candidates = named
+            .iter()
+            .cloned()
+            .chain(
+                kasten
+                    .complete_title(q, LIMIT)
+                    .into_iter()
+                    .filter(|z| !named.iter().any(|n| Rc::ptr_eq(n, z))),
+            )
+            .take(LIMIT)
+            .map(Candidate::Zettel)
+            .collect();
+        if !q.is_empty() && named.is_none() {
+            self.candidates.push(Candidate::New(q.to_string()));
+        }
+
+        while let Some(row) = self.list.last_child() {
+            self.list.remove(&row);
+        }
+        for c in self.candidates.iter() {
+            let text = match c {
+                Candidate::Zettel(z) => z.borrow().title().to_string(),
+                Candidate::New(title) => {
+                    format!("Create \u{201c}{}\u{201d}", title)
+                }
+            };
+            let label = gtk::Label::builder()
+                .label(&text)
+                .halign(gtk::Align::Start)
+                .build();
+            self.list.append(&label);
+        }
+        self.list.select_row(self.list.row_at_index(0).as_ref());
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        self.popover.unparent();
+    }
+}
+
+/// Iters at the start of the line of the cursor and at the cursor.
+fn line_to_cursor(buffer: &TextBuffer) -> (TextIter, TextIter) {
+    let cursor = buffer.iter_at_offset(buffer.cursor_position());
+    let mut line = cursor;
+    line.set_line_offset(0);
+    (line, cursor)
+}
+
+fn select_next(list: &gtk::ListBox, down: bool) {
+    let index = list.selected_row().map_or(-1, |r| r.index());
+    let next = if down { index + 1 } else { index - 1 };
+    if let Some(row) = list.row_at_index(next) {
+        list.select_row(Some(&row));
+    }
+}
@@ -1,69 +1,183 @@
 mod block;
+mod completion;
 mod style;
 
 use std::cell::RefCell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
 
+use chrono::Local;
+use gdk::Key;
 use gtk::{
-    prelude::*, ActionBar, EventControllerFocus, MessageType, ScrolledWindow,
-    TextTagTable, TextView,
+    prelude::*, ActionBar, EventControllerFocus, EventControllerKey, Inhibit,
+    MessageType, ScrolledWindow, TextTagTable, TextView,
 };
 use relm4::{send, ComponentUpdate, Components, Widgets};
+use rustybrain_core::config::{Autosave, ConfigLoader, Theme};
+use rustybrain_core::draft::{Draft, Drafts};
 use rustybrain_core::kasten::Kasten;
+use rustybrain_core::md::{self, InputEdit, Node, Point};
+use rustybrain_core::position::Positions;
 use rustybrain_core::zettel::Zettel;
 
 use self::block::Blocking;
 
+fn is_image(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    matches!(
+        ext.as_deref(),
+        Some("png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp")
+    )
+}
+
+/// Aliases of the text of the aliases entry, the empty ones are dropped.
+fn split_aliases(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_string())
+        .collect()
+}
+
+/// Character of the objects, such as images, in the text of a buffer.
+const OBJECT_REPLACEMENT: char = '\u{fffc}';
+
 pub enum Msg {
     Open(Rc<RefCell<Zettel>>),
     Insert(Rc<RefCell<Zettel>>),
     OpenOnStack(Rc<RefCell<Zettel>>),
     Changed,
     Save,
+    /// Save the note on the top of the stack and insert its link into the
+    /// note under it.
+    Done,
     Cursor,
+    /// Click in the text view at the widget coordinates.
+    Click(f64, f64),
+    /// File dropped at the widget coordinates of the text view.
+    DropFile(PathBuf, f64, f64),
+    PasteImage(gdk::Texture),
     EditTitle,
     DoneEditTitle,
+    /// Restyle by the theme, in dark mode or not.
+    Theme(Theme, bool),
+    /// A while after the change of the count, drafts are written if there
+    /// is no change since.
+    Idle(usize),
+    /// Save the changed notes if there is no change since the count.
+    Autosave(usize),
+    /// Quit the app, asking to save the changed notes first.
+    Quit,
+    QuitResponse(gtk::ResponseType),
+    /// Offer to restore the drafts left by the last run.
+    OfferDrafts,
+    RestoreDrafts(bool),
+    /// The tab of the index is switched to in the notebook.
+    SwitchTab(u32),
+    NextTab,
+    PrevTab,
+    /// Close the tab of the id, or the current one if `None`.
+    CloseTab(Option<usize>),
+    CloseResponse(usize, gtk::ResponseType),
+    /// Complete the link by the row of the completion.
+    Complete(i32),
+    DismissCompletion,
 }
 
+/// Seconds without an edit before drafts are written.
+const DRAFT_DELAY: u64 = 1;
+
 /// Zettel that be editing.
 pub struct EditingZettel {
     title: gtk::EntryBuffer,
+    /// Aliases separated by commas.
+    aliases: gtk::EntryBuffer,
     buffer: gtk::TextBuffer,
     zettel: Rc<RefCell<Zettel>>,
 
     view: TextView,
     blocks: Vec<block::Block>,
 
+    /// Edits of the buffer since the last parse.
+    edits: Rc<RefCell<Vec<InputEdit>>>,
+
+    kasten: Rc<RefCell<Kasten>>,
+
+    /// Title, aliases and content as they are on disk.
+    saved: (String, String, String),
+
     #[allow(dead_code)]
     table: TextTagTable,
 }
 
 impl EditingZettel {
-    fn new(zettel: Rc<RefCell<Zettel>>, view: TextView) -> Self {
-        let table = style::Style::new().table();
+    fn new(
+        zettel: Rc<RefCell<Zettel>>,
+        view: TextView,
+        kasten: Rc<RefCell<Kasten>>,
+        table: TextTagTable,
+    ) -> Self {
         let buffer = gtk::TextBuffer::builder()
             .enable_undo(true)
             .tag_table(&table)
             .build();
         let title = gtk::EntryBuffer::builder().build();
+        let aliases = gtk::EntryBuffer::builder().build();
 
         buffer.set_text(zettel.borrow().content());
         title.set_text(zettel.borrow().title());
+        aliases.set_text(&zettel.borrow().aliases().join(", "));
+        let saved = (
+            zettel.borrow().title().to_string(),
+            aliases.text(),
+            zettel.borrow().content().to_string(),
+        );
 
         let mut r = Self {
             buffer,
             title,
+            aliases,
             zettel,
             table,
             view,
 
             blocks: vec![],
+            edits: Rc::new(RefCell::new(vec![])),
+            kasten,
+            saved,
         };
-        r.on_buffer_changed();
+        let len = r.zettel.borrow().content().len();
+        r.restyle(vec![0..len]);
         r
     }
 
     fn listen_buffer_event(&self, sender: relm4::Sender<Msg>) {
+        // Both handlers run before the buffer changes, so the iters are
+        // positions of the old text that the tree is parsed from.
+        let edits = self.edits.clone();
+        self.buffer
+            .connect_insert_text(move |buffer, location, text| {
+                let (byte, point) = Self::position(buffer, location);
+                edits.borrow_mut().push(md::insert_edit(byte, point, text));
+            });
+        let edits = self.edits.clone();
+        self.buffer.connect_delete_range(move |buffer, start, end| {
+            let (start_byte, start) = Self::position(buffer, start);
+            let (end_byte, end) = Self::position(buffer, end);
+            if start_byte == end_byte {
+                // Only the object of an image is deleted.
+                return;
+            }
+            edits
+                .borrow_mut()
+                .push(md::delete_edit(start_byte, start, end_byte, end));
+        });
+
         let s = sender.clone();
 
         self.buffer.connect_changed(move |_| send!(s, Msg::Changed));
@@ -73,29 +187,169 @@ impl EditingZettel {
         });
     }
 
-    fn on_buffer_changed(&mut self) {
-        while let Some(blk) = self.blocks.pop() {
-            blk.umount(&self.view, &self.buffer);
-        }
+    /// Byte offset and point of the iter in the text of the buffer, objects
+    /// of images are not in the text.
+    fn position(
+        buffer: &gtk::TextBuffer,
+        iter: &gtk::TextIter,
+    ) -> (usize, Point) {
+        let byte = buffer.text(&buffer.start_iter(), iter, true).len();
+        let mut line = *iter;
+        line.set_line_offset(0);
+        let column = buffer.text(&line, iter, true).len();
+        (byte, Point::new(iter.line() as usize, column))
+    }
 
+    fn on_buffer_changed(&mut self) {
+        let edits: Vec<InputEdit> = self.edits.borrow_mut().drain(..).collect();
         let start = self.buffer.start_iter();
         let end = self.buffer.end_iter();
+        let text = self.buffer.text(&start, &end, true);
+        let ranges = match self
+            .zettel
+            .borrow_mut()
+            .edit_content(text.as_str(), &edits)
+        {
+            Ok(ranges) => ranges,
+            Err(_) => return,
+        };
+        self.restyle(ranges);
+    }
 
-        self.buffer.remove_all_tags(&start, &end);
-        self.buffer.apply_tag_by_name("p", &start, &end);
+    /// Remount blocks in the byte ranges, blocks that overlap the ranges are
+    /// remounted as a whole.
+    fn restyle(&mut self, mut ranges: Vec<Range<usize>>) {
+        let zettel = self.zettel.borrow();
+        let buffer = &self.buffer;
+        loop {
+            // Math is found in the text of paragraphs, they are restyled as
+            // a whole.
+            if let Some(tree) = zettel.tree() {
+                Self::paragraph_ranges(&tree.root_node(), &mut ranges);
+                ranges = md::merge_ranges(&mut ranges, zettel.content().len());
+            }
+            // Unmounted images take their objects out of the buffer.
+            let pos = Self::positions(buffer, zettel.content());
+            let block_range = |blk: &block::Block| {
+                let start =
+                    pos.char_to_byte(blk.start(buffer).offset() as usize);
+                let end = pos.char_to_byte(blk.end(buffer).offset() as usize);
+                start..end
+            };
+            let (dirty, kept): (Vec<_>, Vec<_>) = self
+                .blocks
+                .drain(..)
+                .partition(|blk| Self::overlaps(&ranges, &block_range(blk)));
+            self.blocks = kept;
+            if dirty.is_empty() {
+                break;
+            }
+            ranges.extend(dirty.iter().map(block_range));
+            for blk in dirty {
+                blk.umount(&self.view, buffer);
+            }
+            ranges = md::merge_ranges(&mut ranges, zettel.content().len());
+        }
 
-        let text = self.buffer.text(&start, &end, true);
-        if self.zettel.borrow_mut().set_content(text.as_str()).is_err() {
+        let pos = Self::positions(buffer, zettel.content());
+        for r in ranges.iter() {
+            let start = buffer.iter_at_offset(pos.byte_to_char(r.start) as i32);
+            let end = buffer.iter_at_offset(pos.byte_to_char(r.end) as i32);
+            buffer.remove_all_tags(&start, &end);
+            buffer.apply_tag_by_name("p", &start, &end);
+        }
+
+        let mut blocks = vec![];
+        if let Some(tree) = zettel.tree() {
+            Self::collect_nodes(
+                &tree.root_node(),
+                &ranges,
+                zettel.content(),
+                &pos,
+                buffer,
+                &mut blocks,
+            );
+        }
+        // Blocks are mounted after all of them are marked, marks keep up
+        // with the objects that images insert.
+        let kasten = self.kasten.borrow();
+        let max_width = kasten.image_max_width();
+        for blk in blocks.iter_mut() {
+            blk.resolve_image(
+                |link| kasten.resolve_attachment(&zettel, link),
+                max_width,
+            );
+            blk.mount(&self.view, buffer);
+        }
+        self.blocks.extend(blocks);
+    }
+
+    /// Positions of the content in the buffer, that has an object for every
+    /// shown image.
+    fn positions<'a>(
+        buffer: &gtk::TextBuffer,
+        content: &'a str,
+    ) -> Positions<'a> {
+        let slice =
+            buffer.slice(&buffer.start_iter(), &buffer.end_iter(), true);
+        let mut objects = vec![];
+        let mut chars = 0;
+        for c in slice.chars() {
+            if c == OBJECT_REPLACEMENT {
+                objects.push(chars);
+            } else {
+                chars += 1;
+            }
+        }
+        Positions::with_objects(content, objects)
+    }
+
+    /// Blocks of the node and its descendants that overlap the ranges.
+    fn collect_nodes(
+        node: &Node,
+        ranges: &[Range<usize>],
+        content: &str,
+        pos: &Positions,
+        buffer: &gtk::TextBuffer,
+        blocks: &mut Vec<block::Block>,
+    ) {
+        if !Self::overlaps(ranges, &block::node_range(node)) {
             return;
-        };
+        }
+        let blk = block::Block::from_node(node, buffer, pos);
+        if !blk.is_anonymous() {
+            blocks.push(blk);
+        }
+        blocks.extend(block::Block::math_blocks(node, content, buffer, pos));
+        for i in 0..node.child_count() {
+            if let Some(child) = node.child(i) {
+                Self::collect_nodes(
+                    &child, ranges, content, pos, buffer, blocks,
+                );
+            }
+        }
+    }
 
-        let zettel = &self.zettel.borrow();
-        let iter = zettel.walk_iter();
-        for node in iter {
-            let blk = block::Block::from_node(&node, &self.buffer);
-            blk.mount(&self.view, &self.buffer);
-            self.blocks.push(blk);
+    /// Add ranges of the paragraphs that overlap the ranges.
+    fn paragraph_ranges(node: &Node, ranges: &mut Vec<Range<usize>>) {
+        if !Self::overlaps(ranges, &node.byte_range()) {
+            return;
         }
+        if node.kind() == "paragraph" {
+            ranges.push(node.byte_range());
+            return;
+        }
+        for i in 0..node.child_count() {
+            if let Some(child) = node.child(i) {
+                Self::paragraph_ranges(&child, ranges);
+            }
+        }
+    }
+
+    /// Touching ranges overlap too, an empty range of deletion restyles the
+    /// block around it.
+    fn overlaps(ranges: &[Range<usize>], r: &Range<usize>) -> bool {
+        ranges.iter().any(|x| x.start <= r.end && r.start <= x.end)
     }
 
     fn on_cursor_notify(&mut self) {
@@ -116,44 +370,194 @@ impl EditingZettel {
         }
     }
 
+    /// Toggle the task checkbox under the click if there is one.
+    fn on_click(&self, x: f64, y: f64) {
+        let iter = match self.iter_at(x, y) {
+            Some(iter) => iter,
+            None => return,
+        };
+        for blk in &self.blocks {
+            if blk.toggle_checkbox(&self.buffer, &iter) {
+                return;
+            }
+        }
+    }
+
+    /// Iter at the widget coordinates of the text view.
+    fn iter_at(&self, x: f64, y: f64) -> Option<gtk::TextIter> {
+        let (x, y) = self.view.window_to_buffer_coords(
+            gtk::TextWindowType::Widget,
+            x as i32,
+            y as i32,
+        );
+        self.view.iter_at_location(x, y)
+    }
+
+    /// Insert the image at the coordinates, or at the cursor if `None`.
+    fn insert_image(&self, alt: &str, link: &str, at: Option<(f64, f64)>) {
+        let mut iter = match at.and_then(|(x, y)| self.iter_at(x, y)) {
+            Some(iter) => iter,
+            None => self.buffer.iter_at_offset(self.buffer.cursor_position()),
+        };
+        self.buffer
+            .insert(&mut iter, &format!("![{}](<{}>)", alt, link));
+    }
+
     fn insert_zettel_at_cursor(&self, z: &Zettel) {
         self.buffer
             .insert_at_cursor(&format!("[{}]({})", z.title(), z.zid(),));
     }
 
+    /// Pad the columns of the tables, the zettel is reparsed now for it to
+    /// be saved with them.
+    fn format_tables(&mut self) {
+        let mut formatted = false;
+        for blk in self.blocks.iter() {
+            formatted |= blk.format_table(&self.buffer);
+        }
+        if formatted {
+            self.on_buffer_changed();
+        }
+    }
+
     fn save(
         &mut self,
         kasten: &mut Kasten,
+        drafts: &Drafts,
         parent_sender: relm4::Sender<super::Msg>,
     ) -> bool {
         if !self.buffer.is_modified() {
             return false;
         }
 
+        // It's still modified if the write fails, so it's not lost.
+        match self.write(kasten, drafts) {
+            Ok(()) => {
+                self.buffer.set_modified(false);
+                true
+            }
+            Err(err) => {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowMsg(
+                        MessageType::Error,
+                        format!("Save note failed: {:?}", err)
+                    )
+                );
+                false
+            }
+        }
+    }
+
+    /// Save the title, the aliases and the content, the draft is not needed
+    /// then.
+    fn write(
+        &mut self,
+        kasten: &mut Kasten,
+        drafts: &Drafts,
+    ) -> Result<(), anyhow::Error> {
         // TODO set when title is changed
         let title = self.title.text();
+        let aliases = self.aliases.text();
         self.zettel.borrow_mut().set_title(&title);
+        self.zettel
+            .borrow_mut()
+            .set_aliases(split_aliases(&aliases));
 
-        if let Err(err) = kasten.save(&self.zettel.borrow()) {
-            send!(
-                parent_sender,
-                super::Msg::ShowMsg(
-                    MessageType::Error,
-                    format!("Save note failed: {:?}", err)
-                )
-            );
+        let zettel = self.zettel.borrow();
+        kasten.save(&zettel)?;
+        self.saved = (title, aliases, zettel.content().to_string());
+        drafts.remove(zettel.zid())
+    }
+
+    /// Whether the title, the aliases or the content differ from the saved
+    /// ones.
+    fn is_dirty(&self) -> bool {
+        let zettel = self.zettel.borrow();
+        self.saved.0 != self.title.text().as_str()
+            || self.saved.1 != self.aliases.text().as_str()
+            || self.saved.2 != zettel.content()
+    }
+
+    fn draft(&self) -> Draft {
+        let zettel = self.zettel.borrow();
+        Draft::new(zettel.zid(), &self.title.text(), zettel.content())
+    }
+}
+
+/// Notes open in a tab, each note on the stack is inserted into the one
+/// below it once saved.
+struct Tab {
+    id: usize,
+    stack: Vec<EditingZettel>,
+
+    view: TextView,
+    page: ScrolledWindow,
+    header: gtk::Box,
+    label: gtk::Label,
+    completion: completion::Completion,
+}
+
+impl Tab {
+    fn new(id: usize, sender: relm4::Sender<Msg>) -> Self {
+        let view = Model::new_view(sender.clone());
+        let completion = completion::Completion::new(&view, sender.clone());
+        let page = ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(10)
+            .margin_bottom(10)
+            .child(&view)
+            .build();
+
+        let header = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(4)
+            .build();
+        let label = gtk::Label::new(None);
+        let close = gtk::Button::from_icon_name("window-close-symbolic");
+        close.set_has_frame(false);
+        close.connect_clicked(move |_| send!(sender, Msg::CloseTab(Some(id))));
+        header.append(&label);
+        header.append(&close);
+
+        Tab {
+            id,
+            stack: vec![],
+            view,
+            page,
+            header,
+            label,
+            completion,
         }
-        self.buffer.set_modified(false);
-        true
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.stack.iter().any(|z| z.is_dirty())
     }
 }
 
 pub struct Model {
     kasten: Rc<RefCell<Kasten>>,
-    stack: Vec<EditingZettel>,
+    tabs: Vec<Tab>,
+    /// Index of the shown tab.
+    current: usize,
+    /// Id of the next tab.
+    next_id: usize,
 
-    view: gtk::TextView,
     editing_title: bool,
+
+    style: style::Style,
+    /// Colors of the text view by the theme.
+    css: gtk::CssProvider,
+
+    autosave: Autosave,
+    drafts: Drafts,
+    /// Count of the changes of the buffers, timers started by an older
+    /// change do nothing.
+    changes: usize,
 }
 
 pub struct EditorComponents {}
@@ -179,12 +583,18 @@ impl relm4::Model for Model {
 
 pub struct Editor {
     layout: gtk::Box,
-    main_win: gtk::ScrolledWindow,
+    notebook: gtk::Notebook,
+    switch_handler: gtk::glib::SignalHandlerId,
     title_in: gtk::Entry,
+    aliases_in: gtk::Entry,
+    /// Entries of the title and the aliases.
+    title_edit: gtk::Box,
     title_label: gtk::Label,
+    aliases_label: gtk::Label,
     title_show: gtk::Box,
     action_bar: gtk::ActionBar,
     save_btn: gtk::Button,
+    done_btn: gtk::Button,
 }
 
 impl Model {
@@ -193,53 +603,610 @@ impl Model {
         zettel: Rc<RefCell<Zettel>>,
         sender: relm4::Sender<Msg>,
     ) {
-        loop {
-            if self.stack.pop().is_none() {
-                break;
-            }
+        // Zettels are reloaded by saves, the zid stays the same.
+        let zid = zettel.borrow().zid().to_string();
+        self.reload_header(&zettel);
+        let opened = self.tabs.iter().position(|t| match t.stack.first() {
+            Some(z) => z.zettel.borrow().zid() == zid,
+            None => false,
+        });
+        if let Some(i) = opened {
+            self.current = i;
+            return;
         }
 
+        self.tabs.push(Tab::new(self.next_id, sender.clone()));
+        self.next_id += 1;
+        self.current = self.tabs.len() - 1;
         self.open_zettel_on_stack(zettel, sender);
     }
 
+    /// Open notes take the header of the reloaded zettel, so that saving
+    /// them doesn't undo a move in the sequence.
+    fn reload_header(&mut self, zettel: &Rc<RefCell<Zettel>>) {
+        let reloaded = zettel.borrow();
+        for ez in self.tabs.iter_mut().flat_map(|t| t.stack.iter_mut()) {
+            if Rc::ptr_eq(&ez.zettel, zettel)
+                || ez.zettel.borrow().zid() != reloaded.zid()
+            {
+                continue;
+            }
+            ez.zettel.borrow_mut().reload_header(&reloaded);
+        }
+    }
+
     fn open_zettel_on_stack(
         &mut self,
         zettel: Rc<RefCell<Zettel>>,
         sender: relm4::Sender<Msg>,
     ) {
-        let ez = EditingZettel::new(zettel, self.view.clone());
+        if self.tabs.is_empty() {
+            return self.open_zettel(zettel, sender);
+        }
+        let tab = &mut self.tabs[self.current];
+        let ez = EditingZettel::new(
+            zettel,
+            tab.view.clone(),
+            self.kasten.clone(),
+            self.style.table(),
+        );
         ez.listen_buffer_event(sender);
-        self.stack.push(ez);
+        tab.stack.push(ez);
     }
 
+    /// The last note of a tab stays, it has no note to insert into.
     fn pop_stack_and_insert(&mut self, sender: relm4::Sender<Msg>) {
-        if let Some(z) = self.stack.pop() {
+        let tab = match self.tabs.get_mut(self.current) {
+            Some(tab) if tab.stack.len() > 1 => tab,
+            _ => return,
+        };
+        if let Some(z) = tab.stack.pop() {
             send!(sender, Msg::Insert(z.zettel));
         }
     }
 
-    fn on_buffer_changed(&mut self) {
-        if let Some(z) = self.stack.last_mut() {
+    fn remove_tab(&mut self, index: usize) {
+        self.tabs.remove(index);
+        if index < self.current || self.current >= self.tabs.len() {
+            self.current = self.current.saturating_sub(1);
+        }
+    }
+
+    /// Note on the top of the stack of the current tab.
+    fn top(&self) -> Option<&EditingZettel> {
+        self.tabs.get(self.current).and_then(|t| t.stack.last())
+    }
+
+    fn top_mut(&mut self) -> Option<&mut EditingZettel> {
+        self.tabs
+            .get_mut(self.current)
+            .and_then(|t| t.stack.last_mut())
+    }
+
+    /// Notes of all the tabs.
+    fn notes(&self) -> impl Iterator<Item = &EditingZettel> {
+        self.tabs.iter().flat_map(|t| t.stack.iter())
+    }
+
+    /// Switch to the tab that the user selected in the notebook, the other
+    /// panes follow its note.
+    fn switch_tab(
+        &mut self,
+        index: usize,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        if index == self.current || index >= self.tabs.len() {
+            return;
+        }
+        self.current = index;
+        if let Some(z) = self.tabs[index].stack.first() {
+            send!(parent_sender, super::Msg::ChangeZettel(z.zettel.clone()));
+        }
+    }
+
+    /// Switch to the next or previous tab, around the ends.
+    fn cycle_tab(
+        &mut self,
+        forward: bool,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let len = self.tabs.len();
+        if len < 2 {
+            return;
+        }
+        let index = if forward {
+            (self.current + 1) % len
+        } else {
+            (self.current + len - 1) % len
+        };
+        self.switch_tab(index, parent_sender);
+    }
+
+    /// Close the tab, asking to save its changed notes first.
+    fn close_tab(&mut self, id: Option<usize>, sender: relm4::Sender<Msg>) {
+        let index = match id {
+            Some(id) => self.tabs.iter().position(|t| t.id == id),
+            None if self.current < self.tabs.len() => Some(self.current),
+            None => None,
+        };
+        let index = match index {
+            Some(i) => i,
+            None => return,
+        };
+        let tab = &self.tabs[index];
+        if !tab.is_dirty() {
+            return self.remove_tab(index);
+        }
+        let id = tab.id;
+        let dialog = self.dialog(
+            "Save the changes before closing?",
+            &tab.label.text(),
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Discard", gtk::ResponseType::Reject),
+                ("Save", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.connect_response(move |d, r| {
+            d.close();
+            send!(sender, Msg::CloseResponse(id, r));
+        });
+        dialog.show();
+    }
+
+    fn on_close_response(
+        &mut self,
+        id: usize,
+        response: gtk::ResponseType,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let index = match self.tabs.iter().position(|t| t.id == id) {
+            Some(i) => i,
+            None => return,
+        };
+        match response {
+            gtk::ResponseType::Accept => {
+                if self.save_dirty(Some(id), parent_sender) {
+                    self.remove_tab(index);
+                }
+            }
+            gtk::ResponseType::Reject => {
+                for z in self.tabs[index].stack.iter() {
+                    let _ = self.drafts.remove(z.zettel.borrow().zid());
+                }
+                self.remove_tab(index);
+            }
+            _ => {}
+        }
+    }
+
+    /// Text view of a tab, the events of the user are handled for the note
+    /// on the top of the current tab.
+    fn new_view(sender: relm4::Sender<Msg>) -> TextView {
+        let view = gtk::TextView::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .pixels_inside_wrap(10)
+            .wrap_mode(gtk::WrapMode::Char)
+            .build();
+        view.add_css_class("editor");
+
+        let click = gtk::GestureClick::new();
+        let s = sender.clone();
+        click.connect_released(move |_, _, x, y| send!(s, Msg::Click(x, y)));
+        view.add_controller(&click);
+
+        let drop = gtk::DropTarget::new(
+            gtk::gio::File::static_type(),
+            gdk::DragAction::COPY,
+        );
+        let s = sender.clone();
+        drop.connect_drop(move |_, value, x, y| {
+            let path = match value.get::<gtk::gio::File>() {
+                Ok(file) => file.path().filter(|p| is_image(p)),
+                Err(_) => None,
+            };
+            match path {
+                Some(path) => {
+                    send!(s, Msg::DropFile(path, x, y));
+                    true
+                }
+                None => false,
+            }
+        });
+        view.add_controller(&drop);
+
+        view.connect_paste_clipboard(move |view| {
+            let clipboard = view.clipboard();
+            if !clipboard
+                .formats()
+                .contains_type(gdk::Texture::static_type())
+            {
+                return;
+            }
+            // Images are pasted as attachments instead of text.
+            view.stop_signal_emission_by_name("paste-clipboard");
+            let s = sender.clone();
+            clipboard.read_texture_async(
+                None::<&gtk::gio::Cancellable>,
+                move |texture| {
+                    if let Ok(Some(texture)) = texture {
+                        send!(s, Msg::PasteImage(texture));
+                    }
+                },
+            );
+        });
+
+        let keys = EventControllerKey::new();
+        let buffer_of = view.clone();
+        keys.connect_key_pressed(move |_, key, _, _| {
+            let forward = match key {
+                Key::Tab => true,
+                Key::ISO_Left_Tab => false,
+                _ => return Inhibit(false),
+            };
+            Inhibit(block::move_to_cell(&buffer_of.buffer(), forward))
+        });
+        view.add_controller(&keys);
+        view
+    }
+
+    fn on_buffer_changed(&mut self, sender: relm4::Sender<Msg>) {
+        if let Some(z) = self.top_mut() {
             z.buffer.set_modified(true);
             z.on_buffer_changed();
         }
+        self.update_completion();
+
+        self.changes += 1;
+        let changes = self.changes;
+        let s = sender.clone();
+        gtk::glib::timeout_add_local_once(
+            Duration::from_secs(DRAFT_DELAY),
+            move || send!(s, Msg::Idle(changes)),
+        );
+        if self.autosave.enabled() {
+            gtk::glib::timeout_add_local_once(
+                Duration::from_secs(self.autosave.delay()),
+                move || send!(sender, Msg::Autosave(changes)),
+            );
+        }
+    }
+
+    /// Write the drafts of the changed notes, and remove the ones of the
+    /// notes that are changed back.
+    fn write_drafts(&self, parent_sender: relm4::Sender<super::Msg>) {
+        for z in self.notes() {
+            let r = if z.is_dirty() {
+                self.drafts.write(&z.draft())
+            } else {
+                self.drafts.remove(z.zettel.borrow().zid())
+            };
+            if let Err(e) = r {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowMsg(
+                        MessageType::Error,
+                        format!("Write draft failed: {:?}", e)
+                    )
+                );
+                return;
+            }
+        }
+    }
+
+    /// Save the changed notes of the tab of the id, or of all the tabs if
+    /// `None`. `false` if any of them fails.
+    fn save_dirty(
+        &mut self,
+        tab: Option<usize>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) -> bool {
+        let mut kasten = self.kasten.borrow_mut();
+        let notes = self
+            .tabs
+            .iter_mut()
+            .filter(|t| tab.map_or(true, |id| t.id == id))
+            .flat_map(|t| t.stack.iter_mut());
+        for z in notes.filter(|z| z.is_dirty()) {
+            if let Err(e) = z.write(&mut kasten, &self.drafts) {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowMsg(
+                        MessageType::Error,
+                        format!("Save note failed: {:?}", e)
+                    )
+                );
+                return false;
+            }
+            z.buffer.set_modified(false);
+        }
+        true
+    }
+
+    fn quit(
+        &self,
+        sender: relm4::Sender<Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let titles: Vec<String> = self
+            .notes()
+            .filter(|z| z.is_dirty())
+            .map(|z| z.title.text())
+            .collect();
+        if titles.is_empty() {
+            send!(parent_sender, super::Msg::Exit);
+            return;
+        }
+        let dialog = self.dialog(
+            "Save the changes before quitting?",
+            &titles.join("\n"),
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Discard", gtk::ResponseType::Reject),
+                ("Save", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.connect_response(move |d, r| {
+            d.close();
+            send!(sender, Msg::QuitResponse(r));
+        });
+        dialog.show();
+    }
+
+    fn on_quit_response(
+        &mut self,
+        response: gtk::ResponseType,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        match response {
+            gtk::ResponseType::Accept => {
+                if self.save_dirty(None, parent_sender.clone()) {
+                    send!(parent_sender, super::Msg::Exit);
+                }
+            }
+            gtk::ResponseType::Reject => {
+                for z in self.notes() {
+                    let _ = self.drafts.remove(z.zettel.borrow().zid());
+                }
+                send!(parent_sender, super::Msg::Exit);
+            }
+            _ => {}
+        }
+    }
+
+    fn offer_drafts(
+        &self,
+        sender: relm4::Sender<Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let drafts = match self.drafts.list() {
+            Ok(drafts) => drafts,
+            Err(e) => {
+                send!(
+                    parent_sender,
+                    super::Msg::ShowMsg(
+                        MessageType::Error,
+                        format!("Read drafts failed: {:?}", e)
+                    )
+                );
+                return;
+            }
+        };
+        if drafts.is_empty() {
+            return;
+        }
+        let titles: Vec<&str> = drafts.iter().map(|d| d.title()).collect();
+        let dialog = self.dialog(
+            "Restore the unsaved changes of the last run?",
+            &titles.join("\n"),
+            &[
+                ("Discard", gtk::ResponseType::Reject),
+                ("Restore", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.connect_response(move |d, r| {
+            d.close();
+            send!(sender, Msg::RestoreDrafts(r == gtk::ResponseType::Accept));
+        });
+        dialog.show();
+    }
+
+    /// Save the drafts into their notes, the replaced content is kept in
+    /// the history of the repo. The newest one is opened.
+    fn restore_drafts(
+        &self,
+        restore: bool,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let drafts = self.drafts.list().unwrap_or_default();
+        let mut restored = vec![];
+        for draft in drafts.iter() {
+            let zettel = self.kasten.borrow().get(draft.zid());
+            if let (true, Some(z)) = (restore, zettel) {
+                let r = z.borrow_mut().set_content(draft.content());
+                let r = r.and_then(|_| {
+                    z.borrow_mut().set_title(draft.title());
+                    self.kasten.borrow_mut().save(&z.borrow())
+                });
+                if let Err(e) = r {
+                    send!(
+                        parent_sender,
+                        super::Msg::ShowMsg(
+                            MessageType::Error,
+                            format!("Restore draft failed: {:?}", e)
+                        )
+                    );
+                    continue;
+                }
+                restored.push(z);
+            }
+            let _ = self.drafts.remove(draft.zid());
+        }
+        if let Some(z) = restored.into_iter().next() {
+            send!(parent_sender, super::Msg::ChangeZettel(z));
+        }
+    }
+
+    fn dialog(
+        &self,
+        text: &str,
+        secondary: &str,
+        buttons: &[(&str, gtk::ResponseType)],
+    ) -> gtk::MessageDialog {
+        let window = self
+            .tabs
+            .first()
+            .and_then(|t| t.view.root())
+            .and_then(|r| r.downcast::<gtk::Window>().ok());
+        let dialog = gtk::MessageDialog::builder()
+            .modal(true)
+            .message_type(MessageType::Question)
+            .text(text)
+            .secondary_text(secondary)
+            .build();
+        dialog.set_transient_for(window.as_ref());
+        for (label, response) in buttons {
+            dialog.add_button(label, *response);
+        }
+        dialog
     }
 
     fn on_cursor_notify(&mut self) {
-        if let Some(z) = self.stack.last_mut() {
+        if let Some(z) = self.top_mut() {
             z.on_cursor_notify();
         }
+        self.update_completion();
+    }
+
+    fn update_completion(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.current) {
+            tab.completion.update(&tab.view, &self.kasten.borrow());
+        }
+    }
+
+    fn dismiss_completion(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.current) {
+            tab.completion.dismiss(&tab.view);
+        }
+    }
+
+    fn complete(
+        &mut self,
+        index: i32,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let tab = match self.tabs.get_mut(self.current) {
+            Some(tab) => tab,
+            None => return,
+        };
+        let r = tab.completion.complete(
+            index,
+            &tab.view.buffer(),
+            &mut self.kasten.borrow_mut(),
+        );
+        if let Err(e) = r {
+            send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Error,
+                    format!("Create note failed: {:?}", e)
+                )
+            );
+        }
+    }
+
+    fn on_click(&self, x: f64, y: f64) {
+        if let Some(z) = self.top() {
+            z.on_click(x, y);
+        }
+    }
+
+    /// Copy the dropped file into the repo and link it at the drop point.
+    fn drop_file(
+        &self,
+        path: &Path,
+        x: f64,
+        y: f64,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let z = match self.top() {
+            Some(z) => z,
+            None => return,
+        };
+        let alt = path.file_stem().unwrap_or_default().to_string_lossy();
+        match self.kasten.borrow().attach_file(path) {
+            Ok(link) => z.insert_image(&alt, &link, Some((x, y))),
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Error,
+                    format!("Attach file failed: {:?}", e)
+                )
+            ),
+        }
+    }
+
+    /// Save the pasted image into the repo and link it at the cursor.
+    fn paste_image(
+        &self,
+        texture: &gdk::Texture,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let z = match self.top() {
+            Some(z) => z,
+            None => return,
+        };
+        let name = Local::now().format("%Y%m%d%H%M%S").to_string();
+        let kasten = self.kasten.borrow();
+        let r = kasten.new_attachment(&format!("{}.png", name)).and_then(
+            |(link, path)| {
+                texture.save_to_png(&path).map_err(|e| {
+                    anyhow::anyhow!("can't write {:?}: {}", path, e)
+                })?;
+                kasten.commit_attachment(&path)?;
+                Ok(link)
+            },
+        );
+        match r {
+            Ok(link) => z.insert_image(&name, &link, None),
+            Err(e) => send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Error,
+                    format!("Paste image failed: {:?}", e)
+                )
+            ),
+        }
     }
 
     fn insert_zettel_at_cursor(&self, zettel: &Zettel) {
-        if let Some(z) = self.stack.last() {
+        if let Some(z) = self.top() {
             z.insert_zettel_at_cursor(zettel);
         }
     }
 
+    fn apply_theme(&mut self, theme: &Theme, dark: bool) {
+        self.style.apply(theme, dark);
+        self.css.load_from_data(self.style.css().as_bytes());
+    }
+
     fn save(&mut self, parent_sender: relm4::Sender<super::Msg>) -> bool {
-        match self.stack.last_mut() {
-            Some(z) => z.save(&mut self.kasten.borrow_mut(), parent_sender),
+        let top = self
+            .tabs
+            .get_mut(self.current)
+            .and_then(|t| t.stack.last_mut());
+        match top {
+            Some(z) => {
+                if z.buffer.is_modified() {
+                    z.format_tables();
+                }
+                z.save(
+                    &mut self.kasten.borrow_mut(),
+                    &self.drafts,
+                    parent_sender,
+                )
+            }
             None => false,
         }
     }
@@ -247,18 +1214,28 @@ impl Model {
 
 impl ComponentUpdate<super::AppModel> for Model {
     fn init_model(parent_model: &super::AppModel) -> Self {
-        let view = gtk::TextView::builder()
-            .vexpand(true)
-            .hexpand(true)
-            .pixels_inside_wrap(10)
-            .wrap_mode(gtk::WrapMode::Char)
-            .build();
+        let style = style::Style::new(
+            parent_model.config.borrow().theme(),
+            parent_model.dark,
+        );
+        let css = gtk::CssProvider::new();
+        css.load_from_data(style.css().as_bytes());
+
+        let autosave = parent_model.config.borrow().autosave().clone();
+        let drafts =
+            Drafts::new(&ConfigLoader::new().dir().join(autosave.drafts()));
 
         Model {
             kasten: parent_model.kasten.clone(),
-            stack: vec![],
+            tabs: vec![],
+            current: 0,
+            next_id: 0,
             editing_title: false,
-            view,
+            style,
+            css,
+            autosave,
+            drafts,
+            changes: 0,
         }
     }
 
@@ -270,8 +1247,15 @@ impl ComponentUpdate<super::AppModel> for Model {
         parent_sender: relm4::Sender<super::Msg>,
     ) {
         match msg {
-            Msg::Changed => self.on_buffer_changed(),
+            Msg::Changed => self.on_buffer_changed(sender),
             Msg::Cursor => self.on_cursor_notify(),
+            Msg::Click(x, y) => self.on_click(x, y),
+            Msg::DropFile(path, x, y) => {
+                self.drop_file(&path, x, y, parent_sender)
+            }
+            Msg::PasteImage(texture) => {
+                self.paste_image(&texture, parent_sender)
+            }
             Msg::Open(z) => {
                 self.editing_title = false;
                 self.open_zettel(z, sender)
@@ -285,7 +1269,12 @@ impl ComponentUpdate<super::AppModel> for Model {
                 self.insert_zettel_at_cursor(&z.borrow());
             }
             Msg::Save => {
-                if self.save(parent_sender) || self.stack.len() > 1 {
+                self.save(parent_sender);
+            }
+            Msg::Done => {
+                let modified =
+                    self.top().map_or(false, |z| z.buffer.is_modified());
+                if !modified || self.save(parent_sender) {
                     self.pop_stack_and_insert(sender);
                 }
             }
@@ -293,6 +1282,35 @@ impl ComponentUpdate<super::AppModel> for Model {
             Msg::DoneEditTitle => {
                 self.editing_title = false;
             }
+            Msg::Theme(theme, dark) => self.apply_theme(&theme, dark),
+            Msg::Idle(changes) => {
+                if changes == self.changes {
+                    self.write_drafts(parent_sender);
+                }
+            }
+            Msg::Autosave(changes) => {
+                if changes == self.changes {
+                    self.save_dirty(None, parent_sender);
+                }
+            }
+            Msg::Quit => self.quit(sender, parent_sender),
+            Msg::QuitResponse(r) => self.on_quit_response(r, parent_sender),
+            Msg::OfferDrafts => self.offer_drafts(sender, parent_sender),
+            Msg::RestoreDrafts(restore) => {
+                self.restore_drafts(restore, parent_sender)
+            }
+            Msg::SwitchTab(index) => {
+                self.editing_title = false;
+                self.switch_tab(index as usize, parent_sender)
+            }
+            Msg::NextTab => self.cycle_tab(true, parent_sender),
+            Msg::PrevTab => self.cycle_tab(false, parent_sender),
+            Msg::CloseTab(id) => self.close_tab(id, sender),
+            Msg::CloseResponse(id, r) => {
+                self.on_close_response(id, r, parent_sender)
+            }
+            Msg::Complete(index) => self.complete(index, parent_sender),
+            Msg::DismissCompletion => self.dismiss_completion(),
         }
     }
 }
@@ -323,45 +1341,86 @@ impl Widgets<Model, super::AppModel> for Editor {
             .placeholder_text("Title")
             .build();
 
+        let aliases_in = gtk::Entry::builder()
+            .hexpand(true)
+            .vexpand(false)
+            .placeholder_text("Aliases, separated by commas")
+            .build();
+        let title_edit = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(6)
+            .hexpand(true)
+            .vexpand(false)
+            .build();
+        title_edit.append(&entry);
+        title_edit.append(&aliases_in);
+
+        // Moving between the entries keeps the focus in the box.
         let focus_ctrl = EventControllerFocus::builder().build();
         let s = sender.clone();
         focus_ctrl.connect_leave(move |_| send!(s, Msg::DoneEditTitle));
-        entry.add_controller(&focus_ctrl);
+        title_edit.add_controller(&focus_ctrl);
 
-        let window = ScrolledWindow::builder()
+        if let Some(display) = gdk::Display::default() {
+            gtk::StyleContext::add_provider_for_display(
+                &display,
+                &model.css,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        }
+
+        let notebook = gtk::Notebook::builder()
             .hexpand(true)
             .vexpand(true)
-            .margin_start(10)
-            .margin_end(10)
-            .margin_top(10)
-            .margin_bottom(10)
-            .child(&model.view)
+            .scrollable(true)
             .build();
+        let s = sender.clone();
+        let switch_handler =
+            notebook.connect_switch_page(move |_, _, index| {
+                send!(s, Msg::SwitchTab(index))
+            });
 
         let title_show = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
             .hexpand(true)
             .vexpand(false)
             .build();
+        let aliases_label = gtk::Label::builder()
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        aliases_label.add_css_class("dim-label");
         let edit_btn = gtk::Button::builder().label("Edit").build();
         let s = sender.clone();
         edit_btn.connect_clicked(move |_| send!(s, Msg::EditTitle));
         title_show.append(&label);
+        title_show.append(&aliases_label);
         title_show.append(&edit_btn);
 
         let action_bar = ActionBar::builder().build();
         let save_btn = gtk::Button::builder().label("Save").build();
-        save_btn.connect_clicked(move |_| send!(sender, Msg::Save));
+        let s = sender.clone();
+        save_btn.connect_clicked(move |_| send!(s, Msg::Save));
         action_bar.pack_end(&save_btn);
+        let done_btn = gtk::Button::builder()
+            .label("Done")
+            .tooltip_text("Save and insert the link into the previous note")
+            .build();
+        done_btn.connect_clicked(move |_| send!(sender, Msg::Done));
+        action_bar.pack_end(&done_btn);
 
         Editor {
             layout: box_,
             title_in: entry,
+            aliases_in,
+            title_edit,
             title_label: label,
+            aliases_label,
             title_show,
-            main_win: window,
+            notebook,
+            switch_handler,
             action_bar,
             save_btn,
+            done_btn,
         }
     }
 
@@ -370,23 +1429,27 @@ impl Widgets<Model, super::AppModel> for Editor {
             self.layout.remove(&c);
         }
         if model.editing_title {
-            self.layout.append(&self.title_in);
+            self.layout.append(&self.title_edit);
         } else {
             self.layout.append(&self.title_show);
         }
         self.layout.append(&self.action_bar);
-        self.layout.append(&self.main_win);
+        self.layout.append(&self.notebook);
 
-        if let Some(ez) = model.stack.last() {
-            model.view.set_buffer(Some(&ez.buffer));
+        self.sync_tabs(model);
+        let tab = match model.tabs.get(model.current) {
+            Some(tab) => tab,
+            None => return,
+        };
+        if let Some(ez) = tab.stack.last() {
+            tab.view.set_buffer(Some(&ez.buffer));
             self.title_in.set_buffer(&ez.title);
+            self.aliases_in.set_buffer(&ez.aliases);
 
-            if ez.buffer.is_modified() || model.stack.len() > 1 {
-                self.save_btn.set_sensitive(true);
-            } else {
-                self.save_btn.set_sensitive(false);
-            }
+            self.save_btn.set_sensitive(ez.buffer.is_modified());
+            self.done_btn.set_visible(tab.stack.len() > 1);
             self.title_label.set_text(&ez.title.text());
+            self.aliases_label.set_text(&ez.aliases.text());
 
             if ez.title.text() == "" {
                 self.title_in.set_placeholder_text(Some("Title"))
@@ -394,6 +1457,54 @@ impl Widgets<Model, super::AppModel> for Editor {
                 self.title_in.set_placeholder_text(None)
             }
         }
-        model.view.grab_focus();
+        tab.view.grab_focus();
+    }
+}
+
+impl Editor {
+    /// Pages of the notebook in the order of the tabs, the switches made
+    /// here are not the user's.
+    fn sync_tabs(&self, model: &Model) {
+        self.notebook.block_signal(&self.switch_handler);
+        // Pages of the closed tabs first, the others are moved then.
+        for n in (0..self.notebook.n_pages()).rev() {
+            let page = match self.notebook.nth_page(Some(n)) {
+                Some(page) => page,
+                None => continue,
+            };
+            if !model
+                .tabs
+                .iter()
+                .any(|t| t.page.upcast_ref::<gtk::Widget>() == &page)
+            {
+                self.notebook.remove_page(Some(n));
+            }
+        }
+        for (i, tab) in model.tabs.iter().enumerate() {
+            let i = i as u32;
+            match self.notebook.page_num(&tab.page) {
+                Some(n) if n == i => {}
+                Some(_) => self.notebook.reorder_child(&tab.page, Some(i)),
+                None => {
+                    self.notebook.insert_page(
+                        &tab.page,
+                        Some(&tab.header),
+                        Some(i),
+                    );
+                }
+            }
+            if let Some(ez) = tab.stack.last() {
+                let title = ez.title.text();
+                if ez.buffer.is_modified() {
+                    tab.label.set_text(&format!("\u{2022} {}", title));
+                } else {
+                    tab.label.set_text(&title);
+                }
+            }
+        }
+        if !model.tabs.is_empty() {
+            self.notebook.set_current_page(Some(model.current as u32));
+        }
+        self.notebook.unblock_signal(&self.switch_handler);
     }
 }
@@ -1,18 +1,22 @@
 use gtk::pango::FontDescription;
+use gtk::prelude::*;
 use gtk::TextTag;
 use gtk::TextTagTable;
+use rustybrain_core::config::Theme;
 
+/// Tags of the editor styled by the theme, the table is shared by the
+/// buffers of all notes so a changed theme applies to them at once.
 pub struct Style {
-    font: String,
-    font_size: i32,
+    theme: Theme,
+    dark: bool,
     table: TextTagTable,
 }
 
 impl Style {
-    pub fn new() -> Self {
-        let mut style = Style {
-            font: "Victor Mono".to_string(),
-            font_size: 14,
+    pub fn new(theme: &Theme, dark: bool) -> Self {
+        let style = Style {
+            theme: theme.clone(),
+            dark,
             table: TextTagTable::new(),
         };
         style.fill();
@@ -23,84 +27,252 @@ impl Style {
         self.table.clone()
     }
 
-    fn fill(&mut self) {
+    /// Restyle the tags by the theme.
+    pub fn apply(&mut self, theme: &Theme, dark: bool) {
+        self.theme = theme.clone();
+        self.dark = dark;
+        self.fill();
+    }
+
+    /// CSS of the text view for the colors of the theme.
+    pub fn css(&self) -> String {
+        format!(
+            "textview.editor, textview.editor text {{ \
+             background-color: {}; color: {}; }}",
+            self.color("background"),
+            self.color("foreground")
+        )
+    }
+
+    fn fill(&self) {
         self.fill_paragraph();
         self.fill_headline();
         self.fill_link();
         self.fill_code();
         self.fill_code_block();
+        self.fill_code_highlight();
         self.fill_bold();
         self.fill_italic();
         self.fill_strikethrough();
+        self.fill_table();
+        self.fill_list();
+        self.fill_blockquote();
+        self.fill_thematic_break();
         self.fill_hidden();
     }
 
-    fn fill_paragraph(&mut self) {
+    /// Tag of the name, it is created the first time. Tags created later
+    /// take priority.
+    fn tag(&self, name: &str) -> TextTag {
+        match self.table.lookup(name) {
+            Some(tag) => tag,
+            None => {
+                let tag = TextTag::new(Some(name));
+                self.table.add(&tag);
+                tag
+            }
+        }
+    }
+
+    /// Color of the theme, or the default one of the light or dark mode.
+    fn color(&self, name: &str) -> String {
+        match self.theme.color(name, self.dark) {
+            Some(color) => color.to_string(),
+            None => default_color(name, self.dark).to_string(),
+        }
+    }
+
+    fn fill_paragraph(&self) {
         let fd = self.font_desc();
-        let tag = TextTag::builder().name("p").font_desc(&fd).build();
-        self.table.add(&tag);
+        self.tag("p").set_property("font-desc", &fd);
     }
 
     fn font_desc(&self) -> FontDescription {
-        let font = format!("{} {}", self.font, self.font_size);
+        self.font_desc_of_size(self.theme.font_size())
+    }
+
+    fn font_desc_of_size(&self, size: i32) -> FontDescription {
+        let font = format!("{} {}", self.theme.font(), size);
         FontDescription::from_string(&font)
     }
 
-    fn fill_headline(&mut self) {
-        let font_sizes: Vec<i32> = vec![16, 14, 10, 8, 6, 4, 2];
-        let mut hn = 1;
-        for sz in font_sizes {
-            let font = format!("{} {}", self.font, self.font_size + sz);
-            let mut fd = FontDescription::from_string(&font);
+    fn fill_headline(&self) {
+        for hn in 1..=7 {
+            let mut fd = self.font_desc_of_size(self.theme.heading_size(hn));
             fd.set_style(gtk::pango::Style::Oblique);
-            let name = format!("h{}", hn);
-            let tag = TextTag::builder().name(&name).font_desc(&fd).build();
-            self.table.add(&tag);
-            hn += 1;
+            self.tag(&format!("h{}", hn)).set_property("font-desc", &fd);
         }
     }
 
-    fn fill_link(&mut self) {
+    fn fill_link(&self) {
+        let fd = self.font_desc();
+        let tag = self.tag("link");
+        tag.set_property("foreground", self.color("link"));
+        tag.set_property("underline", gtk::pango::Underline::Single);
+        tag.set_property("font-desc", &fd);
+    }
+
+    fn fill_code(&self) {
         let fd = self.font_desc();
-        let tag = TextTag::builder()
-            .name("link")
-            .foreground("blue")
-            .underline(gtk::pango::Underline::SingleLine)
-            .font_desc(&fd)
-            .build();
-        self.table.add(&tag);
+        let tag = self.tag("code");
+        tag.set_property("background", self.color("code-background"));
+        tag.set_property("foreground", self.color("code"));
+        tag.set_property("font-desc", &fd);
+    }
+
+    fn fill_code_block(&self) {
+        let fd = self.font_desc();
+        let tag = self.tag("code-block");
+        tag.set_property(
+            "paragraph-background",
+            self.color("code-block-background"),
+        );
+        tag.set_property("font-desc", &fd);
+    }
+
+    fn fill_code_highlight(&self) {
+        // Tags of `rustybrain_core::highlight::HIGHLIGHT_NAMES`.
+        let names = [
+            "attribute",
+            "comment",
+            "constant",
+            "constant-builtin",
+            "function",
+            "function-macro",
+            "keyword",
+            "number",
+            "operator",
+            "property",
+            "string",
+            "type",
+            "type-builtin",
+            "variable-builtin",
+        ];
+        for name in names {
+            let name = format!("code-{}", name);
+            let tag = self.tag(&name);
+            tag.set_property("foreground", self.color(&name));
+            if name == "code-comment" {
+                tag.set_property("style", gtk::pango::Style::Italic);
+            }
+        }
+    }
+
+    fn fill_bold(&self) {
+        let mut fd = self.font_desc();
+        fd.set_weight(gtk::pango::Weight::Bold);
+        self.tag("bold").set_property("font-desc", &fd);
     }
 
-    fn fill_code(&mut self) {}
+    fn fill_italic(&self) {
+        let mut fd = self.font_desc();
+        fd.set_style(gtk::pango::Style::Italic);
+        self.tag("italic").set_property("font-desc", &fd);
+    }
 
-    fn fill_code_block(&mut self) {
+    fn fill_strikethrough(&self) {
+        self.tag("strikethrough")
+            .set_property("strikethrough", true);
+    }
+
+    fn fill_table(&self) {
+        // Wrapped rows would break the grid.
         let fd = self.font_desc();
-        let tag = TextTag::builder()
-            .name("code-block")
-            .paragraph_background("#E7ECF0")
-            .font_desc(&fd)
-            .build();
-        self.table.add(&tag);
+        let tag = self.tag("table");
+        tag.set_property("wrap-mode", gtk::WrapMode::None);
+        tag.set_property("font-desc", &fd);
+
+        let mut fd = self.font_desc();
+        fd.set_weight(gtk::pango::Weight::Bold);
+        self.tag("table-header").set_property("font-desc", &fd);
+
+        self.tag("table-delimiter")
+            .set_property("foreground", self.color("table-delimiter"));
+        self.tag("table-pipe")
+            .set_property("foreground", self.color("table-pipe"));
     }
 
-    fn fill_bold(&mut self) {
+    fn fill_list(&self) {
+        // Deeper levels are added later to take priority over the outer ones.
+        for depth in 1..=6 {
+            self.tag(&format!("list-{}", depth))
+                .set_property("left-margin", 16 * depth);
+        }
+
         let mut fd = self.font_desc();
         fd.set_weight(gtk::pango::Weight::Bold);
-        let tag = TextTag::builder().name("bold").font_desc(&fd).build();
-        self.table.add(&tag);
+        let marker = self.tag("list-marker");
+        marker.set_property("foreground", self.color("list-marker"));
+        marker.set_property("font-desc", &fd);
+
+        let checkbox = self.tag("checkbox");
+        checkbox.set_property("foreground", self.color("checkbox"));
+        checkbox.set_property("font-desc", &fd);
+
+        let done = self.tag("task-done");
+        done.set_property("foreground", self.color("task-done"));
+        done.set_property("strikethrough", true);
     }
 
-    fn fill_italic(&mut self) {
+    fn fill_blockquote(&self) {
         let mut fd = self.font_desc();
         fd.set_style(gtk::pango::Style::Italic);
-        let tag = TextTag::builder().name("italic").font_desc(&fd).build();
-        self.table.add(&tag);
+        let tag = self.tag("blockquote");
+        tag.set_property("foreground", self.color("blockquote"));
+        tag.set_property("left-margin", 16);
+        tag.set_property("font-desc", &fd);
+
+        let bar = self.tag("quote-bar");
+        bar.set_property("foreground", self.color("quote-bar"));
+        bar.set_property("background", self.color("quote-bar"));
     }
 
-    fn fill_strikethrough(&mut self) {}
+    fn fill_thematic_break(&self) {
+        let tag = self.tag("thematic-break");
+        tag.set_property("foreground", self.color("thematic-break"));
+        tag.set_property("strikethrough", true);
+        tag.set_property("letter-spacing", 12 * gtk::pango::SCALE);
+        tag.set_property("justification", gtk::Justification::Center);
+    }
 
-    fn fill_hidden(&mut self) {
-        let tag = TextTag::builder().name("hidden").invisible(true).build();
-        self.table.add(&tag);
+    fn fill_hidden(&self) {
+        self.tag("hidden").set_property("invisible", true);
+    }
+}
+
+/// Colors of the tags that the theme doesn't set, light then dark.
+fn default_color(name: &str, dark: bool) -> &'static str {
+    let (light, night) = match name {
+        "foreground" => ("#333333", "#adbac7"),
+        "background" => ("#fefefe", "#22272e"),
+        "link" => ("blue", "#6cb6ff"),
+        "code" => ("#d33682", "#f47067"),
+        "code-background" => ("#E7ECF0", "#2d333b"),
+        "code-block-background" => ("#E7ECF0", "#2d333b"),
+        "code-attribute" => ("#b58900", "#daaa3f"),
+        "code-comment" => ("#93a1a1", "#768390"),
+        "code-constant" | "code-constant-builtin" => ("#cb4b16", "#f69d50"),
+        "code-function" => ("#268bd2", "#dcbdfb"),
+        "code-function-macro" => ("#6c71c4", "#b083f0"),
+        "code-keyword" => ("#859900", "#f47067"),
+        "code-number" => ("#d33682", "#6cb6ff"),
+        "code-operator" => ("#657b83", "#adbac7"),
+        "code-property" => ("#268bd2", "#6cb6ff"),
+        "code-string" => ("#2aa198", "#96d0ff"),
+        "code-type" | "code-type-builtin" => ("#b58900", "#f69d50"),
+        "code-variable-builtin" => ("#dc322f", "#6cb6ff"),
+        "list-marker" => ("#6c71c4", "#b083f0"),
+        "checkbox" => ("#268bd2", "#6cb6ff"),
+        "task-done" | "table-pipe" => ("#93a1a1", "#768390"),
+        "blockquote" => ("#586e75", "#9fb0bd"),
+        "quote-bar" | "thematic-break" | "table-delimiter" => {
+            ("#b0bec5", "#545d68")
+        }
+        _ => ("#333333", "#adbac7"),
+    };
+    if dark {
+        night
+    } else {
+        light
     }
 }
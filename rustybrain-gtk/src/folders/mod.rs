@@ -0,0 +1,377 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use gtk::glib::Type;
+use gtk::prelude::*;
+use gtk::{MessageType, ResponseType, TreeStore};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::kasten::{Kasten, SavedSearch, SortBy};
+use rustybrain_core::query;
+
+use crate::AppModel;
+
+/// Saved search and the notes it finds.
+struct Folder {
+    search: SavedSearch,
+    /// Titles and zids of the notes.
+    notes: Vec<(String, String)>,
+    error: Option<String>,
+}
+
+pub struct Model {
+    kasten: Rc<RefCell<Kasten>>,
+    folders: Vec<Folder>,
+    selected: Option<usize>,
+    version: usize,
+}
+
+pub enum Msg {
+    /// Run the saved searches again.
+    Refresh,
+    /// Row of a folder, or of a note in it if the zid is not empty.
+    Selected(usize, String),
+    Add(String, String),
+    Remove,
+    Sort(u32),
+}
+
+pub struct Folders {
+    layout: gtk::Box,
+    store: TreeStore,
+    view: gtk::TreeView,
+    sort: gtk::DropDown,
+    /// Names of the expanded folders, kept when the folders are refilled.
+    expanded: Rc<RefCell<HashSet<String>>>,
+    version: usize,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Folders;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        let mut model = Model {
+            kasten: parent_model.kasten.clone(),
+            folders: vec![],
+            selected: None,
+            version: 0,
+        };
+        model.reload();
+        model
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        let r = match msg {
+            Msg::Refresh => {
+                self.reload();
+                Ok(())
+            }
+            Msg::Selected(folder, zid) => {
+                self.selected = Some(folder);
+                if !zid.is_empty() {
+                    if let Some(z) = self.kasten.borrow().get(&zid) {
+                        send!(parent_sender, super::Msg::ChangeZettel(z));
+                    }
+                }
+                Ok(())
+            }
+            Msg::Add(name, query) => self.add(name.trim(), query.trim()),
+            Msg::Remove => self.remove(),
+            Msg::Sort(index) => self.sort(index),
+        };
+        if let Err(e) = r {
+            send!(
+                parent_sender,
+                super::Msg::ShowMsg(
+                    MessageType::Error,
+                    format!("Update saved searches failed: {}!", e)
+                )
+            );
+        }
+    }
+}
+
+impl Model {
+    fn reload(&mut self) {
+        let kasten = self.kasten.borrow();
+        self.folders = match kasten.saved_searches() {
+            Ok(searches) => searches
+                .into_iter()
+                .map(|search| match kasten.run_saved_search(&search) {
+                    Ok(zettels) => Folder {
+                        search,
+                        notes: zettels
+                            .iter()
+                            .map(|z| {
+                                let z = z.borrow();
+                                (z.title().to_string(), z.zid().to_string())
+                            })
+                            .collect(),
+                        error: None,
+                    },
+                    Err(e) => Folder {
+                        search,
+                        notes: vec![],
+                        error: Some(e.to_string()),
+                    },
+                })
+                .collect(),
+            Err(e) => vec![Folder {
+                search: SavedSearch::new("searches.toml", ""),
+                notes: vec![],
+                error: Some(e.to_string()),
+            }],
+        };
+        if self.selected.map_or(false, |i| i >= self.folders.len()) {
+            self.selected = None;
+        }
+        self.version += 1;
+    }
+
+    /// Save a search, the one of the same name is replaced.
+    fn add(&mut self, name: &str, query: &str) -> Result<(), anyhow::Error> {
+        if name.is_empty() {
+            anyhow::bail!("the name is empty");
+        }
+        if query::parse(query)?.is_none() {
+            anyhow::bail!("the query is empty");
+        }
+        let mut searches = self.kasten.borrow().saved_searches()?;
+        let search = SavedSearch::new(name, query);
+        match searches.iter_mut().find(|s| s.name() == name) {
+            Some(s) => *s = search,
+            None => searches.push(search),
+        }
+        self.kasten.borrow().set_saved_searches(&searches)?;
+        self.reload();
+        Ok(())
+    }
+
+    fn remove(&mut self) -> Result<(), anyhow::Error> {
+        let index = match self.selected.take() {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let mut searches = self.kasten.borrow().saved_searches()?;
+        if index < searches.len() {
+            searches.remove(index);
+            self.kasten.borrow().set_saved_searches(&searches)?;
+        }
+        self.reload();
+        Ok(())
+    }
+
+    fn sort(&mut self, index: u32) -> Result<(), anyhow::Error> {
+        let sort = match SortBy::ALL.get(index as usize) {
+            Some(sort) => *sort,
+            None => return Ok(()),
+        };
+        let folder = match self.selected.and_then(|i| self.folders.get(i)) {
+            Some(folder) if folder.search.sort() != sort => folder,
+            _ => return Ok(()),
+        };
+        let mut searches = self.kasten.borrow().saved_searches()?;
+        let name = folder.search.name();
+        if let Some(s) = searches.iter_mut().find(|s| s.name() == name) {
+            s.set_sort(sort);
+            self.kasten.borrow().set_saved_searches(&searches)?;
+        }
+        self.reload();
+        Ok(())
+    }
+}
+
+impl Widgets<Model, AppModel> for Folders {
+    type Root = gtk::Box;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        // Label, zid of a note, index and name of the folder of the row.
+        let store = TreeStore::new(&[
+            Type::STRING,
+            Type::STRING,
+            Type::U32,
+            Type::STRING,
+        ]);
+        let view = gtk::TreeView::builder()
+            .model(&store)
+            .headers_visible(false)
+            .vexpand(true)
+            .build();
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", 0);
+        view.append_column(&column);
+
+        let s = sender.clone();
+        view.selection().connect_changed(move |selection| {
+            if let Some((model, iter)) = selection.selected() {
+                let zid = model.get::<String>(&iter, 1);
+                let folder = model.get::<u32>(&iter, 2);
+                send!(s, Msg::Selected(folder as usize, zid));
+            }
+        });
+        let expanded = Rc::new(RefCell::new(HashSet::new()));
+        let e = expanded.clone();
+        view.connect_row_expanded(move |view, iter, _| {
+            if let Some(model) = view.model() {
+                e.borrow_mut().insert(model.get::<String>(iter, 3));
+            }
+        });
+        let e = expanded.clone();
+        view.connect_row_collapsed(move |view, iter, _| {
+            if let Some(model) = view.model() {
+                e.borrow_mut().remove(&model.get::<String>(iter, 3));
+            }
+        });
+
+        let actions = gtk::ActionBar::new();
+        let add = gtk::Button::builder()
+            .icon_name("list-add-symbolic")
+            .tooltip_text("Save a search")
+            .build();
+        let s = sender.clone();
+        add.connect_clicked(move |button| {
+            let window =
+                button.root().and_then(|r| r.downcast::<gtk::Window>().ok());
+            add_dialog(window.as_ref(), s.clone()).show();
+        });
+        actions.pack_start(&add);
+        let remove = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text("Remove the selected search")
+            .build();
+        let s = sender.clone();
+        remove.connect_clicked(move |_| send!(s, Msg::Remove));
+        actions.pack_start(&remove);
+        let names: Vec<&str> = SortBy::ALL.iter().map(|s| s.name()).collect();
+        let sort = gtk::DropDown::from_strings(&names);
+        sort.set_tooltip_text(Some("Order of the notes in the search"));
+        sort.connect_selected_notify(move |d| {
+            send!(sender, Msg::Sort(d.selected()))
+        });
+        actions.pack_end(&sort);
+
+        let window = gtk::ScrolledWindow::builder()
+            .child(&view)
+            .vexpand(true)
+            .build();
+        let layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        layout.append(&window);
+        layout.append(&actions);
+        Folders {
+            layout,
+            store,
+            view,
+            sort,
+            expanded,
+            version: 0,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.layout.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        if let Some(folder) = model.selected.and_then(|i| model.folders.get(i))
+        {
+            let sort = folder.search.sort();
+            let index = SortBy::ALL.iter().position(|s| *s == sort);
+            let index = index.unwrap_or_default() as u32;
+            if self.sort.selected() != index {
+                self.sort.set_selected(index);
+            }
+        }
+        if self.version == model.version {
+            return;
+        }
+        self.version = model.version;
+        let expanded = self.expanded.borrow().clone();
+        self.store.clear();
+        for (i, folder) in model.folders.iter().enumerate() {
+            let name = folder.search.name();
+            let label = format!("{} ({})", name, folder.notes.len());
+            let index = i as u32;
+            let iter = self.store.insert_with_values(
+                None,
+                None,
+                &[(0, &label), (1, &""), (2, &index), (3, &name)],
+            );
+            if let Some(e) = folder.error.as_ref() {
+                let label = format!("\u{26a0} {}", e);
+                self.store.insert_with_values(
+                    Some(&iter),
+                    None,
+                    &[(0, &label), (1, &""), (2, &index), (3, &name)],
+                );
+            }
+            for (title, zid) in folder.notes.iter() {
+                self.store.insert_with_values(
+                    Some(&iter),
+                    None,
+                    &[(0, title), (1, zid), (2, &index), (3, &name)],
+                );
+            }
+            if expanded.contains(name) {
+                let path = self.store.path(&iter);
+                self.view.expand_row(&path, false);
+            }
+        }
+    }
+}
+
+/// Dialog to save a search under a name.
+fn add_dialog(
+    window: Option<&gtk::Window>,
+    sender: relm4::Sender<Msg>,
+) -> gtk::Dialog {
+    let dialog = gtk::Dialog::builder()
+        .modal(true)
+        .title("Save search")
+        .build();
+    dialog.set_transient_for(window);
+    dialog.add_button("Cancel", ResponseType::Cancel);
+    dialog.add_button("Save", ResponseType::Accept);
+    dialog.set_default_response(ResponseType::Accept);
+    let name = gtk::Entry::builder()
+        .placeholder_text("Name")
+        .activates_default(true)
+        .build();
+    let query = gtk::Entry::builder()
+        .placeholder_text("Query, like tag:rust created:>=2023")
+        .activates_default(true)
+        .build();
+    let content = dialog.content_area();
+    content.set_spacing(6);
+    content.append(&name);
+    content.append(&query);
+    dialog.connect_response(move |d, response| {
+        if response == ResponseType::Accept {
+            send!(
+                sender,
+                Msg::Add(name.text().to_string(), query.text().to_string())
+            );
+        }
+        d.close();
+    });
+    dialog
+}
@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use chrono::{Datelike, Local, NaiveDate};
+use gtk::prelude::*;
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::kasten::Kasten;
+
+use crate::AppModel;
+
+pub struct Model {
+    kasten: Rc<RefCell<Kasten>>,
+    /// Year and month shown by the calendar.
+    month: (i32, u32),
+    days: Vec<u32>,
+    opened: usize,
+}
+
+pub enum Msg {
+    /// The calendar is moved to another month by its arrows.
+    MonthChanged(i32, u32),
+    /// A day is clicked, which may be in the next or previous month.
+    DaySelected(NaiveDate),
+}
+
+pub struct Journal {
+    button: gtk::MenuButton,
+    popover: gtk::Popover,
+    calendar: gtk::Calendar,
+    opened: usize,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Journal;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        let today = Local::now().date_naive();
+        let days = parent_model
+            .kasten
+            .borrow()
+            .journal_days(today.year(), today.month());
+        Model {
+            kasten: parent_model.kasten.clone(),
+            month: (today.year(), today.month()),
+            days,
+            opened: 0,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        match msg {
+            Msg::MonthChanged(year, month) => self.refresh(year, month),
+            Msg::DaySelected(date) => {
+                if (date.year(), date.month()) != self.month {
+                    self.refresh(date.year(), date.month());
+                }
+                self.opened += 1;
+                send!(parent_sender, super::Msg::OpenJournal(Some(date)));
+            }
+        }
+    }
+}
+
+impl Model {
+    fn refresh(&mut self, year: i32, month: u32) {
+        self.month = (year, month);
+        self.days = self.kasten.borrow().journal_days(year, month);
+    }
+}
+
+impl Widgets<Model, AppModel> for Journal {
+    type Root = gtk::MenuButton;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let calendar = gtk::Calendar::new();
+        let popover = gtk::Popover::builder().child(&calendar).build();
+        let button = gtk::MenuButton::builder()
+            .icon_name("x-office-calendar-symbolic")
+            .tooltip_text("Journal")
+            .popover(&popover)
+            .build();
+
+        // The arrows move the selection without `day-selected`, so it is
+        // only sent for the days that are clicked.
+        let s = sender.clone();
+        calendar.connect_day_selected(move |c| {
+            if let Some(date) = Self::date(c) {
+                send!(s, Msg::DaySelected(date));
+            }
+        });
+
+        let s = sender.clone();
+        calendar.connect_next_month(move |c| Self::month_changed(c, &s));
+        let s = sender.clone();
+        calendar.connect_prev_month(move |c| Self::month_changed(c, &s));
+        let s = sender.clone();
+        calendar.connect_next_year(move |c| Self::month_changed(c, &s));
+        let s = sender.clone();
+        calendar.connect_prev_year(move |c| Self::month_changed(c, &s));
+
+        let c = calendar.clone();
+        popover.connect_show(move |_| Self::month_changed(&c, &sender));
+
+        Journal {
+            button,
+            popover,
+            calendar,
+            opened: 0,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.button.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        if self.opened != model.opened {
+            self.opened = model.opened;
+            self.popover.popdown();
+        }
+        self.calendar.clear_marks();
+        for day in model.days.iter() {
+            self.calendar.mark_day(*day);
+        }
+    }
+}
+
+impl Journal {
+    fn date(calendar: &gtk::Calendar) -> Option<NaiveDate> {
+        let d = calendar.date();
+        NaiveDate::from_ymd_opt(
+            d.year(),
+            d.month() as u32,
+            d.day_of_month() as u32,
+        )
+    }
+
+    fn month_changed(calendar: &gtk::Calendar, sender: &relm4::Sender<Msg>) {
+        if let Some(date) = Self::date(calendar) {
+            send!(sender, Msg::MonthChanged(date.year(), date.month()));
+        }
+    }
+}
@@ -1,12 +1,16 @@
 mod backlinks;
 mod editor;
+mod folders;
+mod journal;
 mod listview;
 mod msg;
 mod search;
+mod sequence;
 
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use chrono::{Local, NaiveDate};
 use gtk::ApplicationWindow;
 use gtk::CssProvider;
 use gtk::MessageType;
@@ -21,13 +25,16 @@ use relm4::Model;
 use relm4::RelmApp;
 use relm4::RelmComponent;
 use relm4::Widgets;
-use rustybrain_core::config::Config;
+use rustybrain_core::config::{Config, ConfigLoader, Theme, ThemeMode};
 use rustybrain_core::kasten::Kasten;
 use rustybrain_core::zettel::Zettel;
 
 #[derive(Clone, Debug)]
 pub enum Msg {
+    /// Quit once the changed notes are saved or discarded.
     Quit,
+    /// Quit right now.
+    Exit,
     StartSearch,
     StartInsert,
     Init(ApplicationWindow),
@@ -35,13 +42,38 @@ pub enum Msg {
     InsertZettel(Rc<RefCell<Zettel>>),
     /// Means insert current zettel to previous zettel after save.
     OpenZettelOnStack(Rc<RefCell<Zettel>>),
-    NewZettel(String, bool),
+    /// Create a new zettel with title and template, read the clipboard for
+    /// the template first.
+    NewZettel(String, bool, Option<String>),
+    CreateZettel(String, bool, Option<String>, Option<String>),
     ShowMsg(MessageType, String),
+    /// Open the daily note of the date, `None` means today.
+    OpenJournal(Option<NaiveDate>),
+    /// Show the sequence tree in the left pane.
+    ShowSequence,
+    /// Read the theme from the changed config.
+    ReloadTheme,
+    /// Apply the theme, after the config or the desktop preference changed.
+    ApplyTheme,
+    NextTab,
+    PrevTab,
+    CloseTab,
+    /// Notes of the slip-box are created, saved or reloaded.
+    KastenChanged,
+    ToggleList,
 }
 
 pub struct AppModel {
     show_list: bool,
     show_back: bool,
+    /// Times the sequence tree is asked to show.
+    sequence_shown: usize,
+    /// Times the list of notes is asked to show.
+    notes_shown: usize,
+    /// Whether the editor is styled in dark mode.
+    dark: bool,
+    /// Times the theme is applied, the user CSS is reloaded each time.
+    theme_version: usize,
 
     config: Rc<RefCell<Config>>,
     kasten: Rc<RefCell<Kasten>>,
@@ -50,8 +82,11 @@ pub struct AppModel {
 pub struct AppComponents {
     editor: RelmComponent<editor::Model, AppModel>,
     listview: RelmComponent<listview::Model, AppModel>,
+    folders: RelmComponent<folders::Model, AppModel>,
     backlinks: RelmComponent<backlinks::Model, AppModel>,
     search: RelmComponent<search::Model, AppModel>,
+    journal: RelmComponent<journal::Model, AppModel>,
+    sequence: RelmComponent<sequence::Model, AppModel>,
     msg: RelmComponent<msg::Model, AppModel>,
 }
 
@@ -63,8 +98,11 @@ impl Components<AppModel> for AppComponents {
         AppComponents {
             editor: RelmComponent::new(parent_model, parent_sender.clone()),
             listview: RelmComponent::new(parent_model, parent_sender.clone()),
+            folders: RelmComponent::new(parent_model, parent_sender.clone()),
             backlinks: RelmComponent::new(parent_model, parent_sender.clone()),
             search: RelmComponent::new(parent_model, parent_sender.clone()),
+            journal: RelmComponent::new(parent_model, parent_sender.clone()),
+            sequence: RelmComponent::new(parent_model, parent_sender.clone()),
             msg: RelmComponent::new(parent_model, parent_sender),
         }
     }
@@ -76,9 +114,18 @@ pub struct AppWidgets {
     window: ApplicationWindow,
 
     main_layout: gtk::Box,
-    left: gtk::ScrolledWindow,
+    left: gtk::Notebook,
+    sequence_shown: usize,
+    notes_shown: usize,
     center: gtk::Box,
     right: gtk::ScrolledWindow,
+
+    /// CSS of the user that overrides the style of the app.
+    user_css: CssProvider,
+    theme_version: usize,
+    /// Watches the config directory for the theme, it stops once dropped.
+    #[allow(dead_code)]
+    monitor: Option<gtk::gio::FileMonitor>,
 }
 
 impl Model for AppModel {
@@ -97,9 +144,14 @@ impl AppUpdate for AppModel {
         sender: relm4::Sender<Self::Msg>,
     ) -> bool {
         match msg {
-            Msg::Quit => relm4::gtk_application().quit(),
+            Msg::Quit => send!(components.editor.sender(), editor::Msg::Quit),
+            Msg::Exit => relm4::gtk_application().quit(),
             Msg::ChangeZettel(z) => {
                 send!(components.editor.sender(), editor::Msg::Open(z.clone()));
+                send!(
+                    components.sequence.sender(),
+                    sequence::Msg::ChangeZettel(z.clone())
+                );
                 send!(
                     components.backlinks.sender(),
                     backlinks::Msg::ChangeZettel(z)
@@ -115,7 +167,8 @@ impl AppUpdate for AppModel {
                 send!(
                     components.search.sender(),
                     search::Msg::Init(w, self.kasten.clone())
-                )
+                );
+                send!(components.editor.sender(), editor::Msg::OfferDrafts);
             }
             Msg::StartSearch => {
                 send!(components.search.sender(), search::Msg::Show(false))
@@ -126,8 +179,34 @@ impl AppUpdate for AppModel {
             Msg::ShowMsg(t, s) => {
                 send!(components.msg.sender(), msg::Msg::Show(t, s))
             }
-            Msg::NewZettel(title, inserting) => {
-                match self.kasten.borrow_mut().create(&title) {
+            Msg::NewZettel(title, inserting, template) => {
+                match gdk::Display::default() {
+                    Some(display) => display.clipboard().read_text_async(
+                        None::<&gtk::gio::Cancellable>,
+                        move |text| {
+                            let text =
+                                text.ok().flatten().map(|t| t.to_string());
+                            send!(
+                                sender,
+                                Msg::CreateZettel(
+                                    title, inserting, template, text
+                                )
+                            );
+                        },
+                    ),
+                    None => send!(
+                        sender,
+                        Msg::CreateZettel(title, inserting, template, None)
+                    ),
+                }
+            }
+            Msg::CreateZettel(title, inserting, template, clipboard) => {
+                let z = self.kasten.borrow_mut().create_from_template(
+                    &title,
+                    template.as_deref(),
+                    clipboard.as_deref(),
+                );
+                match z {
                     Ok(z) => {
                         if inserting {
                             send!(sender, Msg::OpenZettelOnStack(z));
@@ -144,6 +223,65 @@ impl AppUpdate for AppModel {
                     ),
                 }
             }
+            Msg::OpenJournal(date) => {
+                let date = date.unwrap_or_else(|| Local::now().date_naive());
+                match self.kasten.borrow_mut().journal(date) {
+                    Ok(z) => send!(sender, Msg::ChangeZettel(z)),
+                    Err(e) => send!(
+                        sender,
+                        Msg::ShowMsg(
+                            MessageType::Error,
+                            format!("Open journal failed: {:?}!", e)
+                        )
+                    ),
+                }
+            }
+            Msg::ShowSequence => {
+                self.show_list = true;
+                self.sequence_shown += 1;
+                send!(components.sequence.sender(), sequence::Msg::Refresh);
+            }
+            Msg::ReloadTheme => match ConfigLoader::new().load() {
+                Ok(config) => {
+                    self.config.borrow_mut().set_theme(config.theme().clone());
+                    send!(sender, Msg::ApplyTheme);
+                }
+                Err(e) => send!(
+                    sender,
+                    Msg::ShowMsg(
+                        MessageType::Error,
+                        format!("Reload theme failed: {:?}!", e)
+                    )
+                ),
+            },
+            Msg::ApplyTheme => {
+                let theme = self.config.borrow().theme().clone();
+                self.dark = apply_theme_mode(&theme);
+                self.theme_version += 1;
+                send!(
+                    components.editor.sender(),
+                    editor::Msg::Theme(theme, self.dark)
+                );
+            }
+            Msg::NextTab => {
+                send!(components.editor.sender(), editor::Msg::NextTab)
+            }
+            Msg::PrevTab => {
+                send!(components.editor.sender(), editor::Msg::PrevTab)
+            }
+            Msg::CloseTab => {
+                send!(components.editor.sender(), editor::Msg::CloseTab(None))
+            }
+            Msg::KastenChanged => {
+                send!(components.folders.sender(), folders::Msg::Refresh);
+                send!(components.listview.sender(), listview::Msg::Refresh);
+            }
+            Msg::ToggleList => {
+                self.show_list = !self.show_list;
+                if self.show_list {
+                    self.notes_shown += 1;
+                }
+            }
         }
         true
     }
@@ -163,6 +301,15 @@ impl Widgets<AppModel, ()> for AppWidgets {
         ));
         window.set_default_size(1200, 800);
         send!(sender, Msg::Init(window.clone()));
+        let s = sender.clone();
+        model
+            .kasten
+            .borrow()
+            .connect_changed(move || send!(s, Msg::KastenChanged));
+
+        let header = gtk::HeaderBar::new();
+        header.pack_start(components.journal.root_widget());
+        window.set_titlebar(Some(&header));
 
         let box_ = gtk::Box::builder()
             .orientation(gtk::Orientation::Horizontal)
@@ -171,7 +318,18 @@ impl Widgets<AppModel, ()> for AppWidgets {
             .vexpand(true)
             .build();
 
-        let left = components.listview.root_widget().clone();
+        // Smart folders of the saved searches above all the notes.
+        let notes = gtk::Paned::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .start_child(components.folders.root_widget())
+            .end_child(components.listview.root_widget())
+            .build();
+        let left = gtk::Notebook::new();
+        left.append_page(&notes, Some(&gtk::Label::new(Some("Notes"))));
+        left.append_page(
+            components.sequence.root_widget(),
+            Some(&gtk::Label::new(Some("Sequence"))),
+        );
         let center = components.editor.root_widget().clone();
         let right = components.backlinks.root_widget().clone();
 
@@ -197,15 +355,82 @@ impl Widgets<AppModel, ()> for AppWidgets {
             c.shortcut().quit(),
             Msg::Quit,
         ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().journal(),
+            Msg::OpenJournal(None),
+        ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().sequence(),
+            Msg::ShowSequence,
+        ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().next_tab(),
+            Msg::NextTab,
+        ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().prev_tab(),
+            Msg::PrevTab,
+        ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().close_tab(),
+            Msg::CloseTab,
+        ));
+        shortcut_ctrl.add_shortcut(&Self::bind_key(
+            sender.clone(),
+            c.shortcut().list(),
+            Msg::ToggleList,
+        ));
         window.add_controller(&shortcut_ctrl);
+
+        let display = window.display();
+        let provider = CssProvider::new();
+        provider.load_from_resource("/dev/rustybrain/app/assets/css/main.css");
+        StyleContext::add_provider_for_display(&display, &provider, 100);
+        let user_css = CssProvider::new();
+        Self::load_user_css(&user_css, c.theme());
+        StyleContext::add_provider_for_display(
+            &display,
+            &user_css,
+            gtk::STYLE_PROVIDER_PRIORITY_USER,
+        );
+        drop(c);
+
+        if let Some(settings) = gtk::Settings::default() {
+            let s = sender.clone();
+            settings.connect_gtk_application_prefer_dark_theme_notify(
+                move |_| send!(s, Msg::ApplyTheme),
+            );
+            let s = sender.clone();
+            settings.connect_gtk_theme_name_notify(move |_| {
+                send!(s, Msg::ApplyTheme)
+            });
+        }
+        let monitor = Self::watch_config(sender.clone());
+
+        let s = sender.clone();
+        window.connect_close_request(move |_| {
+            send!(s, Msg::Quit);
+            gtk::Inhibit(true)
+        });
+
         window.connect_show(move |_| send!(sender, Msg::StartSearch));
 
         AppWidgets {
             window,
             main_layout: box_,
             left,
+            sequence_shown: 0,
+            notes_shown: 0,
             right,
             center,
+            user_css,
+            theme_version: 0,
+            monitor,
         }
     }
 
@@ -220,22 +445,67 @@ impl Widgets<AppModel, ()> for AppWidgets {
         if model.show_list {
             self.main_layout.append(&self.left);
         }
+        if self.sequence_shown != model.sequence_shown {
+            self.sequence_shown = model.sequence_shown;
+            self.left.set_current_page(Some(1));
+        }
+        if self.notes_shown != model.notes_shown {
+            self.notes_shown = model.notes_shown;
+            self.left.set_current_page(Some(0));
+        }
         self.main_layout.append(&self.center);
         if model.show_back {
             self.main_layout.append(&self.right);
         }
 
-        let provider = CssProvider::new();
-        provider.load_from_resource("/dev/rustybrain/app/assets/css/main.css");
-        StyleContext::add_provider_for_display(
-            &self.root_widget().display(),
-            &provider,
-            100,
-        );
+        if self.theme_version != model.theme_version {
+            self.theme_version = model.theme_version;
+            Self::load_user_css(&self.user_css, model.config.borrow().theme());
+        }
     }
 }
 
 impl AppWidgets {
+    /// Load the CSS file of the theme from the config directory, nothing
+    /// is overridden if there is no such file.
+    fn load_user_css(provider: &CssProvider, theme: &Theme) {
+        let path = ConfigLoader::new().dir().join(theme.css());
+        if path.is_file() {
+            provider.load_from_file(&gtk::gio::File::for_path(&path));
+        } else {
+            provider.load_from_data(b"");
+        }
+    }
+
+    /// Reload the theme once the config or a CSS file in its directory is
+    /// saved.
+    fn watch_config(
+        sender: relm4::Sender<Msg>,
+    ) -> Option<gtk::gio::FileMonitor> {
+        let loader = ConfigLoader::new();
+        let monitor = gtk::gio::File::for_path(loader.dir())
+            .monitor_directory(
+                gtk::gio::FileMonitorFlags::NONE,
+                None::<&gtk::gio::Cancellable>,
+            )
+            .ok()?;
+        let config = loader.path().to_path_buf();
+        monitor.connect_changed(move |_, file, _, event| {
+            if event != gtk::gio::FileMonitorEvent::ChangesDoneHint {
+                return;
+            }
+            let path = match file.path() {
+                Some(path) => path,
+                None => return,
+            };
+            let is_css = path.extension().map_or(false, |e| e == "css");
+            if path == config || is_css {
+                send!(sender, Msg::ReloadTheme);
+            }
+        });
+        Some(monitor)
+    }
+
     fn bind_key(sender: relm4::Sender<Msg>, key: &str, msg: Msg) -> Shortcut {
         let action = CallbackAction::new(move |_, _| {
             send!(sender, msg.clone());
@@ -249,12 +519,50 @@ impl AppWidgets {
     }
 }
 
+/// Set the preference of the app by the mode of the theme, and whether the
+/// editor is dark then.
+///
+/// In the system mode a dark GTK theme also makes the editor dark.
+fn apply_theme_mode(theme: &Theme) -> bool {
+    let settings = match gtk::Settings::default() {
+        Some(settings) => settings,
+        None => return theme.mode() == ThemeMode::Dark,
+    };
+    match theme.mode() {
+        ThemeMode::Light => {
+            settings.set_gtk_application_prefer_dark_theme(false);
+            false
+        }
+        ThemeMode::Dark => {
+            settings.set_gtk_application_prefer_dark_theme(true);
+            true
+        }
+        ThemeMode::System => {
+            // Back to the preference of the desktop after a forced mode.
+            settings.reset_property("gtk-application-prefer-dark-theme");
+            let name = settings
+                .gtk_theme_name()
+                .map(|n| n.to_lowercase())
+                .unwrap_or_default();
+            settings.is_gtk_application_prefer_dark_theme()
+                || name.ends_with("-dark")
+                || name.ends_with(":dark")
+        }
+    }
+}
+
 pub fn run(config: Rc<RefCell<Config>>) {
     gio::resources_register_include!("app.gresource").unwrap();
+    gtk::init().unwrap();
 
+    let dark = apply_theme_mode(config.borrow().theme());
     let model = AppModel {
         show_list: false,
         show_back: true,
+        sequence_shown: 0,
+        notes_shown: 0,
+        dark,
+        theme_version: 0,
         config: config.clone(),
         kasten: Rc::new(RefCell::new(Kasten::new(config).unwrap())),
     };
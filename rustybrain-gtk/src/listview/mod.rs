@@ -1,70 +1,35 @@
-mod item;
-
 use std::cell::RefCell;
 use std::rc::Rc;
 
 use gtk::prelude::*;
-use gtk::ListBox;
-use gtk::ListBoxRow;
-use gtk::ScrolledWindow;
-use relm4::send;
-use relm4::ComponentUpdate;
-use relm4::Components;
-use relm4::RelmComponent;
-use relm4::Widgets;
-use rustybrain_core::kasten::Kasten;
-use rustybrain_core::zettel::Zettel;
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::fuzzy::fuzzy_match;
+use rustybrain_core::kasten::{Kasten, SortBy};
+
+use crate::AppModel;
 
 pub struct Model {
     kasten: Rc<RefCell<Kasten>>,
+    filter: String,
+    sort: SortBy,
+    /// Titles and zids of the notes shown, in order.
+    notes: Vec<(String, String)>,
+    version: usize,
 }
 
 pub enum Msg {
-    RowSelected(ListBoxRow),
-    ZettelSelected(Rc<RefCell<Zettel>>),
+    /// Read the notes from the kasten again.
+    Refresh,
+    Filter(String),
+    Sort(u32),
+    Activate(u32),
 }
 
 pub struct ListView {
-    window: ScrolledWindow,
-
-    #[allow(dead_code)]
-    view: ListBox,
-}
-
-pub struct RowModel {
-    zettel: Rc<RefCell<Zettel>>,
-}
-
-impl relm4::Model for RowModel {
-    type Msg = Msg;
-
-    type Widgets = ListView;
-
-    type Components = ();
-}
-
-pub struct ListViewComponents {
-    rows: Vec<RelmComponent<item::Model, RowModel>>,
-}
-
-impl Components<Model> for ListViewComponents {
-    fn init_components(
-        parent_model: &Model,
-        parent_sender: relm4::Sender<Msg>,
-    ) -> Self {
-        let mut items = vec![];
-        let kasten = parent_model.kasten.borrow();
-        for zettel in kasten.iter() {
-            let model = RowModel {
-                zettel: zettel.clone(),
-            };
-            let item = RelmComponent::new(&model, parent_sender.clone());
-            items.push(item);
-        }
-        ListViewComponents { rows: items }
-    }
-
-    fn connect_parent(&mut self, _parent_widgets: &ListView) {}
+    layout: gtk::Box,
+    /// Titles of the rows, the list view only creates the visible ones.
+    store: gtk::StringList,
+    version: usize,
 }
 
 impl relm4::Model for Model {
@@ -72,68 +37,158 @@ impl relm4::Model for Model {
 
     type Widgets = ListView;
 
-    type Components = ListViewComponents;
+    type Components = ();
 }
 
-impl ComponentUpdate<super::AppModel> for Model {
-    fn init_model(parent_model: &super::AppModel) -> Self {
-        Model {
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        let mut model = Model {
             kasten: parent_model.kasten.clone(),
-        }
+            filter: String::new(),
+            sort: SortBy::default(),
+            notes: vec![],
+            version: 0,
+        };
+        model.reload();
+        model
     }
 
     fn update(
         &mut self,
         msg: Self::Msg,
-        components: &Self::Components,
+        _components: &(),
         _sender: relm4::Sender<Self::Msg>,
         parent_sender: relm4::Sender<super::Msg>,
     ) {
         match msg {
-            Msg::RowSelected(row) => {
-                for item in &components.rows {
-                    if item.root_widget() == &row {
-                        send!(
-                            item.sender(),
-                            crate::listview::item::Msg::Activated
-                        );
-                    }
+            Msg::Refresh => self.reload(),
+            Msg::Filter(filter) => {
+                self.filter = filter;
+                self.reload();
+            }
+            Msg::Sort(index) => {
+                if let Some(sort) = SortBy::ALL.get(index as usize) {
+                    self.sort = *sort;
+                    self.reload();
                 }
             }
-            Msg::ZettelSelected(zettel) => {
-                send!(parent_sender, super::Msg::ChangeZettel(zettel))
+            Msg::Activate(position) => {
+                let zid = match self.notes.get(position as usize) {
+                    Some((_, zid)) => zid,
+                    None => return,
+                };
+                if let Some(z) = self.kasten.borrow().get(zid) {
+                    send!(parent_sender, super::Msg::ChangeZettel(z));
+                }
             }
         }
     }
 }
 
-impl Widgets<Model, super::AppModel> for ListView {
-    type Root = gtk::ScrolledWindow;
+impl Model {
+    fn reload(&mut self) {
+        let kasten = self.kasten.borrow();
+        let filter = self.filter.trim();
+        let mut zettels: Vec<_> = kasten
+            .iter()
+            .filter(|z| fuzzy_match(filter, z.borrow().title()).is_some())
+            .cloned()
+            .collect();
+        self.sort.sort(&mut zettels);
+        self.notes = zettels
+            .iter()
+            .map(|z| {
+                let z = z.borrow();
+                (z.title().to_string(), z.zid().to_string())
+            })
+            .collect();
+        self.version += 1;
+    }
+}
+
+impl Widgets<Model, AppModel> for ListView {
+    type Root = gtk::Box;
 
     fn init_view(
         _model: &Model,
-        components: &ListViewComponents,
+        _components: &(),
         sender: relm4::Sender<Msg>,
     ) -> Self {
-        let view = ListBox::new();
-        for item in &components.rows {
-            view.append(item.root_widget());
-        }
-        view.connect_row_selected(move |_, row| {
-            if let Some(r) = row {
-                send!(sender, Msg::RowSelected(r.clone()))
+        let store = gtk::StringList::new(&[]);
+        let factory = gtk::SignalListItemFactory::new();
+        factory.connect_setup(|_, item| {
+            let label = gtk::Label::builder()
+                .halign(gtk::Align::Start)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+            item.set_child(Some(&label));
+        });
+        factory.connect_bind(|_, item| {
+            let title = item
+                .item()
+                .and_then(|o| o.downcast::<gtk::StringObject>().ok());
+            let label =
+                item.child().and_then(|c| c.downcast::<gtk::Label>().ok());
+            if let (Some(title), Some(label)) = (title, label) {
+                label.set_text(&title.string());
             }
         });
+        let selection = gtk::SingleSelection::new(Some(&store));
+        let view = gtk::ListView::new(Some(&selection), Some(&factory));
+        view.set_single_click_activate(true);
+        let s = sender.clone();
+        view.connect_activate(move |_, position| {
+            send!(s, Msg::Activate(position))
+        });
 
-        let window = ScrolledWindow::new();
-        window.set_child(Some(&view));
-        window.set_width_request(200);
-        ListView { window, view }
+        let filter = gtk::SearchEntry::builder()
+            .hexpand(true)
+            .placeholder_text("Filter notes")
+            .build();
+        let s = sender.clone();
+        filter.connect_changed(move |e| {
+            send!(s, Msg::Filter(e.text().to_string()))
+        });
+        let names: Vec<&str> = SortBy::ALL.iter().map(|s| s.name()).collect();
+        let sort = gtk::DropDown::from_strings(&names);
+        sort.set_tooltip_text(Some("Order of the notes"));
+        sort.connect_selected_notify(move |d| {
+            send!(sender, Msg::Sort(d.selected()))
+        });
+        let bar = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .build();
+        bar.append(&filter);
+        bar.append(&sort);
+
+        let window = gtk::ScrolledWindow::builder()
+            .child(&view)
+            .vexpand(true)
+            .build();
+        let layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .width_request(200)
+            .build();
+        layout.append(&bar);
+        layout.append(&window);
+        ListView {
+            layout,
+            store,
+            version: 0,
+        }
     }
 
     fn root_widget(&self) -> Self::Root {
-        self.window.clone()
+        self.layout.clone()
     }
 
-    fn view(&mut self, _model: &Model, _sender: relm4::Sender<Msg>) {}
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        if self.version == model.version {
+            return;
+        }
+        self.version = model.version;
+        let titles: Vec<&str> =
+            model.notes.iter().map(|(t, _)| t.as_str()).collect();
+        self.store.splice(0, self.store.n_items(), &titles);
+    }
 }
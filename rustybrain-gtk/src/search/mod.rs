@@ -2,22 +2,36 @@ use std::{cell::RefCell, rc::Rc};
 
 use gdk::{Key, ModifierType};
 use gtk::{
-    prelude::*, ApplicationWindow, Dialog, EventControllerKey, MessageType,
+    prelude::*, ApplicationWindow, Dialog, EventControllerKey, Inhibit,
     ScrolledWindow,
 };
 use relm4::{send, ComponentUpdate, Widgets};
-use rustybrain_core::{config::Config, kasten::Kasten, zettel::Zettel};
+use rustybrain_core::{
+    config::Config,
+    kasten::{Found, Kasten},
+    query,
+    zettel::Zettel,
+};
 
 use crate::AppModel;
 
 pub struct Model {
     app_win: Option<ApplicationWindow>,
-    zettels: Vec<Rc<RefCell<Zettel>>>,
+    found: Vec<Found>,
+    /// Bumped when the results change, so the rows are only rebuilt then.
+    version: usize,
     searching: String,
+    /// Whether the search is a query with fields or operators, rather than
+    /// a title to match or create.
+    structured: bool,
+    /// Part of the query that can't be understood.
+    error: Option<String>,
     inserting: bool,
     show: bool,
     kasten: Option<Rc<RefCell<Kasten>>>,
     config: Rc<RefCell<Config>>,
+    templates: Vec<String>,
+    template: Option<String>,
 }
 
 pub enum Msg {
@@ -27,11 +41,20 @@ pub enum Msg {
     Changed(String),
     Search(Rc<RefCell<Kasten>>, String),
     Activate(Option<Rc<RefCell<Zettel>>>),
+    /// Row of the results activated by a click or enter.
+    ActivateRow(i32),
+    /// Template selected for new note, `0` means the default one.
+    SelectTemplate(u32),
 }
 
 pub struct Search {
     dialog: Dialog,
+    entry: gtk::SearchEntry,
+    error: gtk::Label,
     list_box: gtk::ListBox,
+    version: usize,
+    templates: gtk::DropDown,
+    template_names: Vec<String>,
 }
 
 impl relm4::Model for Model {
@@ -44,15 +67,19 @@ impl relm4::Model for Model {
 
 impl ComponentUpdate<AppModel> for Model {
     fn init_model(parent_model: &AppModel) -> Self {
-        let zettels = vec![];
         Model {
             app_win: None,
+            found: vec![],
+            version: 0,
             kasten: None,
             show: false,
             searching: "".to_string(),
+            structured: false,
+            error: None,
             inserting: false,
             config: parent_model.config.clone(),
-            zettels,
+            templates: vec![],
+            template: None,
         }
     }
 
@@ -67,11 +94,16 @@ impl ComponentUpdate<AppModel> for Model {
             Msg::Show(inserting) => {
                 self.show = true;
                 self.inserting = inserting;
+                self.load_templates();
+                if let Some(kasten) = &self.kasten {
+                    let s = self.searching.clone();
+                    send!(sender, Msg::Search(kasten.clone(), s));
+                }
             }
             Msg::Hide => self.show = false,
             Msg::Init(w, k) => {
                 self.app_win = Some(w);
-                self.handle_init(&k.borrow());
+                self.handle_search(&k.borrow(), "");
                 self.kasten = Some(k);
             }
             Msg::Changed(s) => {
@@ -80,9 +112,16 @@ impl ComponentUpdate<AppModel> for Model {
                     send!(sender, Msg::Search(kasten.clone(), s));
                 }
             }
-            Msg::Search(k, s) => {
-                self.handle_search(&k.borrow(), parent_sender, &s)
-            }
+            Msg::Search(k, s) => self.handle_search(&k.borrow(), &s),
+            Msg::ActivateRow(index) => match self.found.get(index as usize) {
+                Some(f) => {
+                    send!(sender, Msg::Activate(Some(f.zettel().clone())))
+                }
+                None if self.can_create() => {
+                    send!(sender, Msg::Activate(None))
+                }
+                None => {}
+            },
             Msg::Activate(item) => {
                 send!(sender, Msg::Hide);
                 if let Some(z) = item {
@@ -96,51 +135,82 @@ impl ComponentUpdate<AppModel> for Model {
                         parent_sender,
                         super::Msg::NewZettel(
                             self.searching.to_string(),
-                            self.inserting
+                            self.inserting,
+                            self.template.clone(),
                         )
                     );
                 }
             }
+            Msg::SelectTemplate(idx) => {
+                self.template = match idx {
+                    0 => None,
+                    n => self.templates.get(n as usize - 1).cloned(),
+                };
+            }
         }
     }
 }
 
 impl Model {
-    fn handle_init(&mut self, kasten: &Kasten) {
-        for item in kasten.iter() {
-            self.zettels.push(item.clone());
+    fn load_templates(&mut self) {
+        if let Some(kasten) = &self.kasten {
+            self.templates = kasten
+                .borrow()
+                .templates()
+                .unwrap_or_default()
+                .iter()
+                .map(|t| t.name().to_string())
+                .collect();
+        }
+        if let Some(t) = &self.template {
+            if !self.templates.contains(t) {
+                self.template = None;
+            }
         }
     }
 
-    fn handle_search(
-        &mut self,
-        kasten: &Kasten,
-        parent_sender: relm4::Sender<super::Msg>,
-        s: &str,
-    ) {
-        self.zettels.clear();
-        match kasten.search_title(s) {
-            Ok(set) => {
-                if set.is_empty() {
-                    self.handle_init(kasten);
-                    return;
+    fn handle_search(&mut self, kasten: &Kasten, s: &str) {
+        self.structured = false;
+        self.error = None;
+        // A query after `~` is searched by meaning.
+        if let Some(q) = s.trim_start().strip_prefix('~') {
+            self.structured = true;
+            let limit = self.config.borrow().search().limit();
+            self.found = match kasten.semantic_search(q, limit) {
+                Ok(zettels) => zettels.into_iter().map(Found::from).collect(),
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    vec![]
                 }
-                for entry in kasten.iter() {
-                    let z = &entry.borrow();
-                    let p = z.path().to_str().unwrap();
-                    if set.contains::<String>(&p.to_string()) {
-                        self.zettels.push(entry.clone());
+            };
+            self.version += 1;
+            return;
+        }
+        self.found = match query::parse(s) {
+            Ok(Some(q)) if q.is_structured() => {
+                self.structured = true;
+                match kasten.search_query(&q) {
+                    Ok(zettels) => {
+                        zettels.into_iter().map(Found::from).collect()
+                    }
+                    Err(e) => {
+                        self.error = Some(e.to_string());
+                        vec![]
                     }
                 }
             }
-            Err(_) => send!(
-                parent_sender,
-                super::Msg::ShowMsg(
-                    MessageType::Error,
-                    "Search notes from slip-box failed!".to_string()
-                )
-            ),
+            Ok(_) => kasten.find(s),
+            Err(e) => {
+                self.error = Some(e.to_string());
+                kasten.find(s)
+            }
         };
+        self.version += 1;
+    }
+
+    /// Whether a note of the searched title can be created.
+    fn can_create(&self) -> bool {
+        !self.structured && !self.searching.trim().is_empty()
     }
 }
 
@@ -167,14 +237,38 @@ impl Widgets<Model, AppModel> for Search {
         let box_ = gtk::Box::builder()
             .orientation(gtk::Orientation::Vertical)
             .build();
-        let list_box = gtk::ListBox::builder().build();
+        let list_box = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::Browse)
+            .build();
+        let s = sender.clone();
+        list_box.connect_row_activated(move |_, row| {
+            send!(s, Msg::ActivateRow(row.index()))
+        });
         let window = ScrolledWindow::builder()
             .hexpand(true)
             .height_request(200)
             .width_request(600)
             .child(&list_box)
             .build();
-        box_.append(&entry);
+        let templates = gtk::DropDown::from_strings(&["Default template"]);
+        templates.set_tooltip_text(Some("Template for new note"));
+        let s = sender.clone();
+        templates.connect_selected_notify(move |d| {
+            send!(s, Msg::SelectTemplate(d.selected()))
+        });
+        let search_box = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .build();
+        search_box.append(&entry);
+        search_box.append(&templates);
+        box_.append(&search_box);
+        let error = gtk::Label::builder()
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .visible(false)
+            .build();
+        error.add_css_class("error");
+        box_.append(&error);
         box_.append(&window);
         dialog.set_child(Some(&box_));
 
@@ -182,6 +276,24 @@ impl Widgets<Model, AppModel> for Search {
         entry.connect_changed(move |e| {
             send!(s, Msg::Changed(e.text().as_str().to_string()))
         });
+        let (s, l) = (sender.clone(), list_box.clone());
+        entry.connect_activate(move |_| {
+            if let Some(row) = l.selected_row() {
+                send!(s, Msg::ActivateRow(row.index()));
+            }
+        });
+        // The selection moves through the results while typing.
+        let keys = EventControllerKey::new();
+        keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let l = list_box.clone();
+        keys.connect_key_pressed(move |_, key, _, _| match key {
+            Key::Down | Key::Up => {
+                select_next(&l, key == Key::Down);
+                Inhibit(true)
+            }
+            _ => Inhibit(false),
+        });
+        entry.add_controller(&keys);
         let key_ctrl = EventControllerKey::new();
         key_ctrl.connect_key_released(move |_, k, _, m| {
             if m == ModifierType::empty() && k == Key::Escape {
@@ -190,81 +302,132 @@ impl Widgets<Model, AppModel> for Search {
         });
         dialog.add_controller(&key_ctrl);
 
-        Search { dialog, list_box }
+        Search {
+            dialog,
+            entry,
+            error,
+            list_box,
+            version: 0,
+            templates,
+            template_names: vec![],
+        }
     }
 
     fn root_widget(&self) -> Self::Root {
         self.dialog.clone()
     }
 
-    fn view(&mut self, model: &Model, sender: relm4::Sender<Msg>) {
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
         self.dialog.set_transient_for(model.app_win.as_ref());
-        if model.show {
+        if self.template_names != model.templates {
+            self.template_names = model.templates.clone();
+            let mut names = vec!["Default template"];
+            names.extend(model.templates.iter().map(|s| s.as_str()));
+            self.templates
+                .set_model(Some(&gtk::StringList::new(&names)));
+        }
+        if model.show && !self.dialog.is_visible() {
+            self.dialog.set_title(Some(if model.inserting {
+                "Insert link to note"
+            } else {
+                "Open note"
+            }));
             self.dialog.show();
-        } else {
+            self.entry.grab_focus();
+        } else if !model.show {
             self.dialog.hide();
         }
+        if self.version == model.version {
+            return;
+        }
+        self.version = model.version;
+        self.error.set_visible(model.error.is_some());
+        self.error
+            .set_text(model.error.as_deref().unwrap_or_default());
         while let Some(c) = self.list_box.last_child() {
             self.list_box.remove(&c);
         }
-        if !model.searching.is_empty() {
-            self.list_box
-                .append(&self.new_list_row(model, sender.clone()));
+        for found in model.found.iter() {
+            self.list_box.append(&found_row(found));
         }
-
-        for item in model.zettels.iter() {
-            if model.inserting {
-                self.list_box.append(&self.row(
-                    item.borrow().title(),
-                    true,
-                    Some(item.clone()),
-                    sender.clone(),
-                ));
-            } else {
-                self.list_box.append(&self.row(
-                    item.borrow().title(),
-                    false,
-                    Some(item.clone()),
-                    sender.clone(),
-                ));
-            }
+        if model.can_create() {
+            self.list_box.append(&new_row(model.searching.trim()));
         }
+        self.list_box
+            .select_row(self.list_box.row_at_index(0).as_ref());
     }
 }
 
-impl Search {
-    fn row(
-        &self,
-        item: &str,
-        inserting: bool,
-        zettel: Option<Rc<RefCell<Zettel>>>,
-        sender: relm4::Sender<Msg>,
-    ) -> gtk::ListBoxRow {
-        let btn_label = if inserting { "Insert" } else { "Go" };
-        let box_ = gtk::Box::builder()
-            .orientation(gtk::Orientation::Horizontal)
-            .hexpand(true)
-            .build();
-        let label = gtk::Label::builder()
-            .label(item)
-            .justify(gtk::Justification::Left)
-            .hexpand(true)
-            .build();
+/// Row of a found note, the title above its zid with the matched characters
+/// in bold.
+fn found_row(found: &Found) -> gtk::ListBoxRow {
+    let z = found.zettel().borrow();
+    let box_ = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .hexpand(true)
+        .build();
+    let mut markup = highlight(z.title(), found.title_indices());
+    if !z.aliases().is_empty() {
+        let aliases: Vec<String> = z
+            .aliases()
+            .iter()
+            .map(|a| match found.alias() {
+                Some((matched, indices)) if matched == a => {
+                    highlight(a, indices)
+                }
+                _ => highlight(a, &[]),
+            })
+            .collect();
+        markup.push_str(&format!(" <i>({})</i>", aliases.join(", ")));
+    }
+    let title = gtk::Label::builder()
+        .use_markup(true)
+        .label(&markup)
+        .halign(gtk::Align::Start)
+        .ellipsize(gtk::pango::EllipsizeMode::End)
+        .build();
+    let path = gtk::Label::builder()
+        .use_markup(true)
+        .label(&format!(
+            "<small>{}</small>",
+            highlight(z.zid(), found.path_indices())
+        ))
+        .halign(gtk::Align::Start)
+        .ellipsize(gtk::pango::EllipsizeMode::Middle)
+        .build();
+    path.add_css_class("dim-label");
+    box_.append(&title);
+    box_.append(&path);
+    gtk::ListBoxRow::builder().child(&box_).build()
+}
 
-        let btn = gtk::Button::builder().label(btn_label).build();
-        btn.connect_clicked(move |_| {
-            send!(sender, Msg::Activate(zettel.clone()));
-        });
-        box_.append(&label);
-        box_.append(&btn);
-        gtk::ListBoxRow::builder().child(&box_).build()
+/// Row that creates a note of the searched title.
+fn new_row(title: &str) -> gtk::ListBoxRow {
+    let label = gtk::Label::builder()
+        .label(&format!("Create \u{201c}{}\u{201d}", title))
+        .halign(gtk::Align::Start)
+        .build();
+    gtk::ListBoxRow::builder().child(&label).build()
+}
+
+/// Pango markup of the text with the chars at the indices in bold.
+fn highlight(text: &str, indices: &[usize]) -> String {
+    let mut markup = String::new();
+    for (i, c) in text.chars().enumerate() {
+        let escaped = gtk::glib::markup_escape_text(&c.to_string());
+        if indices.contains(&i) {
+            markup.push_str(&format!("<b>{}</b>", escaped));
+        } else {
+            markup.push_str(&escaped);
+        }
     }
+    markup
+}
 
-    fn new_list_row(
-        &self,
-        model: &Model,
-        sender: relm4::Sender<Msg>,
-    ) -> gtk::ListBoxRow {
-        self.row(&model.searching, false, None, sender)
+fn select_next(list: &gtk::ListBox, down: bool) {
+    let index = list.selected_row().map_or(-1, |r| r.index());
+    let next = if down { index + 1 } else { index - 1 };
+    if let Some(row) = list.row_at_index(next) {
+        list.select_row(Some(&row));
     }
 }
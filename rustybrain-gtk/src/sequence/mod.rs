@@ -0,0 +1,266 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::glib::Type;
+use gtk::prelude::*;
+use gtk::{MessageType, TreeIter, TreeStore};
+use relm4::{send, ComponentUpdate, Widgets};
+use rustybrain_core::id::is_folgezettel;
+use rustybrain_core::kasten::{Kasten, SequenceNode};
+use rustybrain_core::zettel::Zettel;
+
+use crate::AppModel;
+
+pub struct Model {
+    kasten: Rc<RefCell<Kasten>>,
+    tree: Vec<SequenceNode>,
+    selected: Option<String>,
+    version: usize,
+}
+
+pub enum Msg {
+    Refresh,
+    ChangeZettel(Rc<RefCell<Zettel>>),
+    Selected(String),
+    Move(Move),
+}
+
+#[derive(Clone, Copy)]
+pub enum Move {
+    Up,
+    Down,
+    /// Into the branch of the sibling above as its last child.
+    In,
+    /// Out of the branch to be the next sibling of the parent.
+    Out,
+}
+
+pub struct Sequence {
+    layout: gtk::Box,
+    store: TreeStore,
+    view: gtk::TreeView,
+    version: usize,
+}
+
+impl relm4::Model for Model {
+    type Msg = Msg;
+
+    type Widgets = Sequence;
+
+    type Components = ();
+}
+
+impl ComponentUpdate<AppModel> for Model {
+    fn init_model(parent_model: &AppModel) -> Self {
+        let tree = parent_model.kasten.borrow().sequence_tree();
+        Model {
+            kasten: parent_model.kasten.clone(),
+            tree,
+            selected: None,
+            version: 1,
+        }
+    }
+
+    fn update(
+        &mut self,
+        msg: Self::Msg,
+        _components: &(),
+        _sender: relm4::Sender<Self::Msg>,
+        parent_sender: relm4::Sender<super::Msg>,
+    ) {
+        match msg {
+            Msg::Refresh => self.reload(),
+            Msg::ChangeZettel(z) => {
+                self.selected = Some(z.borrow().zid().to_string());
+                self.reload();
+            }
+            Msg::Selected(zid) => {
+                if self.selected.as_ref() == Some(&zid) {
+                    return;
+                }
+                let z = self.kasten.borrow().get(&zid);
+                self.selected = Some(zid);
+                if let Some(z) = z {
+                    send!(parent_sender, super::Msg::ChangeZettel(z));
+                }
+            }
+            Msg::Move(m) => {
+                if let Some((zid, parent, index)) = self.target(m) {
+                    let r = self.kasten.borrow_mut().move_in_sequence(
+                        &zid,
+                        parent.as_deref(),
+                        index,
+                    );
+                    // Reopen the note for the editor to get the moved header.
+                    let z = self.kasten.borrow().get(&zid);
+                    match (r, z) {
+                        (Ok(_), Some(z)) => {
+                            send!(parent_sender, super::Msg::ChangeZettel(z))
+                        }
+                        (Ok(_), None) => self.reload(),
+                        (Err(e), _) => send!(
+                            parent_sender,
+                            super::Msg::ShowMsg(
+                                MessageType::Error,
+                                format!("Move note failed: {:?}!", e)
+                            )
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Model {
+    fn reload(&mut self) {
+        self.tree = self.kasten.borrow().sequence_tree();
+        self.version += 1;
+    }
+
+    /// Zid, new parent and index of the selected zettel after the move.
+    fn target(&self, m: Move) -> Option<(String, Option<String>, usize)> {
+        let kasten = self.kasten.borrow();
+        let z = kasten.get(self.selected.as_ref()?)?;
+        let z = z.borrow();
+        let zid = z.zid().to_string();
+        let parent = kasten.parent(&z);
+        let parent_zid = parent.as_ref().map(|p| p.borrow().zid().to_string());
+        let siblings = kasten.siblings(&z);
+        let index = siblings.iter().position(|s| s.borrow().zid() == zid)?;
+        match m {
+            Move::Up => Some((zid, parent_zid, index.checked_sub(1)?)),
+            Move::Down if index + 1 < siblings.len() => {
+                Some((zid, parent_zid, index + 1))
+            }
+            Move::In => {
+                let prev = siblings.get(index.checked_sub(1)?)?.borrow();
+                let last = kasten.children(&prev).len();
+                Some((zid, Some(prev.zid().to_string()), last))
+            }
+            Move::Out => {
+                let parent = parent?;
+                let parent = parent.borrow();
+                let grand = kasten
+                    .parent(&parent)
+                    .map(|g| g.borrow().zid().to_string());
+                let index = kasten
+                    .siblings(&parent)
+                    .iter()
+                    .position(|s| s.borrow().zid() == parent.zid())?;
+                Some((zid, grand, index + 1))
+            }
+            Move::Down => None,
+        }
+    }
+}
+
+impl Widgets<Model, AppModel> for Sequence {
+    type Root = gtk::Box;
+
+    fn init_view(
+        _model: &Model,
+        _components: &(),
+        sender: relm4::Sender<Msg>,
+    ) -> Self {
+        let store = TreeStore::new(&[Type::STRING, Type::STRING]);
+        let view = gtk::TreeView::builder()
+            .model(&store)
+            .headers_visible(false)
+            .vexpand(true)
+            .build();
+        let column = gtk::TreeViewColumn::new();
+        let cell = gtk::CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", 0);
+        view.append_column(&column);
+
+        let s = sender.clone();
+        view.selection().connect_changed(move |selection| {
+            if let Some((model, iter)) = selection.selected() {
+                let zid = model.get::<String>(&iter, 1);
+                send!(s, Msg::Selected(zid));
+            }
+        });
+
+        let actions = gtk::ActionBar::new();
+        for (icon, tooltip, m) in [
+            ("go-up-symbolic", "Move up", Move::Up),
+            ("go-down-symbolic", "Move down", Move::Down),
+            ("go-previous-symbolic", "Move out of the branch", Move::Out),
+            ("go-next-symbolic", "Move into the branch above", Move::In),
+        ] {
+            let button = gtk::Button::builder()
+                .icon_name(icon)
+                .tooltip_text(tooltip)
+                .build();
+            let s = sender.clone();
+            button.connect_clicked(move |_| send!(s, Msg::Move(m)));
+            actions.pack_start(&button);
+        }
+
+        let window = gtk::ScrolledWindow::builder()
+            .child(&view)
+            .vexpand(true)
+            .build();
+        let layout = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .width_request(200)
+            .build();
+        layout.append(&window);
+        layout.append(&actions);
+        Sequence {
+            layout,
+            store,
+            view,
+            version: 0,
+        }
+    }
+
+    fn root_widget(&self) -> Self::Root {
+        self.layout.clone()
+    }
+
+    fn view(&mut self, model: &Model, _sender: relm4::Sender<Msg>) {
+        if self.version == model.version {
+            return;
+        }
+        self.version = model.version;
+        self.store.clear();
+        let mut selected = None;
+        for node in model.tree.iter() {
+            self.append(None, node, model.selected.as_deref(), &mut selected);
+        }
+        self.view.expand_all();
+        if let Some(iter) = selected {
+            self.view.selection().select_iter(&iter);
+        }
+    }
+}
+
+impl Sequence {
+    fn append(
+        &self,
+        parent: Option<&TreeIter>,
+        node: &SequenceNode,
+        zid: Option<&str>,
+        selected: &mut Option<TreeIter>,
+    ) {
+        let z = node.zettel().borrow();
+        let label = match z.id().filter(|id| is_folgezettel(id)) {
+            Some(id) => format!("{} {}", id, z.title()),
+            None => z.title().to_string(),
+        };
+        let iter = self.store.insert_with_values(
+            parent,
+            None,
+            &[(0, &label), (1, &z.zid())],
+        );
+        if Some(z.zid()) == zid {
+            *selected = Some(iter);
+        }
+        for child in node.children() {
+            self.append(Some(&iter), child, zid, selected);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rustybrain_core::config::Config;
+use rustybrain_core::import::{self, Importer, Logseq, Obsidian, Roam};
+use rustybrain_core::kasten::Kasten;
+use rustybrain_core::zettel::Zettel;
+use serde::Serialize;
+
+/// Run without a subcommand to start the GUI.
+#[derive(Parser, Debug)]
+#[command(name = "rustybrain", version, about)]
+pub struct Cli {
+    /// Print results as JSON instead of plain text.
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a new note with the title.
+    New {
+        title: String,
+        /// Add the note as the last child of this note in its sequence.
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// Search notes, see the README for the query syntax.
+    Search { query: String },
+    /// List all notes.
+    List,
+    /// List notes that link to the note.
+    Backlinks { zid: String },
+    /// List notes that the note links to.
+    Links { zid: String },
+    /// List children of the note in its sequence.
+    Children { zid: String },
+    /// Rebuild the search index from the repo.
+    Reindex,
+    /// Report unreadable notes and broken links.
+    Check,
+    /// List revisions of the note.
+    History { zid: String },
+    /// Show changes of the note since the revision.
+    Diff { zid: String, rev: String },
+    /// Restore the note to the revision.
+    Restore { zid: String, rev: String },
+    /// Pull changes from the configured git remote.
+    Pull,
+    /// Push changes to the configured git remote.
+    Push,
+    /// Import notes from other tools.
+    Import {
+        #[arg(value_enum)]
+        from: Source,
+        /// Vault or graph directory, or the JSON file of Roam export.
+        path: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Source {
+    Obsidian,
+    Logseq,
+    Roam,
+}
+
+#[derive(Serialize)]
+struct Item {
+    zid: String,
+    title: String,
+    path: String,
+}
+
+impl From<&Zettel> for Item {
+    fn from(z: &Zettel) -> Self {
+        Item {
+            zid: z.zid().to_string(),
+            title: z.title().to_string(),
+            path: z.path().to_string_lossy().to_string(),
+        }
+    }
+}
+
+/// Run the command, the results are written to `out`.
+pub fn run(
+    command: Command,
+    json: bool,
+    config: Rc<RefCell<Config>>,
+    out: &mut dyn Write,
+) -> Result<(), anyhow::Error> {
+    let mut kasten = Kasten::new(config)?;
+    match command {
+        Command::New { title, parent } => {
+            let z = match parent {
+                Some(zid) => {
+                    let parent = find(&kasten, &zid)?;
+                    let parent = parent.borrow();
+                    kasten.create_in_sequence(&title, Some(&parent))?
+                }
+                None => kasten.create(&title)?,
+            };
+            let item = Item::from(&*z.borrow());
+            print_items(out, &[item], json)
+        }
+        Command::Search { query } => {
+            let items: Vec<Item> = kasten
+                .search(&query)?
+                .iter()
+                .map(|z| Item::from(&*z.borrow()))
+                .collect();
+            print_items(out, &items, json)
+        }
+        Command::List => {
+            let items: Vec<Item> =
+                kasten.iter().map(|z| Item::from(&*z.borrow())).collect();
+            print_items(out, &items, json)
+        }
+        Command::Backlinks { zid } => {
+            let z = find(&kasten, &zid)?;
+            let items: Vec<Item> = kasten
+                .iter_backlinks(&z.borrow())
+                .iter()
+                .map(|z| Item::from(&*z.borrow()))
+                .collect();
+            print_items(out, &items, json)
+        }
+        Command::Links { zid } => {
+            let z = find(&kasten, &zid)?;
+            let z = z.borrow();
+            let items: Vec<Item> = z
+                .link_to_iter()
+                .filter_map(|link| kasten.get(link))
+                .map(|z| Item::from(&*z.borrow()))
+                .collect();
+            print_items(out, &items, json)
+        }
+        Command::Children { zid } => {
+            let z = find(&kasten, &zid)?;
+            let items: Vec<Item> = kasten
+                .children(&z.borrow())
+                .iter()
+                .map(|z| Item::from(&*z.borrow()))
+                .collect();
+            print_items(out, &items, json)
+        }
+        Command::Reindex => {
+            kasten.reindex()?;
+            let count = kasten.iter().count();
+            if json {
+                writeln!(out, "{}", serde_json::json!({ "indexed": count }))?;
+            } else {
+                writeln!(out, "Indexed {} notes", count)?;
+            }
+            Ok(())
+        }
+        Command::Check => {
+            let problems = kasten.check();
+            if json {
+                let v: Vec<String> =
+                    problems.iter().map(|p| p.to_string()).collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&v)?)?;
+            } else {
+                for p in problems.iter() {
+                    writeln!(out, "{}", p)?;
+                }
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("{} problems found", problems.len()))
+            }
+        }
+        Command::History { zid } => {
+            let z = find(&kasten, &zid)?;
+            let revisions = kasten.history(&z.borrow())?;
+            if json {
+                let v: Vec<serde_json::Value> = revisions
+                    .iter()
+                    .map(|r| {
+                        serde_json::json!({
+                            "id": r.id(),
+                            "time": r.time().to_rfc3339(),
+                            "message": r.message(),
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&v)?)?;
+            } else {
+                for r in revisions.iter() {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}",
+                        r.short_id(),
+                        r.time().format("%Y-%m-%d %H:%M:%S"),
+                        r.message().trim()
+                    )?;
+                }
+            }
+            Ok(())
+        }
+        Command::Diff { zid, rev } => {
+            let z = find(&kasten, &zid)?;
+            let diff = kasten.diff(&z.borrow(), &rev)?;
+            write!(out, "{}", diff)?;
+            Ok(())
+        }
+        Command::Restore { zid, rev } => {
+            let z = find(&kasten, &zid)?;
+            let z = z.borrow().clone();
+            kasten.restore(&z, &rev)
+        }
+        Command::Pull => kasten.pull(),
+        Command::Push => kasten.push(),
+        Command::Import { from, path } => {
+            let importer: Box<dyn Importer> = match from {
+                Source::Obsidian => Box::new(Obsidian::new(&path)),
+                Source::Logseq => Box::new(Logseq::new(&path)),
+                Source::Roam => Box::new(Roam::new(&path)),
+            };
+            let report = import::import(&mut kasten, importer.as_ref())?;
+            if json {
+                let v: Vec<serde_json::Value> = report
+                    .entries()
+                    .iter()
+                    .map(|e| {
+                        serde_json::json!({
+                            "source": e.source(),
+                            "zid": e.zid(),
+                            "title": e.title(),
+                        })
+                    })
+                    .collect();
+                writeln!(out, "{}", serde_json::to_string_pretty(&v)?)?;
+            } else {
+                writeln!(out, "{}", report)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn find(
+    kasten: &Kasten,
+    zid: &str,
+) -> Result<Rc<RefCell<Zettel>>, anyhow::Error> {
+    kasten
+        .get(zid)
+        .ok_or_else(|| anyhow::anyhow!("note {} not found", zid))
+}
+
+fn print_items(
+    out: &mut dyn Write,
+    items: &[Item],
+    json: bool,
+) -> Result<(), anyhow::Error> {
+    if json {
+        writeln!(out, "{}", serde_json::to_string_pretty(items)?)?;
+    } else {
+        for item in items {
+            writeln!(out, "{}\t{}", item.zid, item.title)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use rustybrain_core::config::Config;
+
+    use super::{run, Command};
+
+    fn config(dir: &Path) -> Rc<RefCell<Config>> {
+        let config = format!(
+            "[repo]\npath = {:?}\n\
+             [shortcut]\nfind = \"<Control>p\"\ninsert = \"<Control>i\"\n\
+             quit = \"<Control>q\"\n",
+            dir.to_str().unwrap(),
+        );
+        Rc::new(RefCell::new(config.parse().unwrap()))
+    }
+
+    fn write(dir: &Path, name: &str, body: &str) {
+        let md = format!("+++\ntitle = \"{}\"\n+++\n{}\n", name, body);
+        fs::write(dir.join(format!("{}.md", name)), md).unwrap();
+    }
+
+    /// Result and output of the command.
+    fn output(
+        dir: &Path,
+        command: Command,
+        json: bool,
+    ) -> (Result<(), anyhow::Error>, String) {
+        let mut out = vec![];
+        let r = run(command, json, config(dir), &mut out);
+        (r, String::from_utf8(out).unwrap())
+    }
+
+    fn lines(dir: &Path, command: Command) -> Vec<String> {
+        let (r, out) = output(dir, command, false);
+        r.unwrap();
+        let mut lines: Vec<String> = out.lines().map(String::from).collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_list_search_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a", "Links to [B](@/b.md).");
+        write(dir.path(), "b", "Nothing here.");
+
+        assert_eq!(
+            lines(dir.path(), Command::List),
+            ["@/a.md\ta", "@/b.md\tb"]
+        );
+        let search = Command::Search {
+            query: "nothing".to_string(),
+        };
+        assert_eq!(lines(dir.path(), search), ["@/b.md\tb"]);
+        let backlinks = Command::Backlinks {
+            zid: "b.md".to_string(),
+        };
+        assert_eq!(lines(dir.path(), backlinks), ["@/a.md\ta"]);
+        let links = Command::Links {
+            zid: "a.md".to_string(),
+        };
+        assert_eq!(lines(dir.path(), links), ["@/b.md\tb"]);
+        assert_eq!(lines(dir.path(), Command::Reindex), ["Indexed 2 notes"]);
+
+        let (r, out) = output(dir.path(), Command::List, true);
+        r.unwrap();
+        let items: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let mut zids: Vec<&str> = items
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| i["zid"].as_str().unwrap())
+            .collect();
+        zids.sort_unstable();
+        assert_eq!(zids, ["@/a.md", "@/b.md"]);
+
+        let missing = Command::Links {
+            zid: "c.md".to_string(),
+        };
+        assert!(output(dir.path(), missing, false).0.is_err());
+    }
+
+    #[test]
+    fn test_new_and_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let new = Command::New {
+            title: "Hello".to_string(),
+            parent: None,
+        };
+        let created = lines(dir.path(), new);
+        assert_eq!(created.len(), 1);
+        assert!(created[0].ends_with("\tHello"));
+        assert_eq!(lines(dir.path(), Command::List), created);
+
+        let (r, out) = output(dir.path(), Command::Check, false);
+        r.unwrap();
+        assert_eq!(out, "");
+        write(dir.path(), "a", "[X](@/x.md)");
+        let (r, out) = output(dir.path(), Command::Check, false);
+        assert_eq!(r.unwrap_err().to_string(), "1 problems found");
+        assert_eq!(out, "@/a.md: broken link to @/x.md\n");
+    }
+}
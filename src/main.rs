@@ -1,5 +1,8 @@
+mod cli;
+
 use std::{cell::RefCell, rc::Rc};
 
+use clap::Parser;
 use rustybrain_core::config::ConfigLoader;
 use rustybrain_gtk::run;
 
@@ -8,7 +11,15 @@ fn main() -> Result<(), anyhow::Error> {
         std::env::set_var("RUST_LIB_BACKTRACE", "1")
     }
     color_eyre::install().unwrap();
-    let config = ConfigLoader::new().load()?;
-    run(Rc::new(RefCell::new(config)));
-    Ok(())
+    let args = cli::Cli::parse();
+    let config = Rc::new(RefCell::new(ConfigLoader::new().load()?));
+    match args.command {
+        Some(command) => {
+            cli::run(command, args.json, config, &mut std::io::stdout())
+        }
+        None => {
+            run(config);
+            Ok(())
+        }
+    }
 }